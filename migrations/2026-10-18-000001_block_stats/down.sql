@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS block_script_type_stats;
+DROP TABLE IF EXISTS block_stats;
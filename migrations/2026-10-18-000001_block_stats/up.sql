@@ -0,0 +1,25 @@
+-- Per-block aggregate statistics, computed by the processor while a block is stored
+
+-- Block level totals
+CREATE TABLE block_stats (
+    block_height INTEGER PRIMARY KEY REFERENCES blocks(block_height),
+    new_address_count INTEGER NOT NULL DEFAULT 0, -- Addresses first seen in this block
+    reused_address_count INTEGER NOT NULL DEFAULT 0, -- Distinct existing addresses receiving again
+    newly_exposed_public_key_count INTEGER NOT NULL DEFAULT 0,
+    newly_exposed_value_satoshis BIGINT NOT NULL DEFAULT 0, -- Unspent value at addresses whose key was first exposed
+    coinbase_value_satoshis BIGINT NOT NULL DEFAULT 0
+);
+
+-- Output and input totals per script type
+CREATE TABLE block_script_type_stats (
+    block_height INTEGER NOT NULL REFERENCES blocks(block_height),
+    script_type VARCHAR(20) NOT NULL REFERENCES script_types(script_type),
+    output_count INTEGER NOT NULL DEFAULT 0,
+    output_value_satoshis BIGINT NOT NULL DEFAULT 0,
+    input_count INTEGER NOT NULL DEFAULT 0, -- Inputs spending outputs of this script type
+    input_value_satoshis BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (block_height, script_type)
+);
+
+-- Index for script type time series
+CREATE INDEX idx_block_script_type_stats_type ON block_script_type_stats(script_type, block_height);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_addresses_public_key_exposed_height;
+ALTER TABLE addresses DROP COLUMN IF EXISTS public_key_exposed_height;
@@ -0,0 +1,18 @@
+-- Track the block height at which an address's public key was first exposed
+
+ALTER TABLE addresses ADD COLUMN public_key_exposed_height INTEGER;
+
+-- Backfill from the earliest input that revealed a public key for the address
+UPDATE addresses a
+SET public_key_exposed_height = revealed.block_height
+FROM (
+    SELECT address_id, MIN(block_height) AS block_height
+    FROM address_inputs
+    WHERE public_key_revealed IS NOT NULL
+    GROUP BY address_id
+) revealed
+WHERE a.address_id = revealed.address_id
+  AND a.is_public_key_exposed = TRUE;
+
+-- Index for exposure era lookups
+CREATE INDEX idx_addresses_public_key_exposed_height ON addresses(public_key_exposed_height) WHERE public_key_exposed_height IS NOT NULL;
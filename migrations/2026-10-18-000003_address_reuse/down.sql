@@ -0,0 +1,5 @@
+ALTER TABLE block_script_type_stats DROP COLUMN IF EXISTS post_exposure_value_satoshis;
+ALTER TABLE block_script_type_stats DROP COLUMN IF EXISTS post_exposure_output_count;
+ALTER TABLE block_script_type_stats DROP COLUMN IF EXISTS reused_output_count;
+DROP INDEX IF EXISTS idx_address_outputs_after_exposure;
+ALTER TABLE address_outputs DROP COLUMN IF EXISTS received_after_exposure;
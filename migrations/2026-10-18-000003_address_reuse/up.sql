@@ -0,0 +1,50 @@
+-- Address reuse analytics
+
+-- Flag outputs paid to an address whose public key was already exposed
+ALTER TABLE address_outputs ADD COLUMN received_after_exposure BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Backfill for addresses exposed by a spend. Outputs in the exposing block itself are not flagged.
+UPDATE address_outputs o
+SET received_after_exposure = TRUE
+FROM addresses a
+WHERE o.address_id = a.address_id
+  AND a.public_key_exposed_height IS NOT NULL
+  AND o.block_height > a.public_key_exposed_height;
+
+-- Backfill for P2PK and P2TR, which expose their key with the first output to the address
+UPDATE address_outputs o
+SET received_after_exposure = TRUE
+FROM addresses a
+WHERE o.address_id = a.address_id
+  AND a.script_type IN ('p2pk', 'p2tr')
+  AND o.output_id > (SELECT MIN(first.output_id) FROM address_outputs first WHERE first.address_id = a.address_id);
+
+-- Index for finding addresses that received after exposure
+CREATE INDEX idx_address_outputs_after_exposure ON address_outputs(address_id) WHERE received_after_exposure = TRUE;
+
+-- Per-block reuse counts per script type
+ALTER TABLE block_script_type_stats ADD COLUMN reused_output_count INTEGER NOT NULL DEFAULT 0; -- Outputs to an existing address
+ALTER TABLE block_script_type_stats ADD COLUMN post_exposure_output_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE block_script_type_stats ADD COLUMN post_exposure_value_satoshis BIGINT NOT NULL DEFAULT 0;
+
+-- Backfill the per-block counts from the outputs
+UPDATE block_script_type_stats s
+SET reused_output_count = counts.reused_output_count,
+    post_exposure_output_count = counts.post_exposure_output_count,
+    post_exposure_value_satoshis = counts.post_exposure_value_satoshis
+FROM (
+    SELECT
+        o.block_height,
+        a.script_type,
+        COUNT(*) FILTER (WHERE o.output_id > first_output.output_id) AS reused_output_count,
+        COUNT(*) FILTER (WHERE o.received_after_exposure) AS post_exposure_output_count,
+        COALESCE(SUM(o.value_satoshis) FILTER (WHERE o.received_after_exposure), 0) AS post_exposure_value_satoshis
+    FROM address_outputs o
+    JOIN addresses a ON a.address_id = o.address_id
+    JOIN (
+        SELECT address_id, MIN(output_id) AS output_id FROM address_outputs GROUP BY address_id
+    ) first_output ON first_output.address_id = o.address_id
+    GROUP BY o.block_height, a.script_type
+) counts
+WHERE s.block_height = counts.block_height
+  AND s.script_type = counts.script_type;
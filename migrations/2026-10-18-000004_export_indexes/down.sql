@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_address_inputs_block_height;
+DROP INDEX IF EXISTS idx_address_outputs_block_height;
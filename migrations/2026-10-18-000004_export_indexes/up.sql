@@ -0,0 +1,3 @@
+-- Indexes for reading the large tables by block height range (e.g. partitioned exports)
+CREATE INDEX idx_address_outputs_block_height ON address_outputs(block_height);
+CREATE INDEX idx_address_inputs_block_height ON address_inputs(block_height);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS multisig_keys;
+DROP TABLE IF EXISTS multisig_scripts;
@@ -0,0 +1,35 @@
+-- Bare multisig (P2MS) scripts and the public keys they embed
+
+-- Signature threshold of each P2MS address
+CREATE TABLE multisig_scripts (
+    address_id BIGINT PRIMARY KEY REFERENCES addresses(address_id),
+    required_signatures SMALLINT NOT NULL, -- m
+    total_keys SMALLINT NOT NULL, -- n
+    CHECK (required_signatures BETWEEN 1 AND total_keys AND total_keys <= 20)
+);
+
+-- Public keys of each P2MS address, in script order
+CREATE TABLE multisig_keys (
+    address_id BIGINT NOT NULL REFERENCES multisig_scripts(address_id),
+    key_index SMALLINT NOT NULL,
+    public_key BYTEA NOT NULL,
+    PRIMARY KEY (address_id, key_index)
+);
+
+-- Index for finding every multisig a public key takes part in
+CREATE INDEX idx_multisig_keys_public_key ON multisig_keys(public_key);
+
+-- The keys of a P2MS script are exposed as soon as the output is created
+UPDATE addresses
+SET is_public_key_exposed = TRUE,
+    public_key_exposed_height = first_seen_block_height
+WHERE script_type = 'p2ms';
+
+-- Backfill the threshold of existing P2MS addresses from their extra data.
+-- Their keys were never stored and can only be recovered by reprocessing their blocks.
+INSERT INTO multisig_scripts (address_id, required_signatures, total_keys)
+SELECT address_id, (script_extra_data->>'m')::SMALLINT, (script_extra_data->>'n')::SMALLINT
+FROM addresses
+WHERE script_type = 'p2ms'
+  AND script_extra_data ? 'm'
+  AND script_extra_data ? 'n';
@@ -0,0 +1 @@
+ALTER TABLE addresses DROP COLUMN IF EXISTS public_key_exposure_source;
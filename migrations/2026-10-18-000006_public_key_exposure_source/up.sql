@@ -0,0 +1,23 @@
+-- Record how an address's public key was exposed: by its own output script (P2PK, P2MS)
+-- or by a spend revealing it in the scriptSig
+
+ALTER TABLE addresses ADD COLUMN public_key_exposure_source VARCHAR(20)
+    CHECK (public_key_exposure_source IN ('output_script', 'spend'));
+
+-- The address string of a P2PK address is the hex of its public key, which is exposed
+-- as soon as the output is created
+UPDATE addresses
+SET public_key = decode(address_string, 'hex'),
+    is_public_key_exposed = TRUE,
+    public_key_exposed_height = first_seen_block_height,
+    public_key_exposure_source = 'output_script'
+WHERE script_type = 'p2pk';
+
+UPDATE addresses
+SET public_key_exposure_source = 'output_script'
+WHERE script_type = 'p2ms' AND is_public_key_exposed = TRUE;
+
+-- Every other exposed address was exposed by a spend
+UPDATE addresses
+SET public_key_exposure_source = 'spend'
+WHERE is_public_key_exposed = TRUE AND public_key_exposure_source IS NULL;
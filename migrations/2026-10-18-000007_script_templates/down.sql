@@ -0,0 +1,2 @@
+ALTER TABLE addresses DROP COLUMN IF EXISTS script_template_hash;
+DROP TABLE IF EXISTS script_templates;
@@ -0,0 +1,66 @@
+-- Templates of non-standard and unknown output scripts: the opcodes with the size of each
+-- data push, the data itself abstracted away, e.g. "OP_RETURN PUSH(4 bytes)"
+
+CREATE TABLE script_templates (
+    template_hash BYTEA PRIMARY KEY, -- SHA256 of the template text
+    template TEXT NOT NULL,
+    script_type VARCHAR(20) NOT NULL REFERENCES script_types(script_type),
+    occurrence_count BIGINT NOT NULL DEFAULT 0, -- Outputs with a script of this template
+    first_seen_block_height INTEGER NOT NULL,
+    last_seen_block_height INTEGER NOT NULL,
+    example_txids BYTEA[] NOT NULL DEFAULT '{}' -- The first few transactions seen using it
+);
+
+-- Index for listing the templates of a script type by popularity
+CREATE INDEX idx_script_templates_type_count ON script_templates(script_type, occurrence_count DESC);
+
+ALTER TABLE addresses ADD COLUMN script_template_hash BYTEA REFERENCES script_templates(template_hash);
+
+-- Backfill the template of existing non-standard and unknown addresses from the script
+-- operations stored in their extra data
+CREATE TEMPORARY TABLE address_templates AS
+SELECT
+    address_id,
+    script_type,
+    CASE script_extra_data->>'pattern'
+        WHEN 'p2pkh-plus' THEN 'OP_DUP OP_HASH160 PUSH(20 bytes) OP_EQUALVERIFY OP_CHECKSIG '
+            || (SELECT string_agg(op, ' ' ORDER BY ord)
+                FROM jsonb_array_elements_text(script_extra_data->'extra_ops') WITH ORDINALITY AS ops(op, ord))
+        WHEN 'hash160-found' THEN
+            (SELECT string_agg(op, ' ' ORDER BY ord)
+             FROM jsonb_array_elements_text(script_extra_data->'script_ops') WITH ORDINALITY AS ops(op, ord))
+        ELSE
+            (SELECT string_agg(op, ' ' ORDER BY ord)
+             FROM jsonb_array_elements_text(script_extra_data->'script_pattern') WITH ORDINALITY AS ops(op, ord))
+    END AS template
+FROM addresses
+WHERE script_type IN ('non-standard', 'unknown');
+
+DELETE FROM address_templates WHERE template IS NULL;
+
+INSERT INTO script_templates (
+    template_hash, template, script_type, occurrence_count,
+    first_seen_block_height, last_seen_block_height, example_txids
+)
+SELECT
+    sha256(convert_to(t.template, 'UTF8')),
+    t.template,
+    MIN(t.script_type),
+    COUNT(o.output_id),
+    MIN(o.block_height),
+    MAX(o.block_height),
+    (ARRAY_AGG(o.transaction_id ORDER BY o.block_height, o.output_id))[1:5]
+FROM address_templates t
+JOIN address_outputs o ON o.address_id = t.address_id
+GROUP BY t.template;
+
+UPDATE addresses a
+SET script_template_hash = sha256(convert_to(t.template, 'UTF8'))
+FROM address_templates t
+WHERE a.address_id = t.address_id
+  AND EXISTS (SELECT 1 FROM script_templates s WHERE s.template_hash = sha256(convert_to(t.template, 'UTF8')));
+
+DROP TABLE address_templates;
+
+-- Index for grouping addresses by template
+CREATE INDEX idx_addresses_script_template_hash ON addresses(script_template_hash) WHERE script_template_hash IS NOT NULL;
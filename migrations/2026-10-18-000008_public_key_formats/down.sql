@@ -0,0 +1,3 @@
+ALTER TABLE multisig_keys DROP COLUMN IF EXISTS public_key_format;
+ALTER TABLE address_inputs DROP COLUMN IF EXISTS public_key_revealed_format;
+ALTER TABLE addresses DROP COLUMN IF EXISTS public_key_format;
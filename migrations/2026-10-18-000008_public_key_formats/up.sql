@@ -0,0 +1,126 @@
+-- Format of stored public keys: compressed, uncompressed, hybrid (0x06/0x07 prefix), or
+-- invalid for pushes that are not points on the curve (usually data embedded in fake keys).
+-- Invalid keys do not count as exposed public keys.
+
+-- The format of a key from its prefix and length alone
+CREATE FUNCTION pg_temp.key_prefix_format(key BYTEA) RETURNS VARCHAR(12) AS $$
+    SELECT CASE
+        WHEN length(key) = 33 AND get_byte(key, 0) IN (2, 3) THEN 'compressed'
+        WHEN length(key) = 65 AND get_byte(key, 0) = 4 THEN 'uncompressed'
+        WHEN length(key) = 65 AND get_byte(key, 0) IN (6, 7) THEN 'hybrid'
+        ELSE 'invalid'
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Modular exponentiation by squaring, for the curve check below
+CREATE FUNCTION pg_temp.mod_pow(base NUMERIC, exponent NUMERIC, modulus NUMERIC) RETURNS NUMERIC AS $$
+DECLARE
+    result NUMERIC := 1;
+BEGIN
+    base := mod(base, modulus);
+    WHILE exponent > 0 LOOP
+        IF mod(exponent, 2) = 1 THEN
+            result := mod(result * base, modulus);
+        END IF;
+        base := mod(base * base, modulus);
+        exponent := div(exponent, 2);
+    END LOOP;
+    RETURN result;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- The format of a key, checking that it is a point on secp256k1 (y^2 = x^3 + 7 mod p) the way
+-- libsecp256k1 parses keys: coordinates below p, and the parity of y for hybrid keys
+CREATE FUNCTION pg_temp.public_key_format(key BYTEA) RETURNS VARCHAR(12) AS $$
+DECLARE
+    p CONSTANT NUMERIC := 115792089237316195423570985008687907853269984665640564039457584007908834671663;
+    format VARCHAR(12);
+    x NUMERIC := 0;
+    y NUMERIC := 0;
+    x_cubed_plus_7 NUMERIC;
+BEGIN
+    format := pg_temp.key_prefix_format(key);
+    IF format = 'invalid' THEN
+        RETURN format;
+    END IF;
+
+    FOR i IN 1..32 LOOP
+        x := x * 256 + get_byte(key, i);
+    END LOOP;
+    IF x >= p THEN
+        RETURN 'invalid';
+    END IF;
+    x_cubed_plus_7 := mod(x * x * x + 7, p);
+
+    -- A compressed key only holds x, which is on the curve if x^3 + 7 is a square mod p
+    -- (Euler's criterion)
+    IF format = 'compressed' THEN
+        IF pg_temp.mod_pow(x_cubed_plus_7, div(p - 1, 2), p) <> 1 THEN
+            RETURN 'invalid';
+        END IF;
+        RETURN format;
+    END IF;
+
+    FOR i IN 33..64 LOOP
+        y := y * 256 + get_byte(key, i);
+    END LOOP;
+    IF y >= p OR mod(y * y, p) <> x_cubed_plus_7 THEN
+        RETURN 'invalid';
+    END IF;
+    -- The prefix of a hybrid key also encodes the parity of y
+    IF format = 'hybrid' AND mod(y, 2) <> get_byte(key, 0) - 6 THEN
+        RETURN 'invalid';
+    END IF;
+    RETURN format;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+ALTER TABLE addresses ADD COLUMN public_key_format VARCHAR(12)
+    CHECK (public_key_format IN ('compressed', 'uncompressed', 'hybrid', 'invalid'));
+ALTER TABLE address_inputs ADD COLUMN public_key_revealed_format VARCHAR(12)
+    CHECK (public_key_revealed_format IN ('compressed', 'uncompressed', 'hybrid', 'invalid'));
+ALTER TABLE multisig_keys ADD COLUMN public_key_format VARCHAR(12)
+    CHECK (public_key_format IN ('compressed', 'uncompressed', 'hybrid', 'invalid'));
+
+-- Backfill, checking that the keys are on the curve. Keys revealed spending P2PKH outputs passed
+-- OP_CHECKSIG, which fails for keys off the curve, so only their prefix is read.
+UPDATE addresses
+SET public_key_format = CASE
+    WHEN script_type = 'p2pkh' AND public_key_exposure_source = 'spend'
+        THEN pg_temp.key_prefix_format(public_key)
+    ELSE pg_temp.public_key_format(public_key)
+END
+WHERE public_key IS NOT NULL;
+
+UPDATE address_inputs i
+SET public_key_revealed_format = CASE
+    WHEN a.script_type = 'p2pkh' THEN pg_temp.key_prefix_format(i.public_key_revealed)
+    ELSE pg_temp.public_key_format(i.public_key_revealed)
+END
+FROM addresses a
+WHERE a.address_id = i.address_id
+  AND i.public_key_revealed IS NOT NULL;
+
+UPDATE multisig_keys
+SET public_key_format = pg_temp.public_key_format(public_key);
+
+ALTER TABLE multisig_keys ALTER COLUMN public_key_format SET NOT NULL;
+
+-- Addresses whose only key is invalid are not exposed
+UPDATE addresses
+SET is_public_key_exposed = FALSE,
+    public_key_exposed_height = NULL,
+    public_key_exposure_source = NULL
+WHERE public_key_format = 'invalid';
+
+-- Bare multisig is only exposed if at least one of its keys is valid
+UPDATE addresses a
+SET is_public_key_exposed = FALSE,
+    public_key_exposed_height = NULL,
+    public_key_exposure_source = NULL
+WHERE a.script_type = 'p2ms'
+  AND EXISTS (SELECT 1 FROM multisig_keys k WHERE k.address_id = a.address_id)
+  AND NOT EXISTS (
+      SELECT 1 FROM multisig_keys k
+      WHERE k.address_id = a.address_id AND k.public_key_format <> 'invalid'
+  );
@@ -0,0 +1,24 @@
+-- ECDSA signatures pushed by inputs, for auditing signature properties and detecting
+-- nonce (R value) reuse
+
+CREATE TABLE signatures (
+    signature_id BIGSERIAL PRIMARY KEY,
+    input_id BIGINT NOT NULL REFERENCES address_inputs(input_id),
+    block_height INTEGER NOT NULL,
+    signature_index SMALLINT NOT NULL, -- Position among the signatures of the input
+    in_witness BOOLEAN NOT NULL, -- Found in the witness rather than the scriptSig
+    public_key BYTEA, -- The key it was paired with, when the input makes that unambiguous
+    r_value BYTEA NOT NULL, -- 32 bytes, big endian
+    s_value BYTEA NOT NULL, -- 32 bytes, big endian
+    sighash_type SMALLINT NOT NULL,
+    is_low_r BOOLEAN NOT NULL,
+    is_low_s BOOLEAN NOT NULL,
+    is_strict_der BOOLEAN NOT NULL,
+    UNIQUE (input_id, signature_index)
+);
+
+-- Index for finding signatures sharing an R value
+CREATE INDEX idx_signatures_r_value ON signatures(r_value);
+
+-- Index for signature property time series
+CREATE INDEX idx_signatures_block_height ON signatures(block_height);
@@ -0,0 +1 @@
+ALTER TABLE address_outputs DROP COLUMN IF EXISTS is_unspendable;
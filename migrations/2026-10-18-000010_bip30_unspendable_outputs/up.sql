@@ -0,0 +1,42 @@
+-- Before BIP30 two coinbase transactions were each included twice (at heights 91722/91880
+-- and 91812/91842). The later copy overwrote the outputs of the earlier one in the UTXO set,
+-- so the earlier outputs can never be spent.
+
+ALTER TABLE address_outputs ADD COLUMN is_unspendable BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TEMPORARY TABLE overwritten_outputs AS
+SELECT
+    old.output_id AS old_output_id,
+    live.output_id AS live_output_id,
+    old.spending_input_id
+FROM (
+    SELECT transaction_id, MAX(block_height) AS live_height
+    FROM txid_block_index
+    GROUP BY transaction_id
+    HAVING COUNT(*) > 1
+) dup
+JOIN address_outputs old
+  ON old.transaction_id = dup.transaction_id AND old.block_height < dup.live_height
+LEFT JOIN address_outputs live
+  ON live.transaction_id = dup.transaction_id
+ AND live.block_height = dup.live_height
+ AND live.output_index = old.output_index;
+
+-- Spends previously linked to an overwritten output actually spent the later copy
+UPDATE address_inputs i
+SET spent_output_id = o.live_output_id
+FROM overwritten_outputs o
+WHERE i.input_id = o.spending_input_id AND o.live_output_id IS NOT NULL;
+
+UPDATE address_outputs a
+SET is_spent = TRUE, spending_input_id = o.spending_input_id
+FROM overwritten_outputs o
+WHERE a.output_id = o.live_output_id AND o.spending_input_id IS NOT NULL AND a.is_spent = FALSE;
+
+UPDATE address_outputs a
+SET is_unspendable = TRUE, is_spent = FALSE, spending_input_id = NULL
+FROM overwritten_outputs o
+WHERE a.output_id = o.old_output_id
+  AND (o.live_output_id IS NOT NULL OR o.spending_input_id IS NULL);
+
+DROP TABLE overwritten_outputs;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS block_miners;
+DROP TABLE IF EXISTS coinbase_transactions;
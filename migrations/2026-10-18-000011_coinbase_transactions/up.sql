@@ -0,0 +1,25 @@
+-- Data extracted from the coinbase transaction of each block, and the miner it was attributed to
+
+CREATE TABLE coinbase_transactions (
+    block_height INTEGER PRIMARY KEY REFERENCES blocks(block_height),
+    transaction_id BYTEA NOT NULL,
+    script_sig BYTEA NOT NULL,
+    bip34_height INTEGER, -- Height pushed first in the scriptSig, for version 2+ blocks
+    ascii_tags TEXT[] NOT NULL DEFAULT '{}', -- Runs of printable ASCII in the scriptSig
+    extranonce BYTEA,
+    has_witness_commitment BOOLEAN NOT NULL DEFAULT FALSE,
+    payout_addresses TEXT[] NOT NULL DEFAULT '{}', -- Addresses of the outputs paying the reward
+    output_value_satoshis BIGINT NOT NULL, -- Total reward claimed: subsidy plus fees
+    subsidy_satoshis BIGINT NOT NULL -- Subsidy the miner was entitled to at this height
+);
+
+-- Blocks attributed to a configured miner or pool
+CREATE TABLE block_miners (
+    block_height INTEGER PRIMARY KEY REFERENCES blocks(block_height),
+    miner_name VARCHAR(100) NOT NULL,
+    matched_by VARCHAR(20) NOT NULL, -- 'payout_address' or 'tag'
+    matched_value TEXT NOT NULL -- The address or tag that identified the miner
+);
+
+-- Index for miner share time series
+CREATE INDEX idx_block_miners_name ON block_miners(miner_name, block_height);
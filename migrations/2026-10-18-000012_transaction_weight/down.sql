@@ -0,0 +1,13 @@
+DROP VIEW IF EXISTS daily_segwit_adoption;
+DROP VIEW IF EXISTS block_segwit_adoption;
+
+DROP INDEX IF EXISTS idx_transactions_block_height;
+
+ALTER TABLE transactions
+    DROP COLUMN IF EXISTS base_size,
+    DROP COLUMN IF EXISTS total_size,
+    DROP COLUMN IF EXISTS witness_size,
+    DROP COLUMN IF EXISTS weight,
+    DROP COLUMN IF EXISTS vsize,
+    DROP COLUMN IF EXISTS segwit_input_count,
+    DROP COLUMN IF EXISTS taproot_input_count;
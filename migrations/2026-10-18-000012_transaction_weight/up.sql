@@ -0,0 +1,53 @@
+-- Serialized size and weight of each transaction, and the SegWit and Taproot inputs it spends.
+-- Transactions stored before this migration keep NULL sizes and counts, as the processor does
+-- not go back over stored blocks, and are left out of the adoption views.
+
+ALTER TABLE transactions
+    ADD COLUMN base_size INTEGER, -- Bytes without witness data (stripped size)
+    ADD COLUMN total_size INTEGER, -- Bytes including witness data
+    ADD COLUMN witness_size INTEGER, -- total_size - base_size: marker, flag and witnesses
+    ADD COLUMN weight INTEGER, -- Weight units (BIP141): base_size * 3 + total_size
+    ADD COLUMN vsize INTEGER, -- Virtual size: weight / 4, rounded up
+    ADD COLUMN segwit_input_count INTEGER, -- Inputs with witness data (native or P2SH wrapped SegWit, Taproot)
+    ADD COLUMN taproot_input_count INTEGER; -- Inputs spending P2TR outputs
+
+-- Index for per-block aggregates of transactions
+CREATE INDEX idx_transactions_block_height ON transactions(block_height);
+
+-- SegWit and Taproot adoption per block. Coinbase transactions are left out as they spend no outputs.
+CREATE VIEW block_segwit_adoption AS
+SELECT
+    block_height,
+    COUNT(*)::BIGINT AS transaction_count,
+    COUNT(*) FILTER (WHERE segwit_input_count > 0)::BIGINT AS segwit_spending_count,
+    COUNT(*) FILTER (WHERE taproot_input_count > 0)::BIGINT AS taproot_spending_count,
+    SUM(input_count)::BIGINT AS input_count,
+    SUM(segwit_input_count)::BIGINT AS segwit_input_count,
+    SUM(taproot_input_count)::BIGINT AS taproot_input_count,
+    SUM(total_size)::BIGINT AS total_size,
+    SUM(witness_size)::BIGINT AS witness_size,
+    SUM(weight)::BIGINT AS weight,
+    SUM(vsize)::BIGINT AS vsize
+FROM transactions
+WHERE NOT is_coinbase
+  AND weight IS NOT NULL
+GROUP BY block_height;
+
+-- The same per UTC day of block timestamps
+CREATE VIEW daily_segwit_adoption AS
+SELECT
+    b.block_timestamp::DATE AS day,
+    COUNT(DISTINCT a.block_height)::BIGINT AS block_count,
+    SUM(a.transaction_count)::BIGINT AS transaction_count,
+    SUM(a.segwit_spending_count)::BIGINT AS segwit_spending_count,
+    SUM(a.taproot_spending_count)::BIGINT AS taproot_spending_count,
+    SUM(a.input_count)::BIGINT AS input_count,
+    SUM(a.segwit_input_count)::BIGINT AS segwit_input_count,
+    SUM(a.taproot_input_count)::BIGINT AS taproot_input_count,
+    SUM(a.total_size)::BIGINT AS total_size,
+    SUM(a.witness_size)::BIGINT AS witness_size,
+    SUM(a.weight)::BIGINT AS weight,
+    SUM(a.vsize)::BIGINT AS vsize
+FROM block_segwit_adoption a
+JOIN blocks b ON b.block_height = a.block_height
+GROUP BY b.block_timestamp::DATE;
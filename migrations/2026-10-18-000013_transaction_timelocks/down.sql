@@ -0,0 +1,14 @@
+DROP VIEW IF EXISTS daily_timelock_usage;
+
+ALTER TABLE address_inputs
+    DROP COLUMN IF EXISTS sequence,
+    DROP COLUMN IF EXISTS relative_lock_type,
+    DROP COLUMN IF EXISTS relative_lock_value;
+
+ALTER TABLE transactions
+    DROP COLUMN IF EXISTS version,
+    DROP COLUMN IF EXISTS lock_time,
+    DROP COLUMN IF EXISTS lock_time_type,
+    DROP COLUMN IF EXISTS is_lock_time_enforced,
+    DROP COLUMN IF EXISTS is_anti_fee_sniping,
+    DROP COLUMN IF EXISTS signals_rbf;
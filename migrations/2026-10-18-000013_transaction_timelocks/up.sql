@@ -0,0 +1,53 @@
+-- Version, locktime and RBF metadata of transactions, and the sequence and BIP68 relative
+-- timelock of inputs. Rows stored before this migration keep NULL values, as the processor
+-- does not go back over stored blocks, and are left out of the usage view.
+
+ALTER TABLE transactions
+    ADD COLUMN version INTEGER,
+    ADD COLUMN lock_time BIGINT, -- nLockTime, an unsigned 32 bit value
+    ADD COLUMN lock_time_type VARCHAR(10), -- 'none', 'height' or 'time'
+    ADD COLUMN is_lock_time_enforced BOOLEAN, -- Some input has a non-final sequence, otherwise the locktime is ignored
+    ADD COLUMN is_anti_fee_sniping BOOLEAN, -- Height locktime within 100 blocks below the block it was mined in
+    ADD COLUMN signals_rbf BOOLEAN; -- BIP125: some input has a sequence below 0xfffffffe
+
+ALTER TABLE address_inputs
+    ADD COLUMN sequence BIGINT, -- nSequence, an unsigned 32 bit value
+    ADD COLUMN relative_lock_type VARCHAR(10), -- BIP68: 'blocks' or 'time', NULL if no relative timelock
+    ADD COLUMN relative_lock_value INTEGER; -- Blocks, or 512 second intervals
+
+-- Timelock and RBF usage per UTC day of block timestamps. Coinbase transactions are left out.
+CREATE VIEW daily_timelock_usage AS
+WITH daily_transactions AS (
+    SELECT
+        b.block_timestamp::DATE AS day,
+        COUNT(*)::BIGINT AS transaction_count,
+        COUNT(*) FILTER (WHERE t.version = 1)::BIGINT AS version_1_count,
+        COUNT(*) FILTER (WHERE t.version = 2)::BIGINT AS version_2_count,
+        COUNT(*) FILTER (WHERE t.version NOT IN (1, 2))::BIGINT AS other_version_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type = 'height')::BIGINT AS height_lock_time_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type = 'time')::BIGINT AS time_lock_time_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type <> 'none' AND NOT t.is_lock_time_enforced)::BIGINT AS ignored_lock_time_count,
+        COUNT(*) FILTER (WHERE t.is_anti_fee_sniping)::BIGINT AS anti_fee_sniping_count,
+        COUNT(*) FILTER (WHERE t.signals_rbf)::BIGINT AS rbf_signalling_count
+    FROM transactions t
+    JOIN blocks b ON b.block_height = t.block_height
+    WHERE NOT t.is_coinbase
+      AND t.version IS NOT NULL
+    GROUP BY b.block_timestamp::DATE
+),
+daily_inputs AS (
+    SELECT
+        b.block_timestamp::DATE AS day,
+        COUNT(*) FILTER (WHERE i.relative_lock_type = 'blocks')::BIGINT AS block_relative_lock_input_count,
+        COUNT(*) FILTER (WHERE i.relative_lock_type = 'time')::BIGINT AS time_relative_lock_input_count
+    FROM address_inputs i
+    JOIN blocks b ON b.block_height = i.block_height
+    WHERE i.sequence IS NOT NULL
+    GROUP BY b.block_timestamp::DATE
+)
+SELECT
+    t.*,
+    COALESCE(i.block_relative_lock_input_count, 0) AS block_relative_lock_input_count,
+    COALESCE(i.time_relative_lock_input_count, 0) AS time_relative_lock_input_count
+FROM daily_transactions t
+LEFT JOIN daily_inputs i ON i.day = t.day;
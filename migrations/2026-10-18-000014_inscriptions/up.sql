@@ -0,0 +1,23 @@
+-- Inscription envelopes (OP_FALSE OP_IF "ord" ... OP_ENDIF) found in the tapscript of
+-- script-path spends of P2TR outputs
+
+CREATE TABLE inscriptions (
+    inscription_id BIGSERIAL PRIMARY KEY,
+    input_id BIGINT NOT NULL REFERENCES address_inputs(input_id),
+    transaction_id BYTEA NOT NULL, -- The reveal transaction
+    block_height INTEGER NOT NULL,
+    input_index INTEGER NOT NULL,
+    envelope_index SMALLINT NOT NULL, -- Position among the envelopes of the input
+    content_type TEXT,
+    content_encoding TEXT,
+    body_size INTEGER NOT NULL, -- Bytes of content
+    envelope_size INTEGER NOT NULL, -- Bytes of tapscript taken by the envelope
+    UNIQUE (input_id, envelope_index),
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height)
+);
+
+-- Index for inscription time series
+CREATE INDEX idx_inscriptions_block_height ON inscriptions(block_height);
+
+-- Index for finding the outputs of reveal transactions
+CREATE INDEX idx_inscriptions_transaction ON inscriptions(transaction_id, block_height);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_addresses_cluster;
+
+ALTER TABLE addresses DROP COLUMN IF EXISTS cluster_id;
+
+DROP TABLE IF EXISTS clusters;
@@ -0,0 +1,20 @@
+-- Clusters of addresses assumed to belong to the same wallet, because they were spent together
+-- as inputs of one transaction (common-input-ownership heuristic). CoinJoin transactions, whose
+-- inputs belong to different participants, are not used.
+--
+-- The clusters form a union-find kept flat: every clustered address points straight at its
+-- cluster, and merging clusters relabels the members of the smaller ones.
+
+-- The representative address of each cluster with more than one address
+CREATE TABLE clusters (
+    cluster_id BIGINT PRIMARY KEY REFERENCES addresses(address_id),
+    address_count INTEGER NOT NULL,
+    first_block_height INTEGER NOT NULL, -- Block of the first merge forming the cluster
+    last_merged_block_height INTEGER NOT NULL -- Block of the latest merge into the cluster
+);
+
+-- NULL while the address has never been spent together with another (a cluster of its own)
+ALTER TABLE addresses ADD COLUMN cluster_id BIGINT REFERENCES clusters(cluster_id);
+
+-- Index for relabelling and aggregating the addresses of a cluster
+CREATE INDEX idx_addresses_cluster ON addresses(cluster_id) WHERE cluster_id IS NOT NULL;
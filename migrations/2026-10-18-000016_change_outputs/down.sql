@@ -0,0 +1,3 @@
+ALTER TABLE address_outputs
+    DROP COLUMN IF EXISTS change_likelihood,
+    DROP COLUMN IF EXISTS change_heuristics;
@@ -0,0 +1,7 @@
+-- How likely each output is to be the change of its transaction, and the heuristics that fired.
+-- NULL for outputs that were not analysed: coinbase outputs, outputs of single output
+-- transactions, and outputs stored before this migration.
+
+ALTER TABLE address_outputs
+    ADD COLUMN change_likelihood VARCHAR(10), -- 'likely', 'possible' or 'unlikely'
+    ADD COLUMN change_heuristics TEXT[]; -- e.g. 'input_address', 'script_type_match', 'optimal_change'
@@ -0,0 +1,5 @@
+ALTER TABLE block_stats
+    DROP COLUMN IF EXISTS coinjoin_count,
+    DROP COLUMN IF EXISTS coinjoin_value_satoshis;
+
+DROP TABLE IF EXISTS coinjoin_transactions;
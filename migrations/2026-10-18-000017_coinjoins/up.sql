@@ -0,0 +1,22 @@
+-- Transactions detected as CoinJoins, and the CoinJoin volume of each block
+
+CREATE TABLE coinjoin_transactions (
+    transaction_id BYTEA NOT NULL,
+    block_height INTEGER NOT NULL,
+    coinjoin_type VARCHAR(12) NOT NULL, -- 'whirlpool', 'wasabi', 'joinmarket' or 'generic'
+    denomination_satoshis BIGINT NOT NULL, -- Value of the most common equal output
+    anonymity_set INTEGER NOT NULL, -- Outputs of that value
+    mixed_value_satoshis BIGINT NOT NULL, -- The equal outputs together
+    coordinator_fee_satoshis BIGINT, -- Paid to a known coordinator address
+    PRIMARY KEY (transaction_id, block_height),
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height)
+);
+
+-- Index for CoinJoin time series
+CREATE INDEX idx_coinjoin_transactions_block_height ON coinjoin_transactions(block_height);
+
+-- CoinJoin volume per block. NULL for the blocks processed before this migration, whose
+-- CoinJoins were not detected.
+ALTER TABLE block_stats
+    ADD COLUMN coinjoin_count INTEGER,
+    ADD COLUMN coinjoin_value_satoshis BIGINT; -- Mixed value of the CoinJoins
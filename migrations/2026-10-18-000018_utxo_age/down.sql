@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS utxo_age_snapshots;
+DROP TABLE IF EXISTS utxo_creation_days;
@@ -0,0 +1,27 @@
+-- Age distribution of the unspent set (HODL waves)
+
+-- Unspent value by the UTC day its outputs were created, kept up to date block by block:
+-- outputs created in a block are added to its day, outputs spent in it are taken from theirs
+CREATE TABLE utxo_creation_days (
+    creation_date DATE PRIMARY KEY,
+    output_count BIGINT NOT NULL DEFAULT 0,
+    value_satoshis BIGINT NOT NULL DEFAULT 0
+);
+
+-- Start from the unspent set of the blocks processed so far
+INSERT INTO utxo_creation_days (creation_date, output_count, value_satoshis)
+SELECT b.block_timestamp::DATE, COUNT(*), SUM(o.value_satoshis)
+FROM address_outputs o
+JOIN blocks b ON b.block_height = o.block_height
+WHERE o.is_spent = FALSE AND o.is_unspendable = FALSE
+GROUP BY b.block_timestamp::DATE;
+
+-- Unspent value per age band, taken periodically from utxo_creation_days
+CREATE TABLE utxo_age_snapshots (
+    block_height INTEGER NOT NULL REFERENCES blocks(block_height),
+    age_band VARCHAR(8) NOT NULL, -- e.g. '<1d', '1w-1m', '10y+'
+    band_index SMALLINT NOT NULL, -- Position of the band, youngest first
+    output_count BIGINT NOT NULL,
+    value_satoshis BIGINT NOT NULL,
+    PRIMARY KEY (block_height, age_band)
+);
@@ -0,0 +1,9 @@
+ALTER TABLE block_stats
+    DROP COLUMN IF EXISTS coin_days_destroyed,
+    DROP COLUMN IF EXISTS average_spent_age_days;
+
+ALTER TABLE address_inputs
+    DROP COLUMN IF EXISTS spent_output_block_height,
+    DROP COLUMN IF EXISTS spent_output_timestamp,
+    DROP COLUMN IF EXISTS coin_age_blocks,
+    DROP COLUMN IF EXISTS coin_age_seconds;
@@ -0,0 +1,38 @@
+-- How long the coins spent by each input sat unspent, and the coin days destroyed per block
+
+ALTER TABLE address_inputs
+    ADD COLUMN spent_output_block_height INTEGER, -- Block the spent output was created in
+    ADD COLUMN spent_output_timestamp TIMESTAMP,
+    ADD COLUMN coin_age_blocks INTEGER,
+    ADD COLUMN coin_age_seconds BIGINT; -- Negative if the spending block's timestamp is the earlier one
+
+-- Coin days destroyed: BTC spent times the days it sat unspent (negative ages count as zero).
+-- The average age of spent value is weighted by value, so it is the coin days destroyed per BTC spent.
+ALTER TABLE block_stats
+    ADD COLUMN coin_days_destroyed DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN average_spent_age_days DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+-- Fill in the inputs and blocks processed so far
+UPDATE address_inputs i
+SET spent_output_block_height = o.block_height,
+    spent_output_timestamp = ob.block_timestamp,
+    coin_age_blocks = i.block_height - o.block_height,
+    coin_age_seconds = EXTRACT(EPOCH FROM (ib.block_timestamp - ob.block_timestamp))::BIGINT
+FROM address_outputs o, blocks ob, blocks ib
+WHERE o.output_id = i.spent_output_id
+  AND ob.block_height = o.block_height
+  AND ib.block_height = i.block_height;
+
+UPDATE block_stats s
+SET coin_days_destroyed = d.coin_days_destroyed,
+    average_spent_age_days = d.coin_days_destroyed / d.spent_btc
+FROM (
+    SELECT
+        block_height,
+        SUM(value_satoshis / 100000000.0 * GREATEST(coin_age_seconds, 0) / 86400.0)::DOUBLE PRECISION AS coin_days_destroyed,
+        SUM(value_satoshis / 100000000.0)::DOUBLE PRECISION AS spent_btc
+    FROM address_inputs
+    GROUP BY block_height
+    HAVING SUM(value_satoshis) > 0
+) d
+WHERE s.block_height = d.block_height;
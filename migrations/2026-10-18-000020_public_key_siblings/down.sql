@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS public_key_siblings;
+
+ALTER TABLE addresses
+    DROP COLUMN IF EXISTS public_key_exposure_input_id,
+    DROP COLUMN IF EXISTS public_key_exposed_via_address_id;
+
+UPDATE addresses SET public_key_exposure_source = 'spend' WHERE public_key_exposure_source = 'sibling';
+
+ALTER TABLE addresses DROP CONSTRAINT IF EXISTS addresses_public_key_exposure_source_check;
+ALTER TABLE addresses ADD CONSTRAINT addresses_public_key_exposure_source_check
+    CHECK (public_key_exposure_source IN ('output_script', 'spend'));
@@ -0,0 +1,27 @@
+-- Link the addresses backed by the same public key (P2PK, P2PKH, P2WPKH, P2SH-P2WPKH):
+-- revealing the key through one of them exposes the others
+
+ALTER TABLE addresses DROP CONSTRAINT IF EXISTS addresses_public_key_exposure_source_check;
+ALTER TABLE addresses ADD CONSTRAINT addresses_public_key_exposure_source_check
+    CHECK (public_key_exposure_source IN ('output_script', 'spend', 'sibling'));
+
+ALTER TABLE addresses
+    -- The spend that revealed the key, for keys exposed by a spend (of this or a sibling address)
+    ADD COLUMN public_key_exposure_input_id BIGINT REFERENCES address_inputs(input_id),
+    -- The sibling address the key was exposed through, for 'sibling' exposures
+    ADD COLUMN public_key_exposed_via_address_id BIGINT REFERENCES addresses(address_id);
+
+-- Every standard address form of every exposed public key, so that addresses first seen
+-- after their key was exposed are created already exposed. The first exposure is kept.
+CREATE TABLE public_key_siblings (
+    address_string VARCHAR(255) PRIMARY KEY,
+    script_type VARCHAR(20) NOT NULL REFERENCES script_types(script_type),
+    public_key BYTEA NOT NULL,
+    public_key_format VARCHAR(12) NOT NULL,
+    source_address_id BIGINT NOT NULL REFERENCES addresses(address_id), -- The address that exposed the key
+    exposure_input_id BIGINT REFERENCES address_inputs(input_id), -- NULL if exposed by an output script
+    exposed_block_height INTEGER NOT NULL
+);
+
+-- Keys exposed before this migration are linked by `btc-tx-stats link-public-keys`,
+-- as Postgres cannot compute the HASH160 of the address forms
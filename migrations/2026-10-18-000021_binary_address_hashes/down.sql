@@ -0,0 +1,95 @@
+-- Derive the address strings back from the script types and address hashes
+
+CREATE FUNCTION pg_temp.base58_address(version INTEGER, payload BYTEA) RETURNS TEXT AS $$
+DECLARE
+    alphabet CONSTANT TEXT := '123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz';
+    data BYTEA := set_byte('\x00'::BYTEA, 0, version) || payload;
+    n NUMERIC := 0;
+    encoded TEXT := '';
+BEGIN
+    data := data || substring(sha256(sha256(data)) FROM 1 FOR 4);
+    FOR i IN 0..length(data) - 1 LOOP
+        n := n * 256 + get_byte(data, i);
+    END LOOP;
+    WHILE n > 0 LOOP
+        encoded := substr(alphabet, mod(n, 58)::INTEGER + 1, 1) || encoded;
+        n := div(n, 58);
+    END LOOP;
+    -- Each leading zero byte is a leading '1'
+    FOR i IN 0..length(data) - 1 LOOP
+        EXIT WHEN get_byte(data, i) <> 0;
+        encoded := '1' || encoded;
+    END LOOP;
+    RETURN encoded;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- bc1 address of a witness program: bech32 for version 0, bech32m for version 1
+CREATE FUNCTION pg_temp.bech32_address(version INTEGER, program BYTEA) RETURNS TEXT AS $$
+DECLARE
+    charset CONSTANT TEXT := 'qpzry9x8gf2tvdw0s3jn54khce6mua7l';
+    generator CONSTANT INTEGER[] := ARRAY[996825010, 642813549, 513874426, 1027748829, 705979059];
+    data INTEGER[] := ARRAY[version];
+    acc INTEGER := 0;
+    bits INTEGER := 0;
+    chk INTEGER := 1;
+    top INTEGER;
+    encoded TEXT := 'bc1';
+BEGIN
+    FOR i IN 0..length(program) - 1 LOOP
+        acc := ((acc << 8) | get_byte(program, i)) & 8191;
+        bits := bits + 8;
+        WHILE bits >= 5 LOOP
+            bits := bits - 5;
+            data := data || ((acc >> bits) & 31);
+        END LOOP;
+    END LOOP;
+    IF bits > 0 THEN
+        data := data || ((acc << (5 - bits)) & 31);
+    END IF;
+
+    -- Checksum over the expanded 'bc' prefix, the data and six zero groups
+    FOREACH acc IN ARRAY ARRAY[3, 3, 0, 2, 3] || data || ARRAY[0, 0, 0, 0, 0, 0] LOOP
+        top := chk >> 25;
+        chk := ((chk & 33554431) << 5) # acc;
+        FOR i IN 0..4 LOOP
+            IF (top >> i) & 1 = 1 THEN
+                chk := chk # generator[i + 1];
+            END IF;
+        END LOOP;
+    END LOOP;
+    chk := chk # CASE WHEN version = 0 THEN 1 ELSE 734539939 END;
+
+    FOREACH acc IN ARRAY data LOOP
+        encoded := encoded || substr(charset, acc + 1, 1);
+    END LOOP;
+    FOR i IN 0..5 LOOP
+        encoded := encoded || substr(charset, ((chk >> (5 * (5 - i))) & 31) + 1, 1);
+    END LOOP;
+    RETURN encoded;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE FUNCTION pg_temp.address_string(script_type TEXT, address_hash BYTEA) RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN script_type = 'p2pk' THEN encode(address_hash, 'hex')
+        WHEN script_type IN ('p2wpkh', 'p2wsh') THEN pg_temp.bech32_address(0, address_hash)
+        WHEN script_type = 'p2tr' THEN pg_temp.bech32_address(1, address_hash)
+        WHEN script_type IN ('p2pkh', 'non-standard') THEN pg_temp.base58_address(0, address_hash)
+        ELSE pg_temp.base58_address(5, address_hash)
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+ALTER TABLE public_key_siblings DROP CONSTRAINT IF EXISTS public_key_siblings_pkey;
+ALTER TABLE public_key_siblings ADD COLUMN address_string VARCHAR(255);
+UPDATE public_key_siblings SET address_string = pg_temp.address_string(script_type, address_hash);
+ALTER TABLE public_key_siblings DROP COLUMN address_hash;
+ALTER TABLE public_key_siblings ADD PRIMARY KEY (address_string);
+
+-- Fails if addresses of different script types now share a string
+DROP INDEX IF EXISTS idx_addresses_hash;
+ALTER TABLE addresses ADD COLUMN address_string VARCHAR(255);
+UPDATE addresses SET address_string = pg_temp.address_string(script_type, address_hash);
+ALTER TABLE addresses ALTER COLUMN address_string SET NOT NULL;
+ALTER TABLE addresses ADD CONSTRAINT addresses_address_string_key UNIQUE (address_string);
+ALTER TABLE addresses DROP COLUMN address_hash;
@@ -0,0 +1,75 @@
+-- Key addresses by their script type and the binary hash their address encodes, instead of
+-- the base58/bech32/hex address string, which is derived when read:
+--   p2pkh, non-standard      HASH160, base58 with the P2PKH prefix
+--   p2sh, p2ms, unknown      HASH160 (of the script for p2ms and unknown), base58 with the P2SH prefix
+--   p2wpkh, p2wsh, p2tr      witness program, bech32 (v0) or bech32m (v1)
+--   p2pk                     public key, hex
+
+-- Base58Check payload without the version byte and checksum
+CREATE FUNCTION pg_temp.base58_payload(address TEXT) RETURNS BYTEA AS $$
+DECLARE
+    alphabet CONSTANT TEXT := '123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz';
+    n NUMERIC := 0;
+    decoded BYTEA := '';
+    leading_zeros INTEGER := 0;
+BEGIN
+    FOR i IN 1..length(address) LOOP
+        n := n * 58 + (strpos(alphabet, substr(address, i, 1)) - 1);
+    END LOOP;
+    WHILE n > 0 LOOP
+        decoded := set_byte('\x00'::BYTEA, 0, mod(n, 256)::INTEGER) || decoded;
+        n := div(n, 256);
+    END LOOP;
+    -- Each leading '1' is a leading zero byte
+    WHILE substr(address, leading_zeros + 1, 1) = '1' LOOP
+        leading_zeros := leading_zeros + 1;
+    END LOOP;
+    decoded := decode(repeat('00', leading_zeros), 'hex') || decoded;
+    RETURN substring(decoded FROM 2 FOR length(decoded) - 5);
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- Witness program of a bc1 address: the 5-bit groups between the version and the checksum
+CREATE FUNCTION pg_temp.bech32_program(address TEXT) RETURNS BYTEA AS $$
+DECLARE
+    charset CONSTANT TEXT := 'qpzry9x8gf2tvdw0s3jn54khce6mua7l';
+    data CONSTANT TEXT := substr(lower(address), 5, length(address) - 10);
+    acc INTEGER := 0;
+    bits INTEGER := 0;
+    program BYTEA := '';
+BEGIN
+    FOR i IN 1..length(data) LOOP
+        acc := (acc << 5) | (strpos(charset, substr(data, i, 1)) - 1);
+        bits := bits + 5;
+        IF bits >= 8 THEN
+            bits := bits - 8;
+            program := program || set_byte('\x00'::BYTEA, 0, (acc >> bits) & 255);
+        END IF;
+        acc := acc & ((1 << bits) - 1);
+    END LOOP;
+    RETURN program;
+END
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE FUNCTION pg_temp.address_hash(script_type TEXT, address TEXT) RETURNS BYTEA AS $$
+    SELECT CASE
+        WHEN script_type = 'p2pk' THEN decode(address, 'hex')
+        WHEN script_type IN ('p2wpkh', 'p2wsh', 'p2tr') THEN pg_temp.bech32_program(address)
+        ELSE pg_temp.base58_payload(address)
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+ALTER TABLE addresses ADD COLUMN address_hash BYTEA;
+UPDATE addresses SET address_hash = pg_temp.address_hash(script_type, address_string);
+ALTER TABLE addresses ALTER COLUMN address_hash SET NOT NULL;
+
+-- Scripts of different types with the same hash (such as a P2PKH address and a non-standard
+-- script around its hash) now get their own addresses; those already merged stay merged
+ALTER TABLE addresses DROP COLUMN address_string;
+CREATE UNIQUE INDEX idx_addresses_hash ON addresses(address_hash, script_type);
+
+ALTER TABLE public_key_siblings ADD COLUMN address_hash BYTEA;
+UPDATE public_key_siblings SET address_hash = pg_temp.address_hash(script_type, address_string);
+ALTER TABLE public_key_siblings ALTER COLUMN address_hash SET NOT NULL;
+ALTER TABLE public_key_siblings DROP COLUMN address_string;
+ALTER TABLE public_key_siblings ADD PRIMARY KEY (address_hash, script_type);
@@ -0,0 +1,147 @@
+-- Back to unpartitioned tables. Partitions detached into the archive schema are not included.
+
+DROP VIEW daily_timelock_usage;
+DROP VIEW daily_segwit_adoption;
+DROP VIEW block_segwit_adoption;
+
+ALTER SEQUENCE address_outputs_output_id_seq OWNED BY NONE;
+ALTER SEQUENCE address_inputs_input_id_seq OWNED BY NONE;
+
+CREATE FUNCTION pg_temp.unpartition(tbl TEXT) RETURNS VOID AS $$
+BEGIN
+    EXECUTE format('ALTER TABLE %I RENAME TO %I', tbl, tbl || '_partitioned');
+    EXECUTE format('CREATE TABLE %I (LIKE %I INCLUDING DEFAULTS INCLUDING CONSTRAINTS)', tbl, tbl || '_partitioned');
+    EXECUTE format('INSERT INTO %I SELECT * FROM %I', tbl, tbl || '_partitioned');
+    EXECUTE format('DROP TABLE %I', tbl || '_partitioned');
+END
+$$ LANGUAGE plpgsql;
+
+SELECT pg_temp.unpartition(tbl)
+FROM unnest(ARRAY['transactions', 'address_outputs', 'address_inputs']) AS tbl;
+
+ALTER SEQUENCE address_outputs_output_id_seq OWNED BY address_outputs.output_id;
+ALTER SEQUENCE address_inputs_input_id_seq OWNED BY address_inputs.input_id;
+
+ALTER TABLE transactions ADD PRIMARY KEY (transaction_id, block_height);
+ALTER TABLE transactions ADD CONSTRAINT transactions_block_height_transaction_index_key
+    UNIQUE (block_height, transaction_index);
+ALTER TABLE transactions ADD CONSTRAINT transactions_block_height_fkey
+    FOREIGN KEY (block_height) REFERENCES blocks(block_height);
+CREATE INDEX idx_transactions_block_height ON transactions(block_height);
+
+ALTER TABLE address_outputs ADD PRIMARY KEY (output_id);
+ALTER TABLE address_outputs ADD CONSTRAINT address_outputs_transaction_id_block_height_output_index_key
+    UNIQUE (transaction_id, block_height, output_index);
+ALTER TABLE address_outputs ADD CONSTRAINT address_outputs_address_id_fkey
+    FOREIGN KEY (address_id) REFERENCES addresses(address_id);
+CREATE INDEX idx_address_outputs_not_spent ON address_outputs(transaction_id, output_index) WHERE is_spent = false;
+CREATE INDEX idx_address_outputs_addr_tx ON address_outputs(address_id, transaction_id);
+CREATE INDEX idx_address_outputs_address_spent ON address_outputs(address_id, is_spent);
+CREATE INDEX idx_address_outputs_value ON address_outputs(value_satoshis);
+CREATE INDEX idx_address_outputs_address_block ON address_outputs(address_id, block_height);
+CREATE INDEX idx_address_outputs_block_height ON address_outputs(block_height);
+CREATE INDEX idx_address_outputs_after_exposure ON address_outputs(address_id) WHERE received_after_exposure = TRUE;
+
+ALTER TABLE address_inputs ADD PRIMARY KEY (input_id);
+ALTER TABLE address_inputs ADD CONSTRAINT address_inputs_transaction_id_block_height_input_index_key
+    UNIQUE (transaction_id, block_height, input_index);
+ALTER TABLE address_inputs ADD CONSTRAINT address_inputs_address_id_fkey
+    FOREIGN KEY (address_id) REFERENCES addresses(address_id);
+CREATE INDEX idx_address_inputs_addr_tx ON address_inputs(address_id, transaction_id);
+CREATE INDEX idx_address_inputs_spent_output ON address_inputs(spent_output_id);
+CREATE INDEX idx_address_inputs_pubkey ON address_inputs(public_key_revealed) WHERE public_key_revealed IS NOT NULL;
+CREATE INDEX idx_address_inputs_address_block ON address_inputs(address_id, block_height);
+CREATE INDEX idx_address_inputs_block_height ON address_inputs(block_height);
+
+ALTER TABLE txid_block_index ADD CONSTRAINT txid_block_index_transaction_id_block_height_fkey
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height);
+ALTER TABLE address_outputs ADD CONSTRAINT address_outputs_transaction_id_block_height_fkey
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height);
+ALTER TABLE address_inputs ADD CONSTRAINT address_inputs_transaction_id_block_height_fkey
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height);
+ALTER TABLE address_inputs ADD CONSTRAINT address_inputs_spent_output_id_fkey
+    FOREIGN KEY (spent_output_id) REFERENCES address_outputs(output_id);
+ALTER TABLE addresses ADD CONSTRAINT addresses_public_key_exposure_input_id_fkey
+    FOREIGN KEY (public_key_exposure_input_id) REFERENCES address_inputs(input_id);
+ALTER TABLE signatures ADD CONSTRAINT signatures_input_id_fkey
+    FOREIGN KEY (input_id) REFERENCES address_inputs(input_id);
+ALTER TABLE inscriptions ADD CONSTRAINT inscriptions_input_id_fkey
+    FOREIGN KEY (input_id) REFERENCES address_inputs(input_id);
+ALTER TABLE inscriptions ADD CONSTRAINT inscriptions_transaction_id_block_height_fkey
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height);
+ALTER TABLE coinjoin_transactions ADD CONSTRAINT coinjoin_transactions_transaction_id_block_height_fkey
+    FOREIGN KEY (transaction_id, block_height) REFERENCES transactions(transaction_id, block_height);
+ALTER TABLE public_key_siblings ADD CONSTRAINT public_key_siblings_exposure_input_id_fkey
+    FOREIGN KEY (exposure_input_id) REFERENCES address_inputs(input_id);
+
+CREATE VIEW block_segwit_adoption AS
+SELECT
+    block_height,
+    COUNT(*)::BIGINT AS transaction_count,
+    COUNT(*) FILTER (WHERE segwit_input_count > 0)::BIGINT AS segwit_spending_count,
+    COUNT(*) FILTER (WHERE taproot_input_count > 0)::BIGINT AS taproot_spending_count,
+    SUM(input_count)::BIGINT AS input_count,
+    SUM(segwit_input_count)::BIGINT AS segwit_input_count,
+    SUM(taproot_input_count)::BIGINT AS taproot_input_count,
+    SUM(total_size)::BIGINT AS total_size,
+    SUM(witness_size)::BIGINT AS witness_size,
+    SUM(weight)::BIGINT AS weight,
+    SUM(vsize)::BIGINT AS vsize
+FROM transactions
+WHERE NOT is_coinbase
+  AND weight IS NOT NULL
+GROUP BY block_height;
+
+CREATE VIEW daily_segwit_adoption AS
+SELECT
+    b.block_timestamp::DATE AS day,
+    COUNT(DISTINCT a.block_height)::BIGINT AS block_count,
+    SUM(a.transaction_count)::BIGINT AS transaction_count,
+    SUM(a.segwit_spending_count)::BIGINT AS segwit_spending_count,
+    SUM(a.taproot_spending_count)::BIGINT AS taproot_spending_count,
+    SUM(a.input_count)::BIGINT AS input_count,
+    SUM(a.segwit_input_count)::BIGINT AS segwit_input_count,
+    SUM(a.taproot_input_count)::BIGINT AS taproot_input_count,
+    SUM(a.total_size)::BIGINT AS total_size,
+    SUM(a.witness_size)::BIGINT AS witness_size,
+    SUM(a.weight)::BIGINT AS weight,
+    SUM(a.vsize)::BIGINT AS vsize
+FROM block_segwit_adoption a
+JOIN blocks b ON b.block_height = a.block_height
+GROUP BY b.block_timestamp::DATE;
+
+CREATE VIEW daily_timelock_usage AS
+WITH daily_transactions AS (
+    SELECT
+        b.block_timestamp::DATE AS day,
+        COUNT(*)::BIGINT AS transaction_count,
+        COUNT(*) FILTER (WHERE t.version = 1)::BIGINT AS version_1_count,
+        COUNT(*) FILTER (WHERE t.version = 2)::BIGINT AS version_2_count,
+        COUNT(*) FILTER (WHERE t.version NOT IN (1, 2))::BIGINT AS other_version_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type = 'height')::BIGINT AS height_lock_time_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type = 'time')::BIGINT AS time_lock_time_count,
+        COUNT(*) FILTER (WHERE t.lock_time_type <> 'none' AND NOT t.is_lock_time_enforced)::BIGINT AS ignored_lock_time_count,
+        COUNT(*) FILTER (WHERE t.is_anti_fee_sniping)::BIGINT AS anti_fee_sniping_count,
+        COUNT(*) FILTER (WHERE t.signals_rbf)::BIGINT AS rbf_signalling_count
+    FROM transactions t
+    JOIN blocks b ON b.block_height = t.block_height
+    WHERE NOT t.is_coinbase
+      AND t.version IS NOT NULL
+    GROUP BY b.block_timestamp::DATE
+),
+daily_inputs AS (
+    SELECT
+        b.block_timestamp::DATE AS day,
+        COUNT(*) FILTER (WHERE i.relative_lock_type = 'blocks')::BIGINT AS block_relative_lock_input_count,
+        COUNT(*) FILTER (WHERE i.relative_lock_type = 'time')::BIGINT AS time_relative_lock_input_count
+    FROM address_inputs i
+    JOIN blocks b ON b.block_height = i.block_height
+    WHERE i.sequence IS NOT NULL
+    GROUP BY b.block_timestamp::DATE
+)
+SELECT
+    t.*,
+    COALESCE(i.block_relative_lock_input_count, 0) AS block_relative_lock_input_count,
+    COALESCE(i.time_relative_lock_input_count, 0) AS time_relative_lock_input_count
+FROM daily_transactions t
+LEFT JOIN daily_inputs i ON i.day = t.day;
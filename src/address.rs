@@ -0,0 +1,203 @@
+use anyhow::Result;
+use clap::Subcommand;
+use diesel::PgConnection;
+
+use crate::db::{self, models, AddressEventKind};
+use crate::reports::{format_btc, resolve_height};
+
+/// Lookups of a single address in the analytics database
+#[derive(Subcommand, Debug)]
+pub enum AddressCommand {
+    /// Current balance of an address, or its balance at a height
+    Balance {
+        /// The address, as stored (e.g. a bech32 or base58 address)
+        address: String,
+        /// Height to compute the balance at (defaults to the current balance)
+        #[arg(long)]
+        height: Option<u32>,
+    },
+    /// Unspent outputs held by an address, oldest first
+    Utxos {
+        /// The address, as stored (e.g. a bech32 or base58 address)
+        address: String,
+    },
+    /// Receives and spends of an address in chain order, with the balance after each
+    History {
+        /// The address, as stored (e.g. a bech32 or base58 address)
+        address: String,
+        /// First height of the history
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the history (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+    /// First and last activity of an address, and how often it received and spent
+    Activity {
+        /// The address, as stored (e.g. a bech32 or base58 address)
+        address: String,
+    },
+}
+
+impl AddressCommand {
+    /// Runs the lookup, printing its output to stdout
+    pub fn run(self, conn: &mut PgConnection) -> Result<()> {
+        match self {
+            AddressCommand::Balance { address, height } => {
+                let address = find_address(conn, &address)?;
+                print_balance(conn, &address, height)
+            }
+            AddressCommand::Utxos { address } => {
+                let address = find_address(conn, &address)?;
+                print_utxos(conn, &address)
+            }
+            AddressCommand::History { address, from, to } => {
+                let address = find_address(conn, &address)?;
+                let to = resolve_height(conn, to)?;
+                print_history(conn, &address, from, to)
+            }
+            AddressCommand::Activity { address } => {
+                let address = find_address(conn, &address)?;
+                print_activity(conn, &address)
+            }
+        }
+    }
+}
+
+/// Loads an address, failing if it has never been seen
+fn find_address(conn: &mut PgConnection, address: &str) -> Result<models::Address> {
+    db::get_address(conn, address)?
+        .ok_or_else(|| anyhow::anyhow!("Address {} has not been seen", address))
+}
+
+/// Prints the balance of an address, now or at a height
+fn print_balance(
+    conn: &mut PgConnection,
+    address: &models::Address,
+    height: Option<u32>,
+) -> Result<()> {
+    match height {
+        Some(h) => {
+            let balance = db::get_address_balance_at(conn, address.address_id, h as i32)?;
+            println!(
+                "{} balance at height {}: {}",
+                address.address_string(),
+                h,
+                format_btc(balance)
+            );
+        }
+        None => {
+            let balance = db::get_address_unspent_value(conn, address.address_id)?;
+            println!(
+                "{} balance: {}",
+                address.address_string(),
+                format_btc(balance)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the unspent outputs of an address
+fn print_utxos(conn: &mut PgConnection, address: &models::Address) -> Result<()> {
+    let utxos = db::get_address_utxos(conn, address.address_id)?;
+    let total: i64 = utxos.iter().map(|o| o.value_satoshis).sum();
+
+    println!(
+        "{} unspent outputs at {}, {}",
+        utxos.len(),
+        address.address_string(),
+        format_btc(total)
+    );
+    for output in &utxos {
+        println!(
+            "  {}:{} height {} {}",
+            hex::encode(&output.transaction_id),
+            output.output_index,
+            output.block_height,
+            format_btc(output.value_satoshis)
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the receives and spends of an address between `from` and `to` (inclusive)
+fn print_history(
+    conn: &mut PgConnection,
+    address: &models::Address,
+    from: u32,
+    to: u32,
+) -> Result<()> {
+    let events = db::get_address_history(conn, address.address_id, from as i32, to as i32)?;
+    let mut balance = match from.checked_sub(1) {
+        Some(before) => db::get_address_balance_at(conn, address.address_id, before as i32)?,
+        None => 0,
+    };
+
+    println!(
+        "{} history in blocks {}-{} ({} events)",
+        address.address_string(),
+        from,
+        to,
+        events.len()
+    );
+    for event in &events {
+        let (label, value) = match event.kind {
+            AddressEventKind::Receive => ("receive", event.value_satoshis),
+            AddressEventKind::Spend => ("spend", -event.value_satoshis),
+            AddressEventKind::Overwritten => ("overwritten", -event.value_satoshis),
+        };
+        balance += value;
+
+        println!(
+            "  {} height {:>7} {:<11} {}:{} {:>24} balance {}",
+            event.block_timestamp.format("%Y-%m-%d %H:%M:%S"),
+            event.block_height,
+            label,
+            hex::encode(&event.transaction_id),
+            event.index,
+            format_btc(value),
+            format_btc(balance)
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints when an address was first and last active
+fn print_activity(conn: &mut PgConnection, address: &models::Address) -> Result<()> {
+    let activity = db::get_address_activity(conn, address)?;
+    let format_time = |timestamp: Option<chrono::NaiveDateTime>| {
+        timestamp
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let format_height = |height: Option<i32>| {
+        height
+            .map(|h| h.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    println!("{} ({})", address.address_string(), address.script_type);
+    println!(
+        "  first active: height {} ({})",
+        activity.first_height,
+        format_time(activity.first_timestamp)
+    );
+    println!(
+        "  last active: height {} ({})",
+        activity.last_height,
+        format_time(activity.last_timestamp)
+    );
+    println!(
+        "  received {} times (last at height {}), spent {} times (last at height {})",
+        activity.receive_count,
+        format_height(activity.last_receive_height),
+        activity.spend_count,
+        format_height(activity.last_spend_height)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+/// Coinbase transactions that were included in two blocks before BIP30 made duplicate txids
+/// invalid, as (txid, height of the overwritten copy, height of the overwriting copy)
+pub const DUPLICATE_COINBASES: [(&str, u32, u32); 2] = [
+    (
+        "e3bf3d07d4b0375638d5f1db5255fe07ba2c4cb067cd81b84ee974b6585fb468",
+        91_722,
+        91_880,
+    ),
+    (
+        "d5d27987d2a3dfc724e359870c6644b40e497bdc0589a033220fe15429d88599",
+        91_812,
+        91_842,
+    ),
+];
+
+/// Of all the heights a txid was included at, the one whose outputs can be spent.
+/// A later duplicate overwrites the outputs of the earlier copy in the UTXO set,
+/// so only the copy at the highest height is spendable.
+pub fn spendable_height(heights: &[i32]) -> Option<i32> {
+    heights.iter().copied().max()
+}
+
+/// Of all the heights a txid was included at, those whose outputs a copy at `height`
+/// overwrites: every earlier one
+pub fn overwritten_heights(heights: &[i32], height: i32) -> Vec<i32> {
+    heights.iter().copied().filter(|h| *h < height).collect()
+}
+
+/// Whether a transaction at `height` overwriting an earlier copy is one of the known duplicates
+pub fn is_known_duplicate_coinbase(txid: &str, height: u32) -> bool {
+    DUPLICATE_COINBASES
+        .iter()
+        .any(|(dup_txid, _, overwriting_height)| *dup_txid == txid && *overwriting_height == height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::consensus::encode::deserialize_hex;
+    use bitcoin::Transaction;
+
+    /// A coinbase shaped like those mined before BIP34 put the height in the scriptSig: the
+    /// scriptSig holds only target bits and an extranonce, and it pays 50 BTC to a public key.
+    /// Nothing in it depends on the block, so a miner reusing its key and extranonce in a later
+    /// block mines the same transaction again, as happened at the heights below.
+    const PRE_BIP34_COINBASE: &str = concat!(
+        "01000000",                                                         // version
+        "01",                                                               // inputs
+        "0000000000000000000000000000000000000000000000000000000000000000", // null outpoint
+        "ffffffff",
+        "06",
+        "0456720e1b00", // bits, extranonce
+        "ffffffff",
+        "01",               // outputs
+        "00f2052a01000000", // 50 BTC
+        "43",
+        "41",
+        "04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61de",
+        "b649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f",
+        "ac",       // OP_CHECKSIG
+        "00000000", // locktime
+    );
+
+    #[test]
+    fn pre_bip34_coinbases_mined_twice_collide() {
+        // The same coinbase mined in the overwritten and in the overwriting block
+        let overwritten: Transaction = deserialize_hex(PRE_BIP34_COINBASE).unwrap();
+        let overwriting: Transaction = deserialize_hex(PRE_BIP34_COINBASE).unwrap();
+        assert!(overwritten.is_coinbase());
+        assert_eq!(overwritten.compute_txid(), overwriting.compute_txid());
+
+        // BIP34 made them differ by putting the height first in the scriptSig
+        let mut with_height = overwriting.clone();
+        let mut script_sig = vec![0x03, 0xe8, 0x66, 0x01]; // 91,880
+        script_sig.extend_from_slice(overwriting.input[0].script_sig.as_bytes());
+        with_height.input[0].script_sig = script_sig.into();
+        assert_ne!(with_height.compute_txid(), overwritten.compute_txid());
+    }
+
+    #[test]
+    fn duplicate_coinbases_resolve_to_the_overwriting_copy() {
+        for (txid, overwritten_height, overwriting_height) in DUPLICATE_COINBASES {
+            let (overwritten_height, overwriting_height) =
+                (overwritten_height as i32, overwriting_height as i32);
+
+            // When first mined, the copy overwrites nothing
+            assert!(overwritten_heights(&[overwritten_height], overwritten_height).is_empty());
+
+            // txid_block_index returns the heights in no particular order
+            for heights in [
+                [overwritten_height, overwriting_height],
+                [overwriting_height, overwritten_height],
+            ] {
+                // The later copy overwrites the outputs of the earlier one...
+                assert_eq!(
+                    overwritten_heights(&heights, overwriting_height),
+                    [overwritten_height],
+                    "{}",
+                    txid
+                );
+                // ...and is the one spends find
+                assert_eq!(
+                    spendable_height(&heights),
+                    Some(overwriting_height),
+                    "{}",
+                    txid
+                );
+            }
+        }
+
+        assert_eq!(spendable_height(&[]), None);
+    }
+
+    #[test]
+    fn known_duplicates_fire_at_the_overwriting_heights() {
+        let coinbase: Transaction = deserialize_hex(PRE_BIP34_COINBASE).unwrap();
+
+        for (txid, overwritten_height, overwriting_height) in DUPLICATE_COINBASES {
+            assert!(is_known_duplicate_coinbase(txid, overwriting_height));
+            assert!(!is_known_duplicate_coinbase(txid, overwritten_height));
+
+            // Any other coinbase overwriting at those heights is unexpected
+            assert!(!is_known_duplicate_coinbase(
+                &coinbase.compute_txid().to_string(),
+                overwriting_height
+            ));
+        }
+
+        // The processor looks duplicates up by the display form of the computed txid
+        for (txid, _, _) in DUPLICATE_COINBASES {
+            let computed: bitcoin::Txid = txid.parse().unwrap();
+            assert_eq!(computed.to_string(), txid);
+        }
+    }
+}
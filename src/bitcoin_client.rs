@@ -1,6 +1,5 @@
 use anyhow::{Context, Result};
 use bitcoin::{consensus::Decodable, Block, BlockHash};
-use hex;
 use reqwest::Client;
 use serde::Deserialize;
 use std::io::Cursor;
@@ -0,0 +1,397 @@
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::fmt;
+use std::io::Write;
+
+use crate::db::OutputInfo;
+use crate::script_type::ScriptType;
+
+/// Payments in whole multiples of 0.001 BTC are taken as round amounts chosen by a person
+const ROUND_VALUE_SATOSHIS: u64 = 100_000;
+
+/// Evidence that an output is the change returned to the sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeHeuristic {
+    /// Pays back to one of the addresses being spent
+    InputAddress,
+    /// Same script type as every input, while some other output has a different one
+    ScriptTypeMatch,
+    /// Not a round value, while some other output is (the payment)
+    NonRoundValue,
+    /// Pays to a new address, while every other output pays to an address already seen
+    FreshAddress,
+    /// The only output smaller than every input: were it the payment, an input would be unnecessary
+    OptimalChange,
+    /// The last output, in a transaction whose outputs are not sorted (BIP69) by value
+    LastOutput,
+}
+
+impl ChangeHeuristic {
+    /// The name stored in the database when this heuristic fires
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeHeuristic::InputAddress => "input_address",
+            ChangeHeuristic::ScriptTypeMatch => "script_type_match",
+            ChangeHeuristic::NonRoundValue => "non_round_value",
+            ChangeHeuristic::FreshAddress => "fresh_address",
+            ChangeHeuristic::OptimalChange => "optimal_change",
+            ChangeHeuristic::LastOutput => "last_output",
+        }
+    }
+}
+
+/// How likely an output is to be change, from the heuristics that fired for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum ChangeLikelihood {
+    /// Pays back to an input address, or more heuristics fired than for any other output
+    Likely,
+    /// Some heuristics fired, but not decisively
+    Possible,
+    /// No heuristic fired
+    Unlikely,
+}
+
+impl ChangeLikelihood {
+    /// The value stored in the database for this likelihood
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeLikelihood::Likely => "likely",
+            ChangeLikelihood::Possible => "possible",
+            ChangeLikelihood::Unlikely => "unlikely",
+        }
+    }
+}
+
+impl fmt::Display for ChangeLikelihood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for ChangeLikelihood {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ChangeLikelihood {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "likely" => Ok(ChangeLikelihood::Likely),
+            "possible" => Ok(ChangeLikelihood::Possible),
+            "unlikely" => Ok(ChangeLikelihood::Unlikely),
+            other => Err(format!("Unknown change likelihood '{}'", other).into()),
+        }
+    }
+}
+
+/// A stored output of the transaction being processed
+pub struct CreatedOutput {
+    pub output_id: i64,
+    pub address_id: i64,
+    pub script_type: ScriptType,
+    pub value_satoshis: u64,
+    pub is_new_address: bool, // The address was first seen in this output
+}
+
+/// The change label of one output
+pub struct ChangeLabel {
+    pub output_id: i64,
+    pub likelihood: ChangeLikelihood,
+    pub heuristics: Vec<ChangeHeuristic>,
+}
+
+/// Labels the outputs of a transaction by how likely each is to be change, given the outputs
+/// it spent. Transactions with fewer than two outputs, or no known inputs, are not labelled.
+/// `outputs` are in output order.
+pub fn label_change_outputs(outputs: &[CreatedOutput], inputs: &[OutputInfo]) -> Vec<ChangeLabel> {
+    if outputs.len() < 2 || inputs.is_empty() {
+        return Vec::new();
+    }
+
+    let input_script_type = inputs
+        .iter()
+        .all(|i| i.script_type == inputs[0].script_type)
+        .then_some(inputs[0].script_type);
+    let mixed_output_types = outputs
+        .iter()
+        .any(|o| Some(o.script_type) != input_script_type);
+    let min_input_value = inputs.iter().map(|i| i.value_satoshis).min().unwrap_or(0) as u64;
+    let below_min_input = outputs
+        .iter()
+        .filter(|o| o.value_satoshis < min_input_value)
+        .count();
+    let is_value_sorted = outputs
+        .windows(2)
+        .all(|pair| pair[0].value_satoshis <= pair[1].value_satoshis);
+
+    let heuristics: Vec<Vec<ChangeHeuristic>> = outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| {
+            let others = || {
+                outputs
+                    .iter()
+                    .enumerate()
+                    .filter(move |(other_index, _)| *other_index != index)
+                    .map(|(_, other)| other)
+            };
+            let mut fired = Vec::new();
+
+            if inputs.iter().any(|i| i.address_id == output.address_id) {
+                fired.push(ChangeHeuristic::InputAddress);
+            }
+            if mixed_output_types && Some(output.script_type) == input_script_type {
+                fired.push(ChangeHeuristic::ScriptTypeMatch);
+            }
+            if !is_round(output.value_satoshis) && others().any(|o| is_round(o.value_satoshis)) {
+                fired.push(ChangeHeuristic::NonRoundValue);
+            }
+            if output.is_new_address && others().all(|o| !o.is_new_address) {
+                fired.push(ChangeHeuristic::FreshAddress);
+            }
+            if below_min_input == 1 && output.value_satoshis < min_input_value {
+                fired.push(ChangeHeuristic::OptimalChange);
+            }
+            if !is_value_sorted && index == outputs.len() - 1 {
+                fired.push(ChangeHeuristic::LastOutput);
+            }
+
+            fired
+        })
+        .collect();
+
+    outputs
+        .iter()
+        .zip(heuristics.iter())
+        .enumerate()
+        .map(|(index, (output, fired))| {
+            let best_of_others = heuristics
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != index)
+                .map(|(_, other)| other.len())
+                .max()
+                .unwrap_or(0);
+
+            let likelihood = if fired.contains(&ChangeHeuristic::InputAddress)
+                || (fired.len() >= 2 && fired.len() > best_of_others)
+            {
+                ChangeLikelihood::Likely
+            } else if !fired.is_empty() {
+                ChangeLikelihood::Possible
+            } else {
+                ChangeLikelihood::Unlikely
+            };
+
+            ChangeLabel {
+                output_id: output.output_id,
+                likelihood,
+                heuristics: fired.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Whether a value is a round amount of bitcoin
+fn is_round(value_satoshis: u64) -> bool {
+    value_satoshis > 0 && value_satoshis.is_multiple_of(ROUND_VALUE_SATOSHIS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ChangeHeuristic::*;
+    use ChangeLikelihood::*;
+
+    /// An output of the transaction, its id the output index
+    fn output(
+        index: i64,
+        address_id: i64,
+        script_type: ScriptType,
+        value_satoshis: u64,
+        is_new_address: bool,
+    ) -> CreatedOutput {
+        CreatedOutput {
+            output_id: index,
+            address_id,
+            script_type,
+            value_satoshis,
+            is_new_address,
+        }
+    }
+
+    /// An output the transaction spends
+    fn input(address_id: i64, script_type: ScriptType, value_satoshis: i64) -> OutputInfo {
+        OutputInfo {
+            output_id: 100 + address_id,
+            address_id,
+            value_satoshis,
+            script_type,
+            block_height: 0,
+            block_timestamp: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    /// The likelihood and heuristics of each output, in output order
+    fn labels(
+        outputs: &[CreatedOutput],
+        inputs: &[OutputInfo],
+    ) -> Vec<(ChangeLikelihood, Vec<ChangeHeuristic>)> {
+        let labels = label_change_outputs(outputs, inputs);
+        for (label, output) in labels.iter().zip(outputs) {
+            assert_eq!(label.output_id, output.output_id);
+        }
+        labels
+            .into_iter()
+            .map(|label| (label.likelihood, label.heuristics))
+            .collect()
+    }
+
+    #[test]
+    fn each_heuristic_fires_on_its_own() {
+        use ScriptType::{P2pkh, P2wpkh};
+
+        // Unless a case says otherwise, the outputs share the script type of the inputs, are
+        // round, pay to addresses already seen, are no smaller than any input, and are sorted
+        let cases = [
+            (
+                "input address",
+                vec![
+                    output(0, 2, P2wpkh, 200_000, false),
+                    output(1, 1, P2wpkh, 300_000, false),
+                ],
+                vec![input(1, P2wpkh, 100_000)],
+                InputAddress,
+                Likely,
+            ),
+            (
+                "script type match",
+                vec![
+                    output(0, 2, P2wpkh, 200_000, false),
+                    output(1, 3, P2pkh, 300_000, false),
+                ],
+                vec![input(1, P2pkh, 100_000)],
+                ScriptTypeMatch,
+                Possible,
+            ),
+            (
+                "non-round value",
+                vec![
+                    output(0, 2, P2wpkh, 200_000, false),
+                    output(1, 3, P2wpkh, 234_567, false),
+                ],
+                vec![input(1, P2wpkh, 100_000)],
+                NonRoundValue,
+                Possible,
+            ),
+            (
+                "fresh address",
+                vec![
+                    output(0, 2, P2wpkh, 200_000, false),
+                    output(1, 3, P2wpkh, 300_000, true),
+                ],
+                vec![input(1, P2wpkh, 100_000)],
+                FreshAddress,
+                Possible,
+            ),
+            (
+                "optimal change",
+                vec![
+                    output(0, 2, P2wpkh, 200_000, false),
+                    output(1, 3, P2wpkh, 700_000, false),
+                ],
+                vec![input(1, P2wpkh, 500_000), input(4, P2wpkh, 600_000)],
+                OptimalChange,
+                Possible,
+            ),
+            (
+                "last output",
+                vec![
+                    output(0, 2, P2wpkh, 300_000, false),
+                    output(1, 3, P2wpkh, 200_000, false),
+                ],
+                vec![input(1, P2wpkh, 100_000)],
+                LastOutput,
+                Possible,
+            ),
+        ];
+
+        for (name, outputs, inputs, heuristic, likelihood) in cases {
+            // The heuristic fires for the last output in every case except optimal change
+            let change_index = if heuristic == OptimalChange { 0 } else { 1 };
+            let mut expected = vec![(Unlikely, Vec::new()), (Unlikely, Vec::new())];
+            expected[change_index] = (likelihood, vec![heuristic]);
+
+            assert_eq!(labels(&outputs, &inputs), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn more_heuristics_than_any_other_output_is_likely() {
+        let outputs = [
+            output(0, 2, ScriptType::P2wpkh, 200_000, false),
+            output(1, 3, ScriptType::P2wpkh, 234_567, true),
+        ];
+        let inputs = [input(1, ScriptType::P2wpkh, 100_000)];
+
+        assert_eq!(
+            labels(&outputs, &inputs),
+            vec![
+                (Unlikely, vec![]),
+                (Likely, vec![NonRoundValue, FreshAddress]),
+            ]
+        );
+    }
+
+    #[test]
+    fn outputs_tied_on_heuristics_are_possible() {
+        let outputs = [
+            output(0, 2, ScriptType::P2wpkh, 712_345, true),
+            output(1, 3, ScriptType::P2wpkh, 200_000, false),
+        ];
+        let inputs = [input(1, ScriptType::P2wpkh, 500_000)];
+
+        assert_eq!(
+            labels(&outputs, &inputs),
+            vec![
+                (Possible, vec![NonRoundValue, FreshAddress]),
+                (Possible, vec![OptimalChange, LastOutput]),
+            ]
+        );
+    }
+
+    #[test]
+    fn paying_back_to_an_input_address_is_likely_even_when_outnumbered() {
+        let outputs = [
+            output(0, 1, ScriptType::P2wpkh, 200_000, false),
+            output(1, 3, ScriptType::P2wpkh, 212_345, true),
+        ];
+        let inputs = [input(1, ScriptType::P2wpkh, 100_000)];
+
+        assert_eq!(
+            labels(&outputs, &inputs),
+            vec![
+                (Likely, vec![InputAddress]),
+                (Likely, vec![NonRoundValue, FreshAddress]),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_outputs_and_unknown_inputs_are_not_labelled() {
+        let inputs = [input(1, ScriptType::P2wpkh, 100_000)];
+        let outputs = [
+            output(0, 2, ScriptType::P2wpkh, 200_000, false),
+            output(1, 3, ScriptType::P2wpkh, 234_567, true),
+        ];
+
+        assert!(label_change_outputs(&outputs[..1], &inputs).is_empty());
+        assert!(label_change_outputs(&outputs, &[]).is_empty());
+    }
+}
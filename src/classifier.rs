@@ -0,0 +1,515 @@
+use anyhow::Result;
+use bech32::{hrp, segwit, Hrp};
+use bitcoin::base58;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::script::Script;
+use std::env;
+use tracing::info;
+
+use crate::script_type::{ScriptExtraData, ScriptType};
+
+mod non_standard;
+mod standard;
+pub mod template;
+
+use non_standard::{Hash160FoundClassifier, P2pkhPlusClassifier, UnknownScriptClassifier};
+use standard::{
+    P2msClassifier, P2pkClassifier, P2pkhClassifier, P2shClassifier, P2trClassifier,
+    P2wpkhClassifier, P2wshClassifier,
+};
+
+/// Environment variable holding a comma separated list of classifier names, in the order
+/// they should be tried. Classifiers that are not listed are disabled, except the catch-all
+/// for unknown scripts, which is always tried last.
+pub const CLASSIFIER_ORDER_ENV: &str = "SCRIPT_CLASSIFIERS";
+
+/// Structure to represent script type and address
+pub struct ScriptInfo {
+    pub address_hash: Vec<u8>, // What the address encodes, see `encode_address`
+    pub script_type: ScriptType,
+    pub extra_data: Option<ScriptExtraData>, // Stored as JSONB
+    pub public_keys: Vec<Vec<u8>>,           // Public keys embedded in the script itself
+}
+
+/// Recognises one pattern of output script and derives its address and metadata
+pub trait ScriptClassifier: Send + Sync {
+    /// Name used to refer to the classifier when configuring the registry order
+    fn name(&self) -> &'static str;
+
+    /// Classifies the script, or returns None if it does not match this pattern.
+    /// `instructions` are the successfully parsed instructions of `script`.
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo>;
+}
+
+/// Ordered list of classifiers, the first to match a script wins. Scripts none of them match
+/// fall through to the catch-all, so every non-empty output script is stored and its later
+/// spend can be found.
+pub struct ClassifierRegistry {
+    classifiers: Vec<Box<dyn ScriptClassifier>>,
+    catch_all: UnknownScriptClassifier,
+}
+
+impl ClassifierRegistry {
+    /// A registry with only the catch-all for unknown scripts
+    pub fn empty() -> Self {
+        Self {
+            classifiers: Vec::new(),
+            catch_all: UnknownScriptClassifier,
+        }
+    }
+
+    /// The built-in classifiers in their default order: standard script types first,
+    /// then the non-standard patterns, and finally the catch-all for unknown scripts
+    pub fn standard() -> Self {
+        let mut registry = Self::empty();
+        for classifier in builtin_classifiers() {
+            registry.register(classifier);
+        }
+        registry
+    }
+
+    /// The built-in classifiers with the given names, in the given order, followed by the
+    /// catch-all. The catch-all may be listed, but only last.
+    pub fn from_names(names: &[&str]) -> Result<Self> {
+        let mut builtins = builtin_classifiers();
+        let mut registry = Self::empty();
+
+        let catch_all_name = registry.catch_all.name();
+        let names = match names.split_last() {
+            Some((last, rest)) if *last == catch_all_name => rest,
+            _ => names,
+        };
+
+        for name in names {
+            if *name == catch_all_name {
+                anyhow::bail!(
+                    "Script classifier '{}' is the catch-all and can only be listed last",
+                    name
+                );
+            }
+            let position = builtins
+                .iter()
+                .position(|c| c.name() == *name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown or duplicate script classifier '{}' (available: {})",
+                        name,
+                        Self::standard().names().join(", ")
+                    )
+                })?;
+            registry.register(builtins.remove(position));
+        }
+
+        Ok(registry)
+    }
+
+    /// Builds the registry from the order configured in `SCRIPT_CLASSIFIERS`,
+    /// falling back to the standard order if it is not set
+    pub fn from_env() -> Result<Self> {
+        let registry = match env::var(CLASSIFIER_ORDER_ENV) {
+            Ok(order) => {
+                let names: Vec<&str> = order
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                Self::from_names(&names)?
+            }
+            Err(_) => Self::standard(),
+        };
+
+        info!("Script classifiers: {}", registry.names().join(", "));
+        Ok(registry)
+    }
+
+    /// Appends a classifier, which is tried after those already registered and before the
+    /// catch-all
+    pub fn register(&mut self, classifier: Box<dyn ScriptClassifier>) {
+        self.classifiers.push(classifier);
+    }
+
+    /// Names of the registered classifiers, in order, ending with the catch-all
+    pub fn names(&self) -> Vec<&'static str> {
+        self.classifiers
+            .iter()
+            .map(|c| c.name())
+            .chain([self.catch_all.name()])
+            .collect()
+    }
+
+    /// Extract address and script type information from output script
+    pub fn classify(&self, script: &Script) -> Option<ScriptInfo> {
+        let instructions = script
+            .instructions()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        self.classifiers
+            .iter()
+            .find_map(|c| c.classify(script, &instructions))
+            .or_else(|| self.catch_all.classify(script, &instructions))
+    }
+}
+
+/// Every built-in classifier but the catch-all, in the default order
+fn builtin_classifiers() -> Vec<Box<dyn ScriptClassifier>> {
+    vec![
+        Box::new(P2pkhClassifier),
+        Box::new(P2shClassifier),
+        Box::new(P2pkClassifier),
+        Box::new(P2wpkhClassifier),
+        Box::new(P2wshClassifier),
+        Box::new(P2trClassifier),
+        Box::new(P2msClassifier),
+        Box::new(P2pkhPlusClassifier),
+        Box::new(Hash160FoundClassifier),
+    ]
+}
+
+/// The human-readable address of a script type and address hash, as stored in `addresses`.
+/// The hash is the HASH160 of P2PKH and P2SH, the witness program of SegWit outputs and the
+/// public key of P2PK; P2MS and unknown scripts use the HASH160 of the script with the P2SH
+/// prefix, and non-standard scripts the HASH160 they contain with the P2PKH prefix.
+pub fn encode_address(script_type: ScriptType, address_hash: &[u8]) -> String {
+    let encoded = match script_type {
+        ScriptType::P2pkh | ScriptType::NonStandard => Ok(encode_base58_address(0, address_hash)),
+        ScriptType::P2sh | ScriptType::P2ms | ScriptType::Unknown => {
+            Ok(encode_base58_address(5, address_hash))
+        }
+        ScriptType::P2pk => Ok(hex::encode(address_hash)),
+        ScriptType::P2wpkh | ScriptType::P2wsh => encode_bech32_address("bc", 0, address_hash),
+        ScriptType::P2tr => encode_bech32_address("bc", 1, address_hash),
+    };
+
+    // Stored hashes always encode, but don't lose one that doesn't
+    encoded.unwrap_or_else(|_| hex::encode(address_hash))
+}
+
+/// Decodes a human-readable address to its address hash and the script types it may be
+/// stored under, the type the address stands for first, or None if it is not a mainnet
+/// address this project produces
+pub fn decode_address(address: &str) -> Option<(Vec<u8>, &'static [ScriptType])> {
+    // P2PK addresses are the hex encoded public key
+    if address.len() == 66 || address.len() == 130 {
+        if let Ok(key) = hex::decode(address) {
+            return Some((key, &[ScriptType::P2pk]));
+        }
+    }
+
+    if let Ok((hrp, version, program)) = segwit::decode(address) {
+        if hrp != hrp::BC {
+            return None;
+        }
+        let script_types: &'static [ScriptType] = match (version.to_u8(), program.len()) {
+            (0, 20) => &[ScriptType::P2wpkh],
+            (0, 32) => &[ScriptType::P2wsh],
+            (1, 32) => &[ScriptType::P2tr],
+            _ => return None,
+        };
+        return Some((program, script_types));
+    }
+
+    let data = base58::decode_check(address).ok()?;
+    let script_types: &'static [ScriptType] = match (data.first(), data.len()) {
+        (Some(0), 21) => &[ScriptType::P2pkh, ScriptType::NonStandard],
+        (Some(5), 21) => &[ScriptType::P2sh, ScriptType::P2ms, ScriptType::Unknown],
+        _ => return None,
+    };
+    Some((data[1..].to_vec(), script_types))
+}
+
+/// Base58Check encodes a payload behind a version prefix
+fn encode_base58_address(prefix: u8, payload: &[u8]) -> String {
+    let mut data = vec![prefix];
+    data.extend_from_slice(payload);
+    base58::encode_check(&data)
+}
+
+/// Describes each instruction of a script, for the extra data of non-standard scripts.
+/// Template hashes are computed from these descriptions, so they must not change.
+fn script_ops(instructions: &[Instruction]) -> Vec<String> {
+    instructions
+        .iter()
+        .map(|inst| match inst {
+            Instruction::PushBytes(bytes) => format!("PUSH({} bytes)", bytes.len()),
+            Instruction::Op(op) => opcode_name(op.to_u8()),
+        })
+        .collect()
+}
+
+/// Names of the opcodes from OP_NOP (0x61) to OP_CHECKSIGADD (0xba)
+const OPCODE_NAMES: [&str; 90] = [
+    "OP_NOP",
+    "OP_VER",
+    "OP_IF",
+    "OP_NOTIF",
+    "OP_VERIF",
+    "OP_VERNOTIF",
+    "OP_ELSE",
+    "OP_ENDIF",
+    "OP_VERIFY",
+    "OP_RETURN",
+    "OP_TOALTSTACK",
+    "OP_FROMALTSTACK",
+    "OP_2DROP",
+    "OP_2DUP",
+    "OP_3DUP",
+    "OP_2OVER",
+    "OP_2ROT",
+    "OP_2SWAP",
+    "OP_IFDUP",
+    "OP_DEPTH",
+    "OP_DROP",
+    "OP_DUP",
+    "OP_NIP",
+    "OP_OVER",
+    "OP_PICK",
+    "OP_ROLL",
+    "OP_ROT",
+    "OP_SWAP",
+    "OP_TUCK",
+    "OP_CAT",
+    "OP_SUBSTR",
+    "OP_LEFT",
+    "OP_RIGHT",
+    "OP_SIZE",
+    "OP_INVERT",
+    "OP_AND",
+    "OP_OR",
+    "OP_XOR",
+    "OP_EQUAL",
+    "OP_EQUALVERIFY",
+    "OP_RESERVED1",
+    "OP_RESERVED2",
+    "OP_1ADD",
+    "OP_1SUB",
+    "OP_2MUL",
+    "OP_2DIV",
+    "OP_NEGATE",
+    "OP_ABS",
+    "OP_NOT",
+    "OP_0NOTEQUAL",
+    "OP_ADD",
+    "OP_SUB",
+    "OP_MUL",
+    "OP_DIV",
+    "OP_MOD",
+    "OP_LSHIFT",
+    "OP_RSHIFT",
+    "OP_BOOLAND",
+    "OP_BOOLOR",
+    "OP_NUMEQUAL",
+    "OP_NUMEQUALVERIFY",
+    "OP_NUMNOTEQUAL",
+    "OP_LESSTHAN",
+    "OP_GREATERTHAN",
+    "OP_LESSTHANOREQUAL",
+    "OP_GREATERTHANOREQUAL",
+    "OP_MIN",
+    "OP_MAX",
+    "OP_WITHIN",
+    "OP_RIPEMD160",
+    "OP_SHA1",
+    "OP_SHA256",
+    "OP_HASH160",
+    "OP_HASH256",
+    "OP_CODESEPARATOR",
+    "OP_CHECKSIG",
+    "OP_CHECKSIGVERIFY",
+    "OP_CHECKMULTISIG",
+    "OP_CHECKMULTISIGVERIFY",
+    "OP_NOP1",
+    "OP_CLTV",
+    "OP_CSV",
+    "OP_NOP4",
+    "OP_NOP5",
+    "OP_NOP6",
+    "OP_NOP7",
+    "OP_NOP8",
+    "OP_NOP9",
+    "OP_NOP10",
+    "OP_CHECKSIGADD",
+];
+
+/// Name of an opcode by its byte, fixed here rather than taken from the `Debug` output of
+/// rust-bitcoin, which may change between versions. The names are those rust-bitcoin 0.32
+/// used when templates were first stored.
+fn opcode_name(code: u8) -> String {
+    match code {
+        0x00..=0x4b => format!("OP_PUSHBYTES_{}", code),
+        0x4c => "OP_PUSHDATA1".to_string(),
+        0x4d => "OP_PUSHDATA2".to_string(),
+        0x4e => "OP_PUSHDATA4".to_string(),
+        0x4f => "OP_PUSHNUM_NEG1".to_string(),
+        0x50 => "OP_RESERVED".to_string(),
+        0x51..=0x60 => format!("OP_PUSHNUM_{}", code - 0x50),
+        0x61..=0xba => OPCODE_NAMES[(code - 0x61) as usize].to_string(),
+        0xbb..=0xfe => format!("OP_RETURN_{}", code),
+        0xff => "OP_INVALIDOPCODE".to_string(),
+    }
+}
+
+/// Helper function to encode a bech32/bech32m address
+/// Returns Result<String, String> to properly handle encoding errors
+fn encode_bech32_address(hrp_str: &str, version_u8: u8, program: &[u8]) -> Result<String, String> {
+    // Parse the HRP (Human Readable Part)
+    let hrp = match hrp_str {
+        "bc" => hrp::BC,
+        _ => Hrp::parse(hrp_str).expect("Invalid HRP"),
+    };
+
+    // Handle known versions with constants
+    if version_u8 == 0 {
+        segwit::encode(hrp, segwit::VERSION_0, program)
+            .map_err(|e| format!("Failed to encode SegWit v0 address: {}", e))
+    } else if version_u8 == 1 {
+        segwit::encode(hrp, segwit::VERSION_1, program)
+            .map_err(|e| format!("Failed to encode SegWit v1 address: {}", e))
+    } else {
+        Err(format!("Unsupported witness version: {}", version_u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::ScriptBuf;
+
+    /// A P2PKH script followed by an OP_NOP
+    const P2PKH_PLUS: &str = "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac61";
+
+    fn script_type(registry: &ClassifierRegistry, script_hex: &str) -> Option<ScriptType> {
+        let script = ScriptBuf::from_hex(script_hex).unwrap();
+        registry.classify(&script).map(|info| info.script_type)
+    }
+
+    #[test]
+    fn standard_order_ends_with_the_catch_all() {
+        assert_eq!(
+            ClassifierRegistry::standard().names(),
+            [
+                "p2pkh",
+                "p2sh",
+                "p2pk",
+                "p2wpkh",
+                "p2wsh",
+                "p2tr",
+                "p2ms",
+                "p2pkh-plus",
+                "hash160-found",
+                "unknown"
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_order_always_keeps_the_catch_all_last() {
+        let registry = ClassifierRegistry::from_names(&["p2sh", "p2pkh"]).unwrap();
+        assert_eq!(registry.names(), ["p2sh", "p2pkh", "unknown"]);
+
+        // Disabled patterns fall through to the catch-all instead of going unstored
+        assert_eq!(
+            script_type(&registry, P2PKH_PLUS),
+            Some(ScriptType::Unknown)
+        );
+
+        let registry = ClassifierRegistry::from_names(&["p2pkh-plus", "unknown"]).unwrap();
+        assert_eq!(registry.names(), ["p2pkh-plus", "unknown"]);
+        assert_eq!(
+            script_type(&registry, P2PKH_PLUS),
+            Some(ScriptType::NonStandard)
+        );
+    }
+
+    #[test]
+    fn configured_order_rejects_misplaced_unknown_and_duplicate_names() {
+        assert!(ClassifierRegistry::from_names(&["unknown", "p2pkh"]).is_err());
+        assert!(ClassifierRegistry::from_names(&["p2pkh", "p2pkh"]).is_err());
+        assert!(ClassifierRegistry::from_names(&["p2pkh", "p2foo"]).is_err());
+    }
+
+    #[test]
+    fn first_matching_classifier_wins() {
+        let registry = ClassifierRegistry::standard();
+        assert_eq!(
+            script_type(&registry, P2PKH_PLUS),
+            Some(ScriptType::NonStandard)
+        );
+
+        // hash160-found before p2pkh-plus takes the same script
+        let registry = ClassifierRegistry::from_names(&["hash160-found", "p2pkh-plus"]).unwrap();
+        let script = ScriptBuf::from_hex(P2PKH_PLUS).unwrap();
+        assert!(matches!(
+            registry.classify(&script).unwrap().extra_data,
+            Some(ScriptExtraData::NonStandard(
+                crate::script_type::NonStandardData::Hash160Found { .. }
+            ))
+        ));
+
+        // Empty scripts match nothing, not even the catch-all
+        assert_eq!(script_type(&registry, ""), None);
+    }
+
+    #[test]
+    fn opcodes_render_from_a_fixed_table() {
+        for (code, name) in [
+            (0x4f, "OP_PUSHNUM_NEG1"),
+            (0x51, "OP_PUSHNUM_1"),
+            (0x60, "OP_PUSHNUM_16"),
+            (0x61, "OP_NOP"),
+            (0x6a, "OP_RETURN"),
+            (0xa9, "OP_HASH160"),
+            (0xb1, "OP_CLTV"),
+            (0xb2, "OP_CSV"),
+            (0xba, "OP_CHECKSIGADD"),
+            (0xbb, "OP_RETURN_187"),
+            (0xff, "OP_INVALIDOPCODE"),
+        ] {
+            assert_eq!(opcode_name(code), name);
+        }
+    }
+
+    #[test]
+    fn template_hashes_are_stable() {
+        // A P2PKH with an OP_NOP appended, stored as a template since the start
+        let script = ScriptBuf::from_hex(P2PKH_PLUS).unwrap();
+        let template = template::ScriptTemplate::from_script(&script);
+        assert_eq!(
+            template.template,
+            "OP_DUP OP_HASH160 PUSH(20 bytes) OP_EQUALVERIFY OP_CHECKSIG OP_NOP"
+        );
+        assert_eq!(
+            hex::encode(template.template_hash),
+            "17b3925ded83bf1d44dbe2c9f923debd5ae98ee4c41f9e1ce328ed5c7328a941"
+        );
+    }
+
+    #[test]
+    fn addresses_decode_to_their_hash_and_script_types() {
+        for (address, script_types) in [
+            (
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+                &[ScriptType::P2pkh, ScriptType::NonStandard][..],
+            ),
+            (
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                &[ScriptType::P2sh, ScriptType::P2ms, ScriptType::Unknown][..],
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                &[ScriptType::P2wpkh][..],
+            ),
+            (
+                "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+                &[ScriptType::P2tr][..],
+            ),
+        ] {
+            let (hash, decoded_types) = decode_address(address).unwrap();
+            assert_eq!(decoded_types, script_types);
+            assert_eq!(encode_address(script_types[0], &hash), address);
+        }
+
+        // Testnet addresses are not produced by this project
+        assert!(decode_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").is_none());
+    }
+}
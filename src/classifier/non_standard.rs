@@ -0,0 +1,226 @@
+use bitcoin::blockdata::opcodes::all::*;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::script::Script;
+use tracing::debug;
+
+use crate::script_type::{NonStandardData, ScriptExtraData, ScriptType, UnknownScriptData};
+
+use super::{script_ops, ScriptClassifier, ScriptInfo};
+
+/// Non-standard: P2PKH with extra operations (like the OP_NOP case)
+pub struct P2pkhPlusClassifier;
+
+impl ScriptClassifier for P2pkhPlusClassifier {
+    fn name(&self) -> &'static str {
+        "p2pkh-plus"
+    }
+
+    fn classify(&self, _script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if instructions.len() <= 5
+            || instructions[0].opcode() != Some(OP_DUP)
+            || instructions[1].opcode() != Some(OP_HASH160)
+            || instructions[3].opcode() != Some(OP_EQUALVERIFY)
+            || instructions[4].opcode() != Some(OP_CHECKSIG)
+        {
+            return None;
+        }
+
+        let hash160 = match &instructions[2] {
+            Instruction::PushBytes(hash160) if hash160.len() == 20 => hash160,
+            _ => return None,
+        };
+
+        // Create extra data with script details
+        let script_ops = script_ops(instructions);
+        let extra_ops = script_ops[5..].to_vec();
+
+        debug!("Found non-standard script: {}", script_ops.join(" "));
+        Some(ScriptInfo {
+            // Address from hash160, encoded with the mainnet P2PKH prefix
+            address_hash: hash160.as_bytes().to_vec(),
+            script_type: ScriptType::NonStandard,
+            extra_data: Some(ScriptExtraData::NonStandard(NonStandardData::P2pkhPlus {
+                extra_ops,
+            })),
+            public_keys: Vec::new(),
+        })
+    }
+}
+
+/// Generic non-standard script with a 20-byte hash (likely a pubkey hash)
+pub struct Hash160FoundClassifier;
+
+impl ScriptClassifier for Hash160FoundClassifier {
+    fn name(&self) -> &'static str {
+        "hash160-found"
+    }
+
+    fn classify(&self, _script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        let (hash_position, hash_bytes) =
+            instructions
+                .iter()
+                .enumerate()
+                .find_map(|(i, instruction)| match instruction {
+                    Instruction::PushBytes(bytes) if bytes.len() == 20 => Some((i, bytes)),
+                    _ => None,
+                })?;
+
+        // Create extra data for analysis
+        let script_ops = script_ops(instructions);
+
+        debug!(
+            "Processing non-standard script with 20-byte hash: {}",
+            script_ops.join(" ")
+        );
+
+        Some(ScriptInfo {
+            // A hash160-based address, encoded with the mainnet P2PKH prefix
+            address_hash: hash_bytes.as_bytes().to_vec(),
+            script_type: ScriptType::NonStandard,
+            extra_data: Some(ScriptExtraData::NonStandard(
+                NonStandardData::Hash160Found {
+                    hash_position,
+                    script_ops,
+                },
+            )),
+            public_keys: Vec::new(),
+        })
+    }
+}
+
+/// Catch-all for any other non-empty script, which is identified by a hash of the entire script
+pub struct UnknownScriptClassifier;
+
+impl ScriptClassifier for UnknownScriptClassifier {
+    fn name(&self) -> &'static str {
+        "unknown"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        let script_bytes = script.to_bytes();
+        if script_bytes.is_empty() {
+            // Truly empty or invalid script
+            return None;
+        }
+
+        let script_hash = hash160::Hash::hash(&script_bytes);
+
+        // Log the script pattern
+        let script_ops = script_ops(instructions);
+
+        debug!(
+            "Handling unknown script with custom hash: {}",
+            script_ops.join(" ")
+        );
+
+        Some(ScriptInfo {
+            // Encoded with the P2SH prefix
+            address_hash: script_hash.to_byte_array().to_vec(),
+            script_type: ScriptType::Unknown,
+            extra_data: Some(ScriptExtraData::Unknown(UnknownScriptData {
+                script_pattern: script_ops,
+            })),
+            public_keys: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::ScriptBuf;
+
+    fn classify(classifier: &dyn ScriptClassifier, script_hex: &str) -> Option<ScriptInfo> {
+        let script = ScriptBuf::from_hex(script_hex).unwrap();
+        let instructions: Vec<_> = script.instructions().filter_map(Result::ok).collect();
+        classifier.classify(&script, &instructions)
+    }
+
+    #[test]
+    fn p2pkh_with_extra_operations() {
+        // P2PKH followed by an OP_NOP
+        let info = classify(
+            &P2pkhPlusClassifier,
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac61",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::NonStandard);
+        assert_eq!(
+            info.address_hash,
+            hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap()
+        );
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::NonStandard(NonStandardData::P2pkhPlus {
+                extra_ops: vec!["OP_NOP".to_string()]
+            }))
+        );
+
+        // A plain P2PKH has no extra operations
+        assert!(classify(
+            &P2pkhPlusClassifier,
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn hash160_anywhere_in_the_script() {
+        // A P2PKH missing its OP_CHECKSIG
+        let info = classify(
+            &Hash160FoundClassifier,
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::NonStandard);
+        assert_eq!(
+            info.address_hash,
+            hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap()
+        );
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::NonStandard(
+                NonStandardData::Hash160Found {
+                    hash_position: 2,
+                    script_ops: vec![
+                        "OP_DUP".to_string(),
+                        "OP_HASH160".to_string(),
+                        "PUSH(20 bytes)".to_string(),
+                        "OP_EQUALVERIFY".to_string(),
+                    ],
+                }
+            ))
+        );
+
+        // The SegWit witness commitment pushes 36 bytes, not 20
+        assert!(classify(
+            &Hash160FoundClassifier,
+            "6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn unknown_scripts_are_keyed_by_their_hash() {
+        // The SegWit witness commitment output of a coinbase
+        let script_hex =
+            "6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9";
+        let info = classify(&UnknownScriptClassifier, script_hex).unwrap();
+        assert_eq!(info.script_type, ScriptType::Unknown);
+        assert_eq!(
+            info.address_hash,
+            hash160::Hash::hash(&hex::decode(script_hex).unwrap())
+                .to_byte_array()
+                .to_vec()
+        );
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::Unknown(UnknownScriptData {
+                script_pattern: vec!["OP_RETURN".to_string(), "PUSH(36 bytes)".to_string()]
+            }))
+        );
+
+        assert!(classify(&UnknownScriptClassifier, "").is_none());
+    }
+}
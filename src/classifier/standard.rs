@@ -0,0 +1,437 @@
+use bitcoin::blockdata::opcodes::all::*;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::script::Script;
+use tracing::error;
+
+use crate::pubkey::classify_public_key;
+use crate::script_type::{P2msData, P2pkData, ScriptExtraData, ScriptType};
+
+use super::{ScriptClassifier, ScriptInfo};
+
+/// P2PKH (Pay to Public Key Hash)
+/// P2PKH is of the form: OP_DUP OP_HASH160 <20-byte hash> OP_EQUALVERIFY OP_CHECKSIG
+/// Actually there is a OP_PUSHBYTES_20 before the 20-byte hash
+/// but this is included in the same element as the hash in the instructions vector
+pub struct P2pkhClassifier;
+
+impl ScriptClassifier for P2pkhClassifier {
+    fn name(&self) -> &'static str {
+        "p2pkh"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if !script.is_p2pkh() {
+            return None;
+        }
+
+        // hash160 is the 20-byte hash of the public key
+        match instructions.get(2) {
+            Some(Instruction::PushBytes(hash160)) if hash160.len() == 20 => {
+                // Create address from hash160
+                // https://learnmeabitcoin.com/technical/script/p2pkh/#address
+                // mainnet prefix is 00, 6f for testnet
+                Some(ScriptInfo {
+                    address_hash: hash160.as_bytes().to_vec(),
+                    script_type: ScriptType::P2pkh,
+                    extra_data: None,
+                    public_keys: Vec::new(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// P2SH (Pay to Script Hash)
+/// P2SH is of the form: OP_HASH160 <20-byte hash> OP_EQUAL
+/// Actually there is a OP_PUSHBYTES_20 before the 20-byte hash
+/// but this is included in the same element as the hash in the instructions vector
+pub struct P2shClassifier;
+
+impl ScriptClassifier for P2shClassifier {
+    fn name(&self) -> &'static str {
+        "p2sh"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if !script.is_p2sh() {
+            return None;
+        }
+
+        match instructions.get(1) {
+            Some(Instruction::PushBytes(hash160)) if hash160.len() == 20 => {
+                // Create address from hash160
+                // https://learnmeabitcoin.com/technical/script/p2sh/#address
+                // mainnet p2sh prefix 05, c4 for testnet
+                Some(ScriptInfo {
+                    address_hash: hash160.as_bytes().to_vec(),
+                    script_type: ScriptType::P2sh,
+                    extra_data: None,
+                    public_keys: Vec::new(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// P2PK (Pay to Public Key)
+/// P2PK is of the form: <pubkey> OP_CHECKSIG
+pub struct P2pkClassifier;
+
+impl ScriptClassifier for P2pkClassifier {
+    fn name(&self) -> &'static str {
+        "p2pk"
+    }
+
+    fn classify(&self, _script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        let pubkey_bytes = match instructions {
+            [Instruction::PushBytes(pubkey_bytes), checksig]
+                if checksig.opcode() == Some(OP_CHECKSIG) =>
+            {
+                pubkey_bytes
+            }
+            _ => return None,
+        };
+
+        if pubkey_bytes.len() != 33 && pubkey_bytes.len() != 65 {
+            error!("Invalid P2PK public key length: {}", pubkey_bytes.len());
+            return None;
+        }
+
+        // Store pubkey format as extra data, invalid keys are still classified as P2PK
+        let pubkey_format = classify_public_key(pubkey_bytes.as_bytes());
+
+        Some(ScriptInfo {
+            // Use the pubkey directly as the address, which is its hex encoding
+            address_hash: pubkey_bytes.as_bytes().to_vec(),
+            script_type: ScriptType::P2pk,
+            extra_data: Some(ScriptExtraData::P2pk(P2pkData { pubkey_format })),
+            public_keys: vec![pubkey_bytes.as_bytes().to_vec()],
+        })
+    }
+}
+
+/// P2WPKH (Pay to Witness Public Key Hash)
+/// P2WPKH is of the form: OP_0 <20-byte hash>
+/// https://learnmeabitcoin.com/technical/script/p2wpkh/#address
+pub struct P2wpkhClassifier;
+
+impl ScriptClassifier for P2wpkhClassifier {
+    fn name(&self) -> &'static str {
+        "p2wpkh"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if !script.is_p2wpkh() {
+            return None;
+        }
+
+        match instructions.get(1) {
+            Some(Instruction::PushBytes(witness_program)) if witness_program.len() == 20 => {
+                witness_script_info(ScriptType::P2wpkh, witness_program.as_bytes())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// P2WSH (Pay to Witness Script Hash)
+/// P2WSH is of the form: OP_0 <32-byte hash>
+/// https://learnmeabitcoin.com/technical/script/p2wsh/#address
+pub struct P2wshClassifier;
+
+impl ScriptClassifier for P2wshClassifier {
+    fn name(&self) -> &'static str {
+        "p2wsh"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if !script.is_p2wsh() {
+            return None;
+        }
+
+        match instructions.get(1) {
+            Some(Instruction::PushBytes(witness_program)) if witness_program.len() == 32 => {
+                witness_script_info(ScriptType::P2wsh, witness_program.as_bytes())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// P2TR (Pay to Taproot)
+/// P2TR is of the form: OP_1 <32-byte hash>
+/// https://learnmeabitcoin.com/technical/script/p2tr/#address
+pub struct P2trClassifier;
+
+impl ScriptClassifier for P2trClassifier {
+    fn name(&self) -> &'static str {
+        "p2tr"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if !script.is_witness_program() {
+            return None;
+        }
+
+        match instructions {
+            // Use OP_PUSHNUM_1 instead of OP_1
+            [version, Instruction::PushBytes(taproot_output_key)]
+                if version.opcode() == Some(OP_PUSHNUM_1) && taproot_output_key.len() == 32 =>
+            {
+                witness_script_info(ScriptType::P2tr, taproot_output_key.as_bytes())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Builds the script info of a witness program, which is the address hash
+fn witness_script_info(script_type: ScriptType, program: &[u8]) -> Option<ScriptInfo> {
+    Some(ScriptInfo {
+        address_hash: program.to_vec(),
+        script_type,
+        extra_data: None,
+        public_keys: Vec::new(),
+    })
+}
+
+/// P2MS (Pay to MultiSig)
+/// P2MS is of the form: <m> <pubkey1> ... <pubkeyN> <n> OP_CHECKMULTISIG
+/// https://learnmeabitcoin.com/technical/script/p2ms/#address
+/// Standardness limits bare multisig to 3 public keys, but consensus allows up to 20
+/// and such scripts exist on-chain, so any m-of-n with 1 <= m <= n <= 20 is accepted.
+/// m and n up to 16 are encoded as OP_1..OP_16, 17 to 20 as single byte pushes.
+pub struct P2msClassifier;
+
+impl ScriptClassifier for P2msClassifier {
+    fn name(&self) -> &'static str {
+        "p2ms"
+    }
+
+    fn classify(&self, script: &Script, instructions: &[Instruction]) -> Option<ScriptInfo> {
+        if instructions.len() < 4 || instructions.last()?.opcode() != Some(OP_CHECKMULTISIG) {
+            return None;
+        }
+
+        let m = multisig_count(instructions.first()?)?;
+        let n = multisig_count(&instructions[instructions.len() - 2])?;
+
+        // The public keys are everything between m and n
+        let public_keys: Vec<Vec<u8>> = instructions[1..instructions.len() - 2]
+            .iter()
+            .filter_map(|i| match i {
+                Instruction::PushBytes(bytes) if bytes.len() == 33 || bytes.len() == 65 => {
+                    Some(bytes.as_bytes().to_vec())
+                }
+                _ => None,
+            })
+            .collect();
+
+        // Valid multisig must have m ≤ n and exactly n public keys
+        if m > n || public_keys.len() != n as usize || instructions.len() != n as usize + 3 {
+            return None;
+        }
+
+        // Create a hash of the script to use as an "address"
+        // Encoded with the same prefix as P2SH for consistency
+        let script_hash = hash160::Hash::hash(&script.to_bytes());
+
+        // Store m and n in the extra data
+        Some(ScriptInfo {
+            address_hash: script_hash.to_byte_array().to_vec(),
+            script_type: ScriptType::P2ms,
+            extra_data: Some(ScriptExtraData::P2ms(P2msData { m, n })),
+            public_keys,
+        })
+    }
+}
+
+/// Decode the m or n count of a multisig script (1 to 20)
+fn multisig_count(instruction: &Instruction) -> Option<u8> {
+    let count = match instruction {
+        Instruction::Op(op) => {
+            let code = op.to_u8();
+            // OP_PUSHNUM_1 (0x51) to OP_PUSHNUM_16 (0x60)
+            if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&code) {
+                code - OP_PUSHNUM_1.to_u8() + 1
+            } else {
+                return None;
+            }
+        }
+        Instruction::PushBytes(bytes) if bytes.len() == 1 => bytes.as_bytes()[0],
+        Instruction::PushBytes(_) => return None,
+    };
+
+    (1..=20).contains(&count).then_some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::encode_address;
+    use crate::script_type::PubkeyFormat;
+    use bitcoin::ScriptBuf;
+
+    /// The genesis block's coinbase output key
+    const GENESIS_PUBKEY: &str = "04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f";
+
+    fn classify(classifier: &dyn ScriptClassifier, script_hex: &str) -> Option<ScriptInfo> {
+        let script = ScriptBuf::from_hex(script_hex).unwrap();
+        let instructions: Vec<_> = script.instructions().filter_map(Result::ok).collect();
+        classifier.classify(&script, &instructions)
+    }
+
+    fn address(info: &ScriptInfo) -> String {
+        encode_address(info.script_type, &info.address_hash)
+    }
+
+    #[test]
+    fn p2pkh() {
+        let info = classify(
+            &P2pkhClassifier,
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2pkh);
+        assert_eq!(address(&info), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+        assert!(info.extra_data.is_none());
+
+        assert!(classify(
+            &P2pkhClassifier,
+            "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn p2sh() {
+        let info = classify(
+            &P2shClassifier,
+            "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2sh);
+        assert_eq!(address(&info), "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy");
+
+        assert!(classify(
+            &P2shClassifier,
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn p2pk() {
+        let info = classify(&P2pkClassifier, &format!("41{}ac", GENESIS_PUBKEY)).unwrap();
+        assert_eq!(info.script_type, ScriptType::P2pk);
+        assert_eq!(address(&info), GENESIS_PUBKEY);
+        assert_eq!(info.public_keys, vec![hex::decode(GENESIS_PUBKEY).unwrap()]);
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::P2pk(P2pkData {
+                pubkey_format: PubkeyFormat::Uncompressed
+            }))
+        );
+
+        // Data of another length pushed before OP_CHECKSIG is not a key
+        assert!(classify(&P2pkClassifier, &format!("40{}ac", &GENESIS_PUBKEY[2..])).is_none());
+    }
+
+    #[test]
+    fn p2wpkh() {
+        let info = classify(
+            &P2wpkhClassifier,
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2wpkh);
+        assert_eq!(address(&info), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+    }
+
+    #[test]
+    fn p2wsh() {
+        let info = classify(
+            &P2wshClassifier,
+            "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2wsh);
+        assert_eq!(
+            address(&info),
+            "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"
+        );
+
+        assert!(classify(
+            &P2wshClassifier,
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn p2tr() {
+        let info = classify(
+            &P2trClassifier,
+            "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2tr);
+        assert_eq!(
+            address(&info),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+
+        // Other witness versions are not taproot
+        assert!(classify(
+            &P2trClassifier,
+            "5220a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn p2ms() {
+        // 1-of-2 of an uncompressed and a compressed key
+        let generator = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let info = classify(
+            &P2msClassifier,
+            &format!("5141{}21{}52ae", GENESIS_PUBKEY, generator),
+        )
+        .unwrap();
+        assert_eq!(info.script_type, ScriptType::P2ms);
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::P2ms(P2msData { m: 1, n: 2 }))
+        );
+        assert_eq!(
+            info.public_keys,
+            vec![
+                hex::decode(GENESIS_PUBKEY).unwrap(),
+                hex::decode(generator).unwrap()
+            ]
+        );
+
+        // 17-of-17, with the counts above 16 pushed as a single byte
+        let keys = format!("21{}", generator).repeat(17);
+        let info = classify(&P2msClassifier, &format!("0111{}0111ae", keys)).unwrap();
+        assert_eq!(
+            info.extra_data,
+            Some(ScriptExtraData::P2ms(P2msData { m: 17, n: 17 }))
+        );
+
+        // n must match the number of keys, and m must not exceed it
+        assert!(classify(
+            &P2msClassifier,
+            &format!("5141{}21{}53ae", GENESIS_PUBKEY, generator)
+        )
+        .is_none());
+        assert!(classify(
+            &P2msClassifier,
+            &format!("5341{}21{}52ae", GENESIS_PUBKEY, generator)
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,35 @@
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::script::Script;
+
+use super::script_ops;
+
+/// Number of example transactions kept for each template
+pub const MAX_TEMPLATE_EXAMPLES: i32 = 5;
+
+/// Fingerprint of a script: its opcodes with the size of each data push, the data itself
+/// abstracted away, so that scripts following the same pattern share a template
+pub struct ScriptTemplate {
+    pub template: String,       // e.g. "OP_RETURN PUSH(4 bytes)"
+    pub template_hash: Vec<u8>, // SHA256 of the template text
+}
+
+impl ScriptTemplate {
+    /// Reduces a script to its template
+    pub fn from_script(script: &Script) -> Self {
+        let instructions = script
+            .instructions()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        // Same format as the script operations stored in the extra data of non-standard scripts
+        let template = script_ops(&instructions).join(" ");
+        let template_hash = sha256::Hash::hash(template.as_bytes())
+            .to_byte_array()
+            .to_vec();
+
+        Self {
+            template,
+            template_hash,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+/// A cluster taking part in a merge: an existing cluster, or an address on its own
+pub struct ClusterSize {
+    pub cluster_id: i64,
+    pub address_count: i32,
+}
+
+/// Picks the cluster that the others are merged into: the largest, so that the fewest
+/// addresses are relabelled, with ties going to the oldest (lowest) id
+pub fn merge_target(clusters: &[ClusterSize]) -> Option<i64> {
+    clusters
+        .iter()
+        .max_by(|a, b| {
+            a.address_count
+                .cmp(&b.address_count)
+                .then(b.cluster_id.cmp(&a.cluster_id))
+        })
+        .map(|c| c.cluster_id)
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+
+use crate::db::OutputInfo;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+pub const SATOSHIS_PER_BTC: f64 = 100_000_000.0;
+
+/// How long the coins spent by an input sat unspent
+pub struct CoinAge {
+    pub spent_output_block_height: i32,
+    pub spent_output_timestamp: NaiveDateTime,
+    pub age_blocks: i32,
+    pub age_seconds: i64, // Negative if the spending block's timestamp is the earlier one
+}
+
+impl CoinAge {
+    /// The age of an output spent in the block at `block_height` with unix time `block_time`
+    pub fn new(spent: &OutputInfo, block_height: i32, block_time: i64) -> Self {
+        Self {
+            spent_output_block_height: spent.block_height,
+            spent_output_timestamp: spent.block_timestamp,
+            age_blocks: block_height - spent.block_height,
+            age_seconds: block_time - spent.block_timestamp.and_utc().timestamp(),
+        }
+    }
+
+    /// Coin days destroyed by spending `value_satoshis` of this age: the BTC spent times the
+    /// days it sat unspent. Block timestamps are not monotonic, so negative ages count as zero.
+    pub fn coin_days_destroyed(&self, value_satoshis: i64) -> f64 {
+        let age_days = self.age_seconds.max(0) as f64 / SECONDS_PER_DAY;
+        value_satoshis as f64 / SATOSHIS_PER_BTC * age_days
+    }
+}
@@ -0,0 +1,97 @@
+use bitcoin::blockdata::constants::SUBSIDY_HALVING_INTERVAL;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::{Amount, Block};
+
+pub mod miners;
+
+/// Start of a BIP141 witness commitment output: OP_RETURN, a 36 byte push and the commitment header
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Shortest run of printable ASCII in a coinbase scriptSig kept as a tag
+const MIN_TAG_LENGTH: usize = 4;
+
+/// Data extracted from the coinbase transaction of a block
+pub struct CoinbaseInfo {
+    pub script_sig: Vec<u8>,
+    pub bip34_height: Option<i32>, // Height pushed first in the scriptSig (version 2+ blocks)
+    pub ascii_tags: Vec<String>,   // Runs of printable ASCII in the scriptSig
+    pub extranonce: Option<Vec<u8>>,
+    pub has_witness_commitment: bool,
+    pub output_value_satoshis: i64,
+    pub subsidy_satoshis: i64, // Subsidy the miner was entitled to at this height
+}
+
+impl CoinbaseInfo {
+    /// Extracts the coinbase data of a block, or None if the block has no coinbase
+    pub fn from_block(block: &Block, height: u32) -> Option<Self> {
+        let coinbase = block.coinbase()?;
+        let script_sig = &coinbase.input.first()?.script_sig;
+
+        let instructions: Vec<_> = script_sig.instructions().collect();
+        let parses = instructions.iter().all(Result::is_ok);
+        let pushes: Vec<&[u8]> = instructions
+            .into_iter()
+            .map_while(Result::ok)
+            .filter_map(|instruction| match instruction {
+                Instruction::PushBytes(bytes) => Some(bytes.as_bytes()),
+                Instruction::Op(_) => None,
+            })
+            .collect();
+
+        // Tags are read from the pushed data so that push lengths are not mistaken for text,
+        // unless the scriptSig is not a valid script
+        let ascii_tags = if parses {
+            pushes.iter().flat_map(|push| ascii_tags(push)).collect()
+        } else {
+            ascii_tags(script_sig.as_bytes())
+        };
+
+        // Most miners put the extranonce in the push after the height (or after nBits,
+        // which early miners pushed first before BIP34)
+        let extranonce = pushes
+            .get(1)
+            .filter(|push| !push.is_empty() && push.len() <= 16)
+            .map(|push| push.to_vec());
+
+        // BIP141: blocks with witness data commit to it in an OP_RETURN output of the coinbase
+        let has_witness_commitment = coinbase.output.iter().any(|output| {
+            output.script_pubkey.len() >= 38
+                && output.script_pubkey.as_bytes()[..6] == WITNESS_COMMITMENT_PREFIX
+        });
+
+        Some(Self {
+            script_sig: script_sig.to_bytes(),
+            bip34_height: block
+                .bip34_block_height()
+                .ok()
+                .and_then(|h| i32::try_from(h).ok()),
+            ascii_tags,
+            extranonce,
+            has_witness_commitment,
+            output_value_satoshis: coinbase
+                .output
+                .iter()
+                .map(|o| o.value.to_sat() as i64)
+                .sum(),
+            subsidy_satoshis: block_subsidy(height) as i64,
+        })
+    }
+}
+
+/// Block subsidy at a height, halving every 210,000 blocks until it reaches zero
+pub fn block_subsidy(height: u32) -> u64 {
+    let halvings = height / SUBSIDY_HALVING_INTERVAL;
+    if halvings >= 64 {
+        return 0;
+    }
+    (Amount::ONE_BTC.to_sat() * 50) >> halvings
+}
+
+/// Splits a scriptSig into the runs of printable ASCII long enough to be a miner's tag
+fn ascii_tags(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|b| !(0x20..=0x7e).contains(b))
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .filter(|tag| tag.len() >= MIN_TAG_LENGTH)
+        .collect()
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use config::{Config, File, FileFormat};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+/// Environment variable holding the path of the mining pool list
+pub const MINERS_FILE_ENV: &str = "MINING_POOLS_FILE";
+
+/// Mining pool list used when `MINING_POOLS_FILE` is not set
+pub const DEFAULT_MINERS_FILE: &str = "mining_pools.toml";
+
+/// A miner or pool, recognised by the tags it puts in its coinbase scriptSig or the addresses it pays to
+#[derive(Deserialize, Debug)]
+pub struct Miner {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub payout_addresses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MinersFile {
+    #[serde(default)]
+    pools: Vec<Miner>,
+}
+
+/// How a block was attributed to a miner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum MinerMatch {
+    /// The coinbase pays to one of the miner's payout addresses
+    PayoutAddress,
+    /// The coinbase scriptSig contains one of the miner's tags
+    Tag,
+}
+
+impl MinerMatch {
+    /// The value stored in the database for this kind of match
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MinerMatch::PayoutAddress => "payout_address",
+            MinerMatch::Tag => "tag",
+        }
+    }
+}
+
+impl fmt::Display for MinerMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for MinerMatch {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for MinerMatch {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "payout_address" => Ok(MinerMatch::PayoutAddress),
+            "tag" => Ok(MinerMatch::Tag),
+            other => Err(format!("Unknown miner match '{}'", other).into()),
+        }
+    }
+}
+
+/// The miner a block was attributed to, and the tag or address that identified it
+pub struct MinerAttribution<'a> {
+    pub miner_name: &'a str,
+    pub matched_by: MinerMatch,
+    pub matched_value: &'a str,
+}
+
+/// The configured list of miners that blocks are attributed to
+pub struct MinerRegistry {
+    miners: Vec<Miner>,
+}
+
+impl MinerRegistry {
+    /// A registry without any miners, so that no block is attributed
+    pub fn empty() -> Self {
+        Self { miners: Vec::new() }
+    }
+
+    /// Loads the miners from a TOML file with a `[[pools]]` table per miner
+    pub fn from_file(path: &str) -> Result<Self> {
+        let file: MinersFile = Config::builder()
+            .add_source(File::new(path, FileFormat::Toml))
+            .build()
+            .and_then(Config::try_deserialize)
+            .with_context(|| format!("Failed to load mining pools from {}", path))?;
+
+        Ok(Self { miners: file.pools })
+    }
+
+    /// Loads the miners from the file named by `MINING_POOLS_FILE`. Without it, the default
+    /// file is used if present, and otherwise blocks are not attributed.
+    pub fn from_env() -> Result<Self> {
+        let registry = match env::var(MINERS_FILE_ENV) {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_MINERS_FILE).exists() => {
+                Self::from_file(DEFAULT_MINERS_FILE)?
+            }
+            Err(_) => Self::empty(),
+        };
+
+        info!("Loaded {} mining pools", registry.miners.len());
+        Ok(registry)
+    }
+
+    /// Finds the miner of a block from its coinbase tags and payout addresses.
+    /// Payout addresses are checked first, as tags are easily copied.
+    pub fn identify<'a>(
+        &'a self,
+        ascii_tags: &'a [String],
+        payout_addresses: &'a [String],
+    ) -> Option<MinerAttribution<'a>> {
+        let by_address = self.miners.iter().find_map(|miner| {
+            payout_addresses
+                .iter()
+                .find(|address| miner.payout_addresses.contains(address))
+                .map(|address| MinerAttribution {
+                    miner_name: &miner.name,
+                    matched_by: MinerMatch::PayoutAddress,
+                    matched_value: address,
+                })
+        });
+
+        by_address.or_else(|| {
+            self.miners.iter().find_map(|miner| {
+                miner
+                    .tags
+                    .iter()
+                    .find(|tag| ascii_tags.iter().any(|found| found.contains(tag.as_str())))
+                    .map(|tag| MinerAttribution {
+                        miner_name: &miner.name,
+                        matched_by: MinerMatch::Tag,
+                        matched_value: tag,
+                    })
+            })
+        })
+    }
+}
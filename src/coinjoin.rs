@@ -0,0 +1,326 @@
+use bitcoin::{Address, ScriptBuf, Transaction};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Whirlpool pool denominations (0.001, 0.01, 0.05 and 0.5 BTC)
+const WHIRLPOOL_DENOMINATIONS: [u64; 4] = [100_000, 1_000_000, 5_000_000, 50_000_000];
+
+/// Inputs and outputs of a Whirlpool mix
+const WHIRLPOOL_MIX_SIZE: usize = 5;
+
+/// Addresses the Wasabi coordinator collected its fees at
+const WASABI_COORDINATOR_ADDRESSES: [&str; 2] = [
+    "bc1qs604c7jv6amk4cxqlnvuxv26hv3e48cds4m0ew",
+    "bc1qa24tsgchvuxsaccp8vrnkfd85hrcpafg20kmjw",
+];
+
+/// Wasabi 1 mixed around 0.1 BTC, adjusted per round within a few percent
+const WASABI_DENOMINATION_SATOSHIS: u64 = 10_000_000;
+const WASABI_DENOMINATION_TOLERANCE_SATOSHIS: u64 = 300_000;
+const WASABI_MIN_ANONYMITY_SET: usize = 10;
+
+/// WabiSabi (Wasabi 2) rounds have many inputs and outputs over several denominations
+const WABISABI_MIN_INPUTS: usize = 50;
+const WABISABI_MIN_OUTPUTS: usize = 50;
+
+/// JoinMarket has a taker and at least two makers, each with at most one change output
+const JOINMARKET_MIN_ANONYMITY_SET: usize = 3;
+
+/// Without a known shape, fewer equal outputs are as likely a payment whose change equals the
+/// amount paid, or a payout of a few round amounts
+const GENERIC_MIN_ANONYMITY_SET: usize = 5;
+
+/// Equal outputs below this (JoinMarket's default minimum maker offer) are dust or spam rather
+/// than a mix denomination, unless the shape is a known implementation's
+const MIN_DENOMINATION_SATOSHIS: u64 = 100_000;
+
+/// The implementation a CoinJoin was made with, as far as its shape tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum CoinJoinKind {
+    /// 5 inputs and 5 outputs of a pool denomination
+    Whirlpool,
+    /// Pays the Wasabi coordinator, or mixes many ~0.1 BTC outputs (Wasabi 1) or has many
+    /// inputs and outputs (WabiSabi)
+    Wasabi,
+    /// Equal outputs for a taker and its makers, plus at most one change output each
+    JoinMarket,
+    /// At least five equal outputs funded by at least as many inputs
+    Generic,
+}
+
+impl CoinJoinKind {
+    /// The value stored in the database for this kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoinJoinKind::Whirlpool => "whirlpool",
+            CoinJoinKind::Wasabi => "wasabi",
+            CoinJoinKind::JoinMarket => "joinmarket",
+            CoinJoinKind::Generic => "generic",
+        }
+    }
+}
+
+impl fmt::Display for CoinJoinKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for CoinJoinKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for CoinJoinKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "whirlpool" => Ok(CoinJoinKind::Whirlpool),
+            "wasabi" => Ok(CoinJoinKind::Wasabi),
+            "joinmarket" => Ok(CoinJoinKind::JoinMarket),
+            "generic" => Ok(CoinJoinKind::Generic),
+            other => Err(format!("Unknown CoinJoin type '{}'", other).into()),
+        }
+    }
+}
+
+/// A transaction detected as a CoinJoin
+pub struct CoinJoin {
+    pub kind: CoinJoinKind,
+    pub denomination_satoshis: u64, // Value of the most common equal output
+    pub anonymity_set: usize,       // Outputs of that value
+    pub coordinator_fee_satoshis: Option<u64>, // Paid to a known coordinator address
+}
+
+impl CoinJoin {
+    /// Value mixed by the transaction: the equal outputs together
+    pub fn mixed_value_satoshis(&self) -> u64 {
+        self.denomination_satoshis * self.anonymity_set as u64
+    }
+}
+
+/// Detects whether a transaction is a CoinJoin, whose inputs belong to several participants.
+/// Participants each receive an output of the same value, so equal outputs funded by at least as
+/// many inputs are a candidate; the shape then tells the implementation apart. Candidates of no
+/// known shape need a larger anonymity set and denomination, to leave out ordinary payments.
+pub fn detect(tx: &Transaction) -> Option<CoinJoin> {
+    if tx.is_coinbase() {
+        return None;
+    }
+
+    let mut value_counts: HashMap<u64, usize> = HashMap::new();
+    for output in &tx.output {
+        let value = output.value.to_sat();
+        if value > 0 {
+            *value_counts.entry(value).or_default() += 1;
+        }
+    }
+
+    // The most common value, the larger one on a tie
+    let (denomination_satoshis, anonymity_set) = value_counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)))?;
+    if anonymity_set < 2 || tx.input.len() < anonymity_set {
+        return None;
+    }
+
+    let coordinator_fee_satoshis = tx
+        .output
+        .iter()
+        .filter(|output| wasabi_coordinator_scripts().contains(&output.script_pubkey))
+        .map(|output| output.value.to_sat())
+        .reduce(|a, b| a + b);
+
+    let kind = if tx.input.len() == WHIRLPOOL_MIX_SIZE
+        && tx.output.len() == WHIRLPOOL_MIX_SIZE
+        && anonymity_set == WHIRLPOOL_MIX_SIZE
+        && WHIRLPOOL_DENOMINATIONS.contains(&denomination_satoshis)
+    {
+        CoinJoinKind::Whirlpool
+    } else if coordinator_fee_satoshis.is_some()
+        || (anonymity_set >= WASABI_MIN_ANONYMITY_SET
+            && denomination_satoshis.abs_diff(WASABI_DENOMINATION_SATOSHIS)
+                <= WASABI_DENOMINATION_TOLERANCE_SATOSHIS)
+        || (tx.input.len() >= WABISABI_MIN_INPUTS && tx.output.len() >= WABISABI_MIN_OUTPUTS)
+    {
+        CoinJoinKind::Wasabi
+    } else if denomination_satoshis < MIN_DENOMINATION_SATOSHIS {
+        return None;
+    } else if anonymity_set >= JOINMARKET_MIN_ANONYMITY_SET && tx.output.len() <= anonymity_set * 2
+    {
+        CoinJoinKind::JoinMarket
+    } else if anonymity_set >= GENERIC_MIN_ANONYMITY_SET {
+        CoinJoinKind::Generic
+    } else {
+        return None;
+    };
+
+    Some(CoinJoin {
+        kind,
+        denomination_satoshis,
+        anonymity_set,
+        coordinator_fee_satoshis,
+    })
+}
+
+/// Output scripts of the Wasabi coordinator addresses, parsed once
+fn wasabi_coordinator_scripts() -> &'static [ScriptBuf] {
+    static SCRIPTS: OnceLock<Vec<ScriptBuf>> = OnceLock::new();
+    SCRIPTS.get_or_init(|| {
+        WASABI_COORDINATOR_ADDRESSES
+            .iter()
+            .filter_map(|address| Address::from_str(address).ok())
+            .map(|address| address.assume_checked().script_pubkey())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, Sequence, TxIn, TxOut, Txid, WPubkeyHash, Witness};
+
+    /// A transaction spending `inputs` distinct outputs into outputs of the given values, each
+    /// paying its own P2WPKH address
+    fn transaction(inputs: usize, values: &[u64]) -> Transaction {
+        Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: (0..inputs)
+                .map(|i| TxIn {
+                    previous_output: OutPoint::new(Txid::from_byte_array([i as u8 + 1; 32]), 0),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| TxOut {
+                    value: Amount::from_sat(*value),
+                    script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array(
+                        [i as u8; 20],
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn whirlpool_mix_of_a_pool_denomination() {
+        // A 0.05 BTC pool mix: the premixed inputs carry the miner fee, the outputs are exact
+        let tx = transaction(5, &[5_000_000; 5]);
+
+        let coinjoin = detect(&tx).unwrap();
+        assert_eq!(coinjoin.kind, CoinJoinKind::Whirlpool);
+        assert_eq!(coinjoin.denomination_satoshis, 5_000_000);
+        assert_eq!(coinjoin.anonymity_set, 5);
+        assert_eq!(coinjoin.mixed_value_satoshis(), 25_000_000);
+    }
+
+    #[test]
+    fn wasabi_round_paying_the_coordinator() {
+        // Wasabi 1: ~0.1 BTC outputs, change, and the coordinator fee
+        let mut values = vec![9_991_272; 40];
+        values.extend([3_125_490, 48_221_007, 712_334]);
+        let mut tx = transaction(45, &values);
+        tx.output.push(TxOut {
+            value: Amount::from_sat(1_198_952),
+            script_pubkey: wasabi_coordinator_scripts()[0].clone(),
+        });
+
+        let coinjoin = detect(&tx).unwrap();
+        assert_eq!(coinjoin.kind, CoinJoinKind::Wasabi);
+        assert_eq!(coinjoin.denomination_satoshis, 9_991_272);
+        assert_eq!(coinjoin.anonymity_set, 40);
+        assert_eq!(coinjoin.coordinator_fee_satoshis, Some(1_198_952));
+    }
+
+    #[test]
+    fn wabisabi_round_of_standard_denominations() {
+        // WabiSabi: many inputs and outputs over several denominations, some of them small
+        let mut values = Vec::new();
+        for (denomination, count) in [(5_000, 12), (10_000, 8), (200_000, 20), (1_062_882, 15)] {
+            values.extend(std::iter::repeat_n(denomination, count));
+        }
+        let tx = transaction(60, &values);
+
+        let coinjoin = detect(&tx).unwrap();
+        assert_eq!(coinjoin.kind, CoinJoinKind::Wasabi);
+        assert_eq!(coinjoin.denomination_satoshis, 200_000);
+        assert_eq!(coinjoin.anonymity_set, 20);
+        assert_eq!(coinjoin.coordinator_fee_satoshis, None);
+    }
+
+    #[test]
+    fn joinmarket_taker_and_makers_with_change() {
+        // A taker and three makers each get the coinjoin amount, three of them take change
+        let tx = transaction(
+            6,
+            &[
+                27_419_801, 27_419_801, 27_419_801, 27_419_801, 1_845_207, 90_113_462, 3_550_018,
+            ],
+        );
+
+        let coinjoin = detect(&tx).unwrap();
+        assert_eq!(coinjoin.kind, CoinJoinKind::JoinMarket);
+        assert_eq!(coinjoin.denomination_satoshis, 27_419_801);
+        assert_eq!(coinjoin.anonymity_set, 4);
+    }
+
+    #[test]
+    fn generic_needs_a_larger_anonymity_set() {
+        // Five equal outputs with more change than JoinMarket makes
+        let mut values = vec![10_000_000; 5];
+        values.extend([
+            1_000_001, 1_000_002, 1_000_003, 1_000_004, 1_000_005, 1_000_006,
+        ]);
+        let tx = transaction(6, &values);
+        assert_eq!(detect(&tx).unwrap().kind, CoinJoinKind::Generic);
+
+        // Four are not enough without a known shape
+        let mut values = vec![10_000_000; 4];
+        values.extend([1_000_001, 1_000_002, 1_000_003, 1_000_004, 1_000_005]);
+        let tx = transaction(6, &values);
+        assert!(detect(&tx).is_none());
+    }
+
+    #[test]
+    fn payment_with_change_equal_to_the_amount_is_not_a_coinjoin() {
+        // 2 inputs paying 0.01 BTC, with 0.01 BTC coming back as change
+        let tx = transaction(2, &[1_000_000, 1_000_000]);
+        assert!(detect(&tx).is_none());
+    }
+
+    #[test]
+    fn batch_payout_funded_by_few_inputs_is_not_a_coinjoin() {
+        // An exchange paying out five round amounts from two inputs
+        let tx = transaction(
+            2,
+            &[
+                5_000_000, 5_000_000, 5_000_000, 5_000_000, 5_000_000, 81_334,
+            ],
+        );
+        assert!(detect(&tx).is_none());
+    }
+
+    #[test]
+    fn dust_outputs_are_not_a_denomination() {
+        let tx = transaction(10, &[546; 10]);
+        assert!(detect(&tx).is_none());
+    }
+}
@@ -1,17 +1,41 @@
 use anyhow::{Context, Result};
-use chrono;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use hex;
-use serde_json::Value;
+use std::collections::BTreeSet;
 use std::time::Duration;
 use tracing::info;
 
+use crate::bip30;
+use crate::change::ChangeLabel;
+use crate::classifier::decode_address;
+use crate::classifier::template::{ScriptTemplate, MAX_TEMPLATE_EXAMPLES};
+use crate::cluster::{merge_target, ClusterSize};
+use crate::coin_age::CoinAge;
+use crate::coinbase::miners::MinerAttribution;
+use crate::coinbase::CoinbaseInfo;
+use crate::coinjoin::CoinJoin;
+use crate::inscription::Envelope;
+use crate::pubkey::{classify_public_key, sibling_addresses, SIBLING_SCRIPT_TYPES};
+use crate::script_type::{ExposureSource, P2msData, PubkeyFormat, ScriptExtraData, ScriptType};
+use crate::signature::InputSignature;
+use crate::timelock::{InputSequence, TransactionTimelocks};
+use crate::utxo_age::{AgeBandTotals, UtxoAgeDay};
+
 // Define migrations
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 
+/// Blocks per partition of the tables partitioned by height
+pub const PARTITION_BLOCKS: i32 = 50_000;
+
+/// Tables partitioned by ranges of block heights. `txid_block_index` is not: it is what finds
+/// the height, and so the partition, of a spent output.
+pub const PARTITIONED_TABLES: [&str; 3] = ["transactions", "address_outputs", "address_inputs"];
+
+/// Schema that archived partitions are moved to
+pub const ARCHIVE_SCHEMA: &str = "archive";
+
 // Define database schema (will be populated by diesel)
 pub mod models;
 pub mod schema;
@@ -46,6 +70,36 @@ pub fn run_migrations(conn: &mut PgConnection) -> Result<()> {
     }
 }
 
+/// Checks that the `script_types` table and the `ScriptType` enum agree,
+/// so that every script type the processor produces can be stored
+pub fn verify_script_types(conn: &mut PgConnection) -> Result<()> {
+    use schema::script_types::dsl::*;
+
+    let db_types: BTreeSet<String> = script_types
+        .select(script_type)
+        .load::<String>(conn)
+        .context("Failed to query script types")?
+        .into_iter()
+        .collect();
+    let enum_types: BTreeSet<String> = ScriptType::ALL
+        .iter()
+        .map(|t| t.as_str().to_string())
+        .collect();
+
+    let missing_in_db: Vec<_> = enum_types.difference(&db_types).collect();
+    let missing_in_enum: Vec<_> = db_types.difference(&enum_types).collect();
+    if !missing_in_db.is_empty() || !missing_in_enum.is_empty() {
+        anyhow::bail!(
+            "script_types table and ScriptType enum disagree: missing in table {:?}, missing in enum {:?}",
+            missing_in_db,
+            missing_in_enum
+        );
+    }
+
+    info!("Verified {} script types", enum_types.len());
+    Ok(())
+}
+
 /// Gets the last processed block height from the database
 pub fn get_last_processed_height(conn: &mut PgConnection) -> Result<Option<u32>> {
     use schema::blocks::dsl::*;
@@ -61,20 +115,32 @@ pub fn get_last_processed_height(conn: &mut PgConnection) -> Result<Option<u32>>
     Ok(result.map(|h| h as u32))
 }
 
-/// Stores a new processed block in the database
+/// Stores a new processed block in the database.
+/// Returns true if the block was already stored, i.e. it is being reprocessed.
 pub fn store_processed_block(
     conn: &mut PgConnection,
     block_height_val: u32,
     block_hash_val: &str,
     block_timestamp_val: i64,
     tx_count_val: u32,
-) -> Result<()> {
+) -> Result<bool> {
     use diesel::insert_into;
     use schema::blocks::dsl::*;
 
     let block_hash_bytes =
         hex::decode(block_hash_val).context("Failed to decode block hash hex string")?;
 
+    // A block is stored in the same database transaction as its contents, so a stored block
+    // was fully processed
+    // DB QUERY!
+    let reprocessing = blocks
+        .find(block_height_val as i32)
+        .select(block_height)
+        .first::<i32>(conn)
+        .optional()
+        .context("Failed to query block")?
+        .is_some();
+
     let new_block_record = models::Block {
         block_height: block_height_val as i32,
         block_hash: block_hash_bytes,
@@ -96,18 +162,17 @@ pub fn store_processed_block(
         .execute(conn)
         .context("Failed to store block")?;
 
-    Ok(())
+    Ok(reprocessing)
 }
 
-/// Stores details of a single transaction in the database
+/// Stores details of a single transaction in the database, including its size, weight and timelocks.
+/// The Taproot input count starts at zero and is set once the spent outputs are known.
 pub fn store_transaction(
     conn: &mut PgConnection,
     block_height_val: u32,
     tx_index_val: u32,
     tx_id_str: &str,
-    is_coinbase_val: bool,
-    input_count_val: i32,
-    output_count_val: i32,
+    tx: &bitcoin::Transaction,
     fee_satoshis_val: Option<i64>,
 ) -> Result<()> {
     use crate::db::models::NewTransaction;
@@ -117,20 +182,63 @@ pub fn store_transaction(
     let tx_id_bytes =
         hex::decode(tx_id_str).context("Failed to decode transaction ID hex string")?;
 
+    let is_coinbase_val = tx.is_coinbase();
+    let base_size_val = tx.base_size() as i32;
+    let total_size_val = tx.total_size() as i32;
+
+    let timelocks = TransactionTimelocks::new(tx, block_height_val);
+
+    // The coinbase witness only holds the witness reserved value, it spends nothing
+    let segwit_input_count_val = if is_coinbase_val {
+        0
+    } else {
+        tx.input.iter().filter(|i| !i.witness.is_empty()).count() as i32
+    };
+
     let new_tx_record = NewTransaction {
         transaction_id: tx_id_bytes.clone(),
         block_height: block_height_val as i32,
         transaction_index: tx_index_val as i32,
         is_coinbase: is_coinbase_val,
-        input_count: input_count_val,
-        output_count: output_count_val,
+        input_count: tx.input.len() as i32,
+        output_count: tx.output.len() as i32,
         fee_satoshis: fee_satoshis_val,
+        base_size: base_size_val,
+        total_size: total_size_val,
+        witness_size: total_size_val - base_size_val,
+        weight: tx.weight().to_wu() as i32,
+        vsize: tx.vsize() as i32,
+        segwit_input_count: segwit_input_count_val,
+        taproot_input_count: 0,
+        version: timelocks.version,
+        lock_time: timelocks.lock_time as i64,
+        lock_time_type: timelocks.lock_time_kind,
+        is_lock_time_enforced: timelocks.is_lock_time_enforced,
+        is_anti_fee_sniping: timelocks.is_anti_fee_sniping,
+        signals_rbf: timelocks.signals_rbf,
     };
 
+    // A transaction stored again, when its block is reprocessed, is updated in place
+    // DB UPSERT!
     insert_into(transactions)
         .values(&new_tx_record)
         .on_conflict((transaction_id, block_height))
-        .do_nothing()
+        .do_update()
+        .set((
+            base_size.eq(new_tx_record.base_size),
+            total_size.eq(new_tx_record.total_size),
+            witness_size.eq(new_tx_record.witness_size),
+            weight.eq(new_tx_record.weight),
+            vsize.eq(new_tx_record.vsize),
+            segwit_input_count.eq(new_tx_record.segwit_input_count),
+            taproot_input_count.eq(new_tx_record.taproot_input_count),
+            version.eq(new_tx_record.version),
+            lock_time.eq(new_tx_record.lock_time),
+            lock_time_type.eq(new_tx_record.lock_time_type),
+            is_lock_time_enforced.eq(new_tx_record.is_lock_time_enforced),
+            is_anti_fee_sniping.eq(new_tx_record.is_anti_fee_sniping),
+            signals_rbf.eq(new_tx_record.signals_rbf),
+        ))
         .execute(conn)
         .context(format!("Failed to store transaction {}", tx_id_str))?;
 
@@ -140,6 +248,32 @@ pub fn store_transaction(
     Ok(())
 }
 
+/// Sets the number of inputs of a transaction that spend Taproot outputs
+pub fn update_transaction_taproot_inputs(
+    conn: &mut PgConnection,
+    block_height_val: i32,
+    tx_id_str: &str,
+    taproot_input_count_val: i32,
+) -> Result<()> {
+    use diesel::update;
+    use schema::transactions::dsl::*;
+
+    let tx_id_bytes =
+        hex::decode(tx_id_str).context("Failed to decode transaction ID hex string")?;
+
+    // DB UPDATE!
+    update(
+        transactions
+            .filter(transaction_id.eq(tx_id_bytes))
+            .filter(block_height.eq(block_height_val)),
+    )
+    .set(taproot_input_count.eq(taproot_input_count_val))
+    .execute(conn)
+    .context("Failed to update transaction Taproot input count")?;
+
+    Ok(())
+}
+
 /// Add a transaction ID to the TXID index table
 pub fn add_txid_to_index(
     conn: &mut PgConnection,
@@ -160,19 +294,26 @@ pub fn add_txid_to_index(
         .on_conflict((transaction_id, block_height))
         .do_nothing()
         .execute(conn)
-        .context(format!("Failed to add TXID to index"))?;
+        .context("Failed to add TXID to index")?;
 
     Ok(())
 }
 
-/// Gets or creates an address record, returning the address_id
+/// Gets or creates an address record, returning the address_id and whether it was created.
+/// Addresses are keyed by their script type and address hash (see `classifier::encode_address`).
+/// `embedded_public_keys` are the public keys visible in the scriptPubKey itself, which
+/// expose a new address from its creation if any of them is a valid key.
+/// A single embedded key (P2PK) is stored on the address.
+/// `script_template_hash_val` links non-standard and unknown scripts to their template.
 pub fn get_or_create_address(
     conn: &mut PgConnection,
-    address_string_val: &str,
-    script_type_val: &str,
+    address_hash_val: &[u8],
+    script_type_val: ScriptType,
     first_seen_block_height_val: u32,
-    extra_data_val: Option<Value>,
-) -> Result<i64> {
+    extra_data_val: Option<ScriptExtraData>,
+    embedded_public_keys: &[Vec<u8>],
+    script_template_hash_val: Option<Vec<u8>>,
+) -> Result<AddressLookup> {
     use crate::db::models::NewAddress;
     use diesel::insert_into;
     use schema::addresses::dsl::*;
@@ -180,35 +321,216 @@ pub fn get_or_create_address(
     // 1. Try to find the address
     // DB QUERY!
     let existing_address = addresses
-        .filter(address_string.eq(address_string_val))
-        .select(address_id)
-        .first::<i64>(conn)
+        .filter(address_hash.eq(address_hash_val))
+        .filter(script_type.eq(script_type_val))
+        .select((
+            address_id,
+            first_seen_block_height,
+            is_public_key_exposed,
+            public_key_exposure_source,
+        ))
+        .first::<(i64, i32, bool, Option<ExposureSource>)>(conn)
         .optional()
         .context("Failed to query address")?;
 
-    if let Some(id) = existing_address {
+    if let Some((id, first_seen, exposed, exposure_source)) = existing_address {
+        let exposed_by_output_script = exposure_source == Some(ExposureSource::OutputScript);
+
+        // An address first seen in this block is either receiving again, or its block is being
+        // reprocessed. Its first output, and the siblings its output script exposed, tell
+        // whether this is still the output that created it.
+        let (first_output_id, newly_exposed_siblings) =
+            if first_seen == first_seen_block_height_val as i32 {
+                let siblings = if exposed_by_output_script {
+                    get_output_script_exposed_siblings(conn, id, first_seen)?
+                } else {
+                    Vec::new()
+                };
+                (get_first_output_id(conn, id, first_seen)?, siblings)
+            } else {
+                (None, Vec::new())
+            };
+
         // Address exists, return its ID
-        return Ok(id);
+        return Ok(AddressLookup {
+            address_id: id,
+            created: false,
+            first_seen_block_height: first_seen,
+            first_output_id,
+            is_public_key_exposed: exposed,
+            exposed_by_output_script,
+            exposed_by_sibling: false,
+            newly_exposed_siblings,
+        });
     }
 
     // 2. Address doesn't exist, create it
-    let new_address = NewAddress {
-        address_string: address_string_val.to_string(),
-        script_type: script_type_val.to_string(),
-        first_seen_block_height: first_seen_block_height_val as i32,
-        script_extra_data: extra_data_val,
-        public_key: None, // Will be updated if revealed in an input
+    let exposed_at_creation = embedded_public_keys
+        .iter()
+        .any(|key| classify_public_key(key).is_valid());
+    let single_key = match embedded_public_keys {
+        [key] => Some(key),
+        _ => None,
+    };
+
+    // An address whose key was already exposed through a sibling address starts out exposed.
+    // Only the script types a public key backs can have a sibling.
+    let sibling = if exposed_at_creation || !SIBLING_SCRIPT_TYPES.contains(&script_type_val) {
+        None
+    } else {
+        find_public_key_sibling(conn, script_type_val, address_hash_val)?
+    };
+
+    let new_address = match &sibling {
+        Some(sibling) => NewAddress {
+            address_hash: address_hash_val.to_vec(),
+            script_type: script_type_val,
+            first_seen_block_height: first_seen_block_height_val as i32,
+            script_extra_data: extra_data_val,
+            public_key: Some(sibling.public_key.clone()),
+            public_key_format: Some(sibling.public_key_format),
+            is_public_key_exposed: true,
+            public_key_exposed_height: Some(first_seen_block_height_val as i32),
+            public_key_exposure_source: Some(ExposureSource::Sibling),
+            script_template_hash: script_template_hash_val,
+            public_key_exposure_input_id: sibling.exposure_input_id,
+            public_key_exposed_via_address_id: Some(sibling.source_address_id),
+        },
+        None => NewAddress {
+            address_hash: address_hash_val.to_vec(),
+            script_type: script_type_val,
+            first_seen_block_height: first_seen_block_height_val as i32,
+            script_extra_data: extra_data_val,
+            // Otherwise updated if revealed in an input
+            public_key: single_key.cloned(),
+            public_key_format: single_key.map(|key| classify_public_key(key)),
+            is_public_key_exposed: exposed_at_creation,
+            public_key_exposed_height: exposed_at_creation
+                .then_some(first_seen_block_height_val as i32),
+            public_key_exposure_source: exposed_at_creation.then_some(ExposureSource::OutputScript),
+            script_template_hash: script_template_hash_val,
+            public_key_exposure_input_id: None,
+            public_key_exposed_via_address_id: None,
+        },
     };
 
     //3. DB INSERT!
-    insert_into(addresses)
+    let id = insert_into(addresses)
         .values(&new_address)
         .returning(address_id)
         .get_result(conn)
-        .context("Failed to insert new address")
+        .context("Failed to insert new address")?;
+
+    // Keys in the output script (P2PK, P2MS) also expose the addresses they back
+    let mut newly_exposed_siblings = Vec::new();
+    for key in embedded_public_keys {
+        newly_exposed_siblings.extend(expose_public_key_siblings(
+            conn,
+            id,
+            key,
+            classify_public_key(key),
+            first_seen_block_height_val as i32,
+            None,
+        )?);
+    }
+
+    Ok(AddressLookup {
+        address_id: id,
+        created: true,
+        first_seen_block_height: new_address.first_seen_block_height,
+        first_output_id: None,
+        is_public_key_exposed: new_address.is_public_key_exposed,
+        exposed_by_output_script: exposed_at_creation,
+        exposed_by_sibling: sibling.is_some(),
+        newly_exposed_siblings,
+    })
+}
+
+/// Get the first output received by an address, looked up in the block it was first seen in
+fn get_first_output_id(
+    conn: &mut PgConnection,
+    address_id_val: i64,
+    first_seen_block_height_val: i32,
+) -> Result<Option<i64>> {
+    use schema::address_outputs::dsl::*;
+
+    // DB QUERY!
+    address_outputs
+        .filter(address_id.eq(address_id_val))
+        .filter(block_height.eq(first_seen_block_height_val))
+        .order(output_id)
+        .select(output_id)
+        .first(conn)
+        .optional()
+        .context("Failed to query first address output")
 }
 
-/// Store a transaction output associated with an address
+/// Get the sibling addresses exposed by the public keys in an address's output script when it
+/// was created. Siblings created after it were created exposed, and have a higher id.
+fn get_output_script_exposed_siblings(
+    conn: &mut PgConnection,
+    source_address_id_val: i64,
+    exposed_height_val: i32,
+) -> Result<Vec<i64>> {
+    use schema::addresses::dsl::*;
+
+    // DB QUERY!
+    addresses
+        .filter(public_key_exposed_via_address_id.eq(source_address_id_val))
+        .filter(public_key_exposed_height.eq(exposed_height_val))
+        .filter(public_key_exposure_input_id.is_null())
+        .filter(address_id.lt(source_address_id_val))
+        .select(address_id)
+        .load(conn)
+        .context("Failed to query siblings exposed by an output script")
+}
+
+/// Store the threshold and public keys of a newly created bare multisig address
+pub fn store_multisig_script(
+    conn: &mut PgConnection,
+    address_id_val: i64,
+    multisig: &P2msData,
+    public_keys: &[Vec<u8>],
+) -> Result<()> {
+    use crate::db::models::{MultisigKey, MultisigScript};
+    use diesel::insert_into;
+
+    let script = MultisigScript {
+        address_id: address_id_val,
+        required_signatures: multisig.m as i16,
+        total_keys: multisig.n as i16,
+    };
+
+    // DB INSERT!
+    insert_into(schema::multisig_scripts::table)
+        .values(&script)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert multisig script")?;
+
+    let keys: Vec<MultisigKey> = public_keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| MultisigKey {
+            address_id: address_id_val,
+            key_index: index as i16,
+            public_key: key.clone(),
+            public_key_format: classify_public_key(key),
+        })
+        .collect();
+
+    // DB INSERT!
+    insert_into(schema::multisig_keys::table)
+        .values(&keys)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert multisig public keys")?;
+
+    Ok(())
+}
+
+/// Store a transaction output associated with an address.
+/// An output stored before, when its block is reprocessed, is kept as it was.
 pub fn store_transaction_output(
     conn: &mut PgConnection,
     address_id_val: i64,
@@ -216,7 +538,8 @@ pub fn store_transaction_output(
     block_height_val: i32,
     output_index_val: i32,
     value_satoshis_val: u64,
-) -> Result<i64> {
+    received_after_exposure_val: bool,
+) -> Result<StoredOutput> {
     use crate::db::models::NewAddressOutput;
     use diesel::insert_into;
     use schema::address_outputs::dsl::*;
@@ -230,77 +553,209 @@ pub fn store_transaction_output(
         output_index: output_index_val,
         value_satoshis: value_satoshis_val as i64,
         spending_input_id: None, // Will be updated when spent
+        received_after_exposure: received_after_exposure_val,
     };
 
     // Insert and get the new output_id
     // DB INSERT!
-    let output_id_val = insert_into(address_outputs)
+    let inserted_id = insert_into(address_outputs)
         .values(&new_output)
+        .on_conflict((transaction_id, block_height, output_index))
+        .do_nothing()
         .returning(output_id)
-        .get_result(conn)
+        .get_result::<i64>(conn)
+        .optional()
         .context("Failed to insert transaction output")?;
 
+    let output_id_val = match inserted_id {
+        Some(id) => id,
+        // The output was stored before, and the address already counts it
+        None => {
+            // DB QUERY!
+            let (stored_id, stored_after_exposure) = address_outputs
+                .filter(transaction_id.eq(&new_output.transaction_id))
+                .filter(block_height.eq(block_height_val))
+                .filter(output_index.eq(output_index_val))
+                .select((output_id, received_after_exposure))
+                .first::<(i64, bool)>(conn)
+                .context("Failed to query stored transaction output")?;
+
+            return Ok(StoredOutput {
+                output_id: stored_id,
+                received_after_exposure: stored_after_exposure,
+            });
+        }
+    };
+
     // Update the address receive count
     update_address_receive_count(conn, address_id_val)?;
 
-    Ok(output_id_val)
+    Ok(StoredOutput {
+        output_id: output_id_val,
+        received_after_exposure: received_after_exposure_val,
+    })
+}
+
+/// Store the change labels of the outputs of a transaction in the block at `block_height_val`,
+/// in one statement
+pub fn store_change_labels(
+    conn: &mut PgConnection,
+    block_height_val: i32,
+    labels: &[ChangeLabel],
+) -> Result<()> {
+    use diesel::sql_query;
+    use diesel::sql_types::{Array, BigInt, Integer, Text};
+
+    let output_ids: Vec<i64> = labels.iter().map(|l| l.output_id).collect();
+    let likelihoods: Vec<&str> = labels.iter().map(|l| l.likelihood.as_str()).collect();
+    // Arrays of arrays must be rectangular, so each output's heuristics travel as one string
+    let heuristics: Vec<String> = labels
+        .iter()
+        .map(|l| {
+            l.heuristics
+                .iter()
+                .map(|h| h.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+
+    // DB UPDATE!
+    sql_query(
+        "UPDATE address_outputs o
+         SET change_likelihood = l.likelihood,
+             change_heuristics = string_to_array(l.heuristics, ',')
+         FROM unnest($1::BIGINT[], $2::TEXT[], $3::TEXT[]) AS l(output_id, likelihood, heuristics)
+         WHERE o.output_id = l.output_id AND o.block_height = $4",
+    )
+    .bind::<Array<BigInt>, _>(output_ids)
+    .bind::<Array<Text>, _>(likelihoods)
+    .bind::<Array<Text>, _>(heuristics)
+    .bind::<Integer, _>(block_height_val)
+    .execute(conn)
+    .context("Failed to store change labels")?;
+
+    Ok(())
 }
 
-/// Find an output by transaction ID and output index
+/// Find an output by transaction ID and output index, unspent or already spent by the input
+/// at `spending_input_index` of `spending_txid_str` (when its block is reprocessed)
 pub fn find_output(
     conn: &mut PgConnection,
     txid_str: &str,
     output_index_val: i32,
+    spending_txid_str: &str,
+    spending_block_height: i32,
+    spending_input_index: i32,
 ) -> Result<Option<OutputInfo>> {
     // Import table namespaces rather than columns to avoid ambiguity
+    use schema::address_inputs;
     use schema::address_outputs;
-    use schema::txid_block_index;
+    use schema::addresses;
+    use schema::blocks;
 
     let txid_bytes = hex::decode(txid_str).context("Failed to decode transaction ID hex string")?;
+    let spending_txid_bytes =
+        hex::decode(spending_txid_str).context("Failed to decode transaction ID hex string")?;
 
     // First, find all blocks containing this TXID
-    let block_heights: Vec<i32> = txid_block_index::table
-        .filter(txid_block_index::transaction_id.eq(&txid_bytes))
-        .select(txid_block_index::block_height)
+    let block_heights = get_txid_heights(conn, &txid_bytes)?;
+
+    // Only the latest copy of a duplicated (BIP30) txid can be spent, the earlier
+    // copy's outputs were overwritten. If no blocks contain this TXID, return None
+    let height = match bip30::spendable_height(&block_heights) {
+        Some(h) => h,
+        None => return Ok(None),
+    };
+
+    // The height prunes the output lookup to the one partition holding it
+    let output_info = address_outputs::table
+        .inner_join(addresses::table)
+        .inner_join(blocks::table.on(blocks::block_height.eq(address_outputs::block_height)))
+        .filter(address_outputs::transaction_id.eq(&txid_bytes))
+        .filter(address_outputs::block_height.eq(height))
+        .filter(address_outputs::output_index.eq(output_index_val))
+        // Ensure it's not already spent by another input
+        .filter(
+            address_outputs::is_spent
+                .eq(false)
+                .or(address_outputs::spending_input_id.eq_any(
+                    address_inputs::table
+                        .filter(address_inputs::transaction_id.eq(&spending_txid_bytes))
+                        .filter(address_inputs::block_height.eq(spending_block_height))
+                        .filter(address_inputs::input_index.eq(spending_input_index))
+                        .select(address_inputs::input_id.nullable()),
+                )),
+        )
+        .select((
+            address_outputs::output_id,
+            address_outputs::address_id,
+            address_outputs::value_satoshis,
+            addresses::script_type,
+            blocks::block_timestamp,
+        ))
+        .first::<(i64, i64, i64, ScriptType, chrono::NaiveDateTime)>(conn)
+        .optional()
+        .context("Failed to query output")?;
+
+    Ok(output_info.map(
+        |(out_id, addr_id, value, addr_script_type, created_timestamp)| OutputInfo {
+            output_id: out_id,
+            address_id: addr_id,
+            value_satoshis: value,
+            script_type: addr_script_type,
+            block_height: height,
+            block_timestamp: created_timestamp,
+        },
+    ))
+}
+
+/// Get the heights of all the blocks a transaction ID was included in, with a single lookup
+/// in the unpartitioned index. More than one only for the duplicate coinbases (BIP30).
+fn get_txid_heights(conn: &mut PgConnection, txid_bytes: &[u8]) -> Result<Vec<i32>> {
+    use schema::txid_block_index::dsl::*;
+
+    // DB QUERY!
+    txid_block_index
+        .filter(transaction_id.eq(txid_bytes))
+        .select(block_height)
         .load(conn)
-        .context("Failed to query txid_block_index")?;
+        .context("Failed to query txid_block_index")
+}
 
-    // If no blocks contain this TXID, return None
-    if block_heights.is_empty() {
-        return Ok(None);
-    }
+/// Flag the unspent outputs of earlier copies of a transaction as unspendable, for a
+/// duplicate coinbase that overwrites them (BIP30). Returns the number of outputs flagged.
+pub fn mark_overwritten_outputs(
+    conn: &mut PgConnection,
+    txid_str: &str,
+    block_height_val: i32,
+) -> Result<usize> {
+    use diesel::update;
+    use schema::address_outputs::dsl::*;
 
-    // For each block_height, try to find the output
-    for height in block_heights {
-        let output_info = address_outputs::table
-            .filter(address_outputs::transaction_id.eq(&txid_bytes))
-            .filter(address_outputs::block_height.eq(height))
-            .filter(address_outputs::output_index.eq(output_index_val))
-            .filter(address_outputs::is_spent.eq(false)) // Ensure it's not already spent
-            .select((
-                address_outputs::output_id,
-                address_outputs::address_id,
-                address_outputs::value_satoshis,
-            ))
-            .first::<(i64, i64, i64)>(conn)
-            .optional()
-            .context("Failed to query output")?;
-
-        if let Some((out_id, addr_id, value)) = output_info {
-            // Found it!
-            return Ok(Some(OutputInfo {
-                output_id: out_id,
-                address_id: addr_id,
-                value_satoshis: value,
-            }));
-        }
+    let txid_bytes = hex::decode(txid_str).context("Failed to decode transaction ID hex string")?;
+
+    let overwritten_heights =
+        bip30::overwritten_heights(&get_txid_heights(conn, &txid_bytes)?, block_height_val);
+    if overwritten_heights.is_empty() {
+        return Ok(0);
     }
 
-    // No matching output found in any block
-    Ok(None)
+    // DB UPDATE!
+    update(
+        address_outputs
+            .filter(transaction_id.eq(&txid_bytes))
+            .filter(block_height.eq_any(overwritten_heights))
+            .filter(is_spent.eq(false)),
+    )
+    .set(is_unspendable.eq(true))
+    .execute(conn)
+    .context("Failed to flag overwritten outputs")
 }
 
-/// Store a transaction input that spends a previous output
+/// Store a transaction input that spends a previous output.
+/// An input stored before, when its block is reprocessed, is kept as it was.
+#[allow(clippy::too_many_arguments)]
 pub fn store_transaction_input(
     conn: &mut PgConnection,
     address_id_val: i64,
@@ -310,13 +765,17 @@ pub fn store_transaction_input(
     spent_output_id_val: i64,
     value_satoshis_val: i64,
     public_key_revealed_val: Option<Vec<u8>>,
-) -> Result<i64> {
+    sequence_val: &InputSequence,
+    coin_age: &CoinAge,
+) -> Result<StoredInput> {
     use crate::db::models::NewAddressInput;
     use diesel::insert_into;
     use schema::address_inputs::dsl::*;
 
     let txid_bytes = hex::decode(txid_str).context("Failed to decode transaction ID hex string")?;
 
+    let public_key_format = public_key_revealed_val.as_deref().map(classify_public_key);
+
     let new_input = NewAddressInput {
         address_id: address_id_val,
         transaction_id: txid_bytes,
@@ -325,38 +784,268 @@ pub fn store_transaction_input(
         spent_output_id: spent_output_id_val,
         value_satoshis: value_satoshis_val,
         public_key_revealed: public_key_revealed_val.clone(),
+        public_key_revealed_format: public_key_format,
+        sequence: sequence_val.sequence as i64,
+        relative_lock_type: sequence_val.relative_lock.as_ref().map(|l| l.kind),
+        relative_lock_value: sequence_val.relative_lock.as_ref().map(|l| l.value as i32),
+        spent_output_block_height: coin_age.spent_output_block_height,
+        spent_output_timestamp: coin_age.spent_output_timestamp,
+        coin_age_blocks: coin_age.age_blocks,
+        coin_age_seconds: coin_age.age_seconds,
     };
 
     // Insert and get the new input_id
     // DB INSERT!
-    let input_id_val = insert_into(address_inputs)
+    let inserted_id = insert_into(address_inputs)
         .values(&new_input)
+        .on_conflict((transaction_id, block_height, input_index))
+        .do_nothing()
         .returning(input_id)
-        .get_result(conn)
+        .get_result::<i64>(conn)
+        .optional()
         .context("Failed to insert transaction input")?;
 
-    // Update the address spend count
-    update_address_spend_count(conn, address_id_val)?;
+    let input_id_val = match inserted_id {
+        Some(id) => {
+            // Update the address spend count
+            update_address_spend_count(conn, address_id_val)?;
+            id
+        }
+        // The input was stored before, and the address already counts it
+        // DB QUERY!
+        None => address_inputs
+            .filter(transaction_id.eq(&new_input.transaction_id))
+            .filter(block_height.eq(block_height_val))
+            .filter(input_index.eq(input_index_val))
+            .select(input_id)
+            .first(conn)
+            .context("Failed to query stored transaction input")?,
+    };
+
+    // If a valid public key was revealed, update the address record and its siblings
+    let mut public_key_newly_exposed = false;
+    let mut newly_exposed_siblings = Vec::new();
+    if let (Some(pubkey), Some(format)) = (public_key_revealed_val, public_key_format) {
+        if format.is_valid() {
+            public_key_newly_exposed = update_address_public_key(
+                conn,
+                address_id_val,
+                &pubkey,
+                format,
+                block_height_val,
+                input_id_val,
+            )?;
+            if public_key_newly_exposed {
+                newly_exposed_siblings = expose_public_key_siblings(
+                    conn,
+                    address_id_val,
+                    &pubkey,
+                    format,
+                    block_height_val,
+                    Some(input_id_val),
+                )?;
+            }
+        }
+    }
+
+    Ok(StoredInput {
+        input_id: input_id_val,
+        public_key_newly_exposed,
+        newly_exposed_siblings,
+    })
+}
+
+/// Store the signatures pushed by an input
+pub fn store_signatures(
+    conn: &mut PgConnection,
+    input_id_val: i64,
+    block_height_val: i32,
+    signatures_val: &[InputSignature],
+) -> Result<()> {
+    use crate::db::models::NewSignature;
+    use diesel::insert_into;
+    use schema::signatures::dsl::*;
+
+    let new_signatures: Vec<NewSignature> = signatures_val
+        .iter()
+        .map(|s| NewSignature {
+            input_id: input_id_val,
+            block_height: block_height_val,
+            signature_index: s.signature_index,
+            in_witness: s.in_witness,
+            public_key: s.public_key.clone(),
+            r_value: s.signature.r_value.to_vec(),
+            s_value: s.signature.s_value.to_vec(),
+            sighash_type: s.signature.sighash_type as i16,
+            is_low_r: s.signature.is_low_r,
+            is_low_s: s.signature.is_low_s,
+            is_strict_der: s.signature.is_strict_der,
+        })
+        .collect();
+
+    // DB INSERT!
+    insert_into(signatures)
+        .values(&new_signatures)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert input signatures")?;
+
+    Ok(())
+}
+
+/// Store the inscription envelopes found in an input
+pub fn store_inscriptions(
+    conn: &mut PgConnection,
+    input_id_val: i64,
+    txid_str: &str,
+    block_height_val: i32,
+    input_index_val: i32,
+    envelopes: &[Envelope],
+) -> Result<()> {
+    use crate::db::models::NewInscription;
+    use diesel::insert_into;
+    use schema::inscriptions::dsl::*;
+
+    let txid_bytes = hex::decode(txid_str).context("Failed to decode transaction ID hex string")?;
+
+    let new_inscriptions: Vec<NewInscription> = envelopes
+        .iter()
+        .map(|e| NewInscription {
+            input_id: input_id_val,
+            transaction_id: txid_bytes.clone(),
+            block_height: block_height_val,
+            input_index: input_index_val,
+            envelope_index: e.envelope_index,
+            content_type: e.content_type.clone(),
+            content_encoding: e.content_encoding.clone(),
+            body_size: e.body_size as i32,
+            envelope_size: e.envelope_size as i32,
+        })
+        .collect();
+
+    // DB INSERT!
+    insert_into(inscriptions)
+        .values(&new_inscriptions)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert inscriptions")?;
+
+    Ok(())
+}
+
+/// Merges the clusters of addresses spent together in one transaction into a single cluster.
+/// The members of the smaller clusters are relabelled to the largest one.
+pub fn merge_address_clusters(
+    conn: &mut PgConnection,
+    address_ids_val: &[i64],
+    block_height_val: i32,
+) -> Result<()> {
+    use diesel::{delete, insert_into, update};
+    use schema::{addresses, clusters};
 
-    // If a public key was revealed, update the address record
-    if let Some(ref pubkey) = public_key_revealed_val {
-        update_address_public_key(conn, address_id_val, pubkey.clone())?;
+    // DB QUERY!
+    let members: Vec<(i64, Option<i64>)> = addresses::table
+        .filter(addresses::address_id.eq_any(address_ids_val))
+        .select((addresses::address_id, addresses::cluster_id))
+        .load(conn)
+        .context("Failed to query address clusters")?;
+
+    // An address that has never been clustered is a cluster of its own
+    let cluster_ids: BTreeSet<i64> = members
+        .iter()
+        .map(|(id, cluster)| cluster.unwrap_or(*id))
+        .collect();
+    if cluster_ids.len() < 2 {
+        return Ok(());
     }
 
-    Ok(input_id_val)
+    // DB QUERY!
+    let existing: Vec<models::Cluster> = clusters::table
+        .filter(clusters::cluster_id.eq_any(&cluster_ids))
+        .select(models::Cluster::as_select())
+        .load(conn)
+        .context("Failed to query clusters")?;
+
+    let sizes: Vec<ClusterSize> = cluster_ids
+        .iter()
+        .map(|id| ClusterSize {
+            cluster_id: *id,
+            address_count: existing
+                .iter()
+                .find(|c| c.cluster_id == *id)
+                .map_or(1, |c| c.address_count),
+        })
+        .collect();
+    let target = match merge_target(&sizes) {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let merged: Vec<i64> = cluster_ids
+        .iter()
+        .copied()
+        .filter(|id| *id != target)
+        .collect();
+    let address_count_val: i32 = sizes.iter().map(|c| c.address_count).sum();
+    let first_block_height_val = existing
+        .iter()
+        .map(|c| c.first_block_height)
+        .min()
+        .unwrap_or(block_height_val);
+
+    // DB UPSERT!
+    insert_into(clusters::table)
+        .values((
+            clusters::cluster_id.eq(target),
+            clusters::address_count.eq(address_count_val),
+            clusters::first_block_height.eq(first_block_height_val),
+            clusters::last_merged_block_height.eq(block_height_val),
+        ))
+        .on_conflict(clusters::cluster_id)
+        .do_update()
+        .set((
+            clusters::address_count.eq(address_count_val),
+            clusters::first_block_height.eq(first_block_height_val),
+            clusters::last_merged_block_height.eq(block_height_val),
+        ))
+        .execute(conn)
+        .context("Failed to store cluster")?;
+
+    // Relabel the members of the merged clusters, and the addresses joining as clusters of their own
+    let mut relabelled_addresses = merged.clone();
+    relabelled_addresses.push(target);
+
+    // DB UPDATE!
+    update(
+        addresses::table.filter(
+            addresses::cluster_id
+                .eq_any(&merged)
+                .or(addresses::address_id.eq_any(&relabelled_addresses)),
+        ),
+    )
+    .set(addresses::cluster_id.eq(target))
+    .execute(conn)
+    .context("Failed to relabel cluster addresses")?;
+
+    delete(clusters::table.filter(clusters::cluster_id.eq_any(&merged)))
+        .execute(conn)
+        .context("Failed to remove merged clusters")?;
+
+    Ok(())
 }
 
-/// Mark an output as spent by an input
+/// Mark an output as spent by an input. The height of the output's block limits the update
+/// to the partition holding it.
 pub fn mark_output_spent(
     conn: &mut PgConnection,
     output_id_val: i64,
+    output_block_height_val: i32,
     spending_input_id_val: i64,
 ) -> Result<()> {
     use diesel::update;
     use schema::address_outputs::dsl::*;
 
     // DB UPDATE!
-    update(address_outputs.filter(output_id.eq(output_id_val)))
+    update(address_outputs.find((output_id_val, output_block_height_val)))
         .set((
             is_spent.eq(true),
             spending_input_id.eq(spending_input_id_val),
@@ -396,26 +1085,1019 @@ fn update_address_spend_count(conn: &mut PgConnection, address_id_val: i64) -> R
 }
 
 /// Update an address's public key if revealed
+/// Returns true if this is the first time the address's public key has been exposed, which it
+/// still is when the block of the exposing input is reprocessed
 fn update_address_public_key(
     conn: &mut PgConnection,
     address_id_val: i64,
-    pubkey: Vec<u8>,
-) -> Result<()> {
+    pubkey: &[u8],
+    format: PubkeyFormat,
+    exposed_height_val: i32,
+    exposure_input_id_val: i64,
+) -> Result<bool> {
     use diesel::update;
     use schema::addresses::dsl::*;
 
+    // Only addresses that are not yet exposed are updated, keeping the first revealed key
     // DB UPDATE!
-    update(addresses.filter(address_id.eq(address_id_val)))
-        .set((public_key.eq(pubkey), is_public_key_exposed.eq(true)))
+    let updated_rows = update(
+        addresses.filter(address_id.eq(address_id_val)).filter(
+            is_public_key_exposed
+                .eq(false)
+                .or(public_key_exposure_input_id.eq(exposure_input_id_val)),
+        ),
+    )
+    .set((
+        public_key.eq(pubkey),
+        public_key_format.eq(format),
+        is_public_key_exposed.eq(true),
+        public_key_exposed_height.eq(exposed_height_val),
+        public_key_exposure_source.eq(ExposureSource::Spend),
+        public_key_exposure_input_id.eq(exposure_input_id_val),
+    ))
+    .execute(conn)
+    .context("Failed to update address public key")?;
+
+    Ok(updated_rows > 0)
+}
+
+/// Find the exposed public key backing an address, if it was exposed through another address
+fn find_public_key_sibling(
+    conn: &mut PgConnection,
+    script_type_val: ScriptType,
+    address_hash_val: &[u8],
+) -> Result<Option<models::PublicKeySibling>> {
+    use schema::public_key_siblings::dsl::*;
+
+    // DB QUERY!
+    public_key_siblings
+        .find((address_hash_val, script_type_val))
+        .select(models::PublicKeySibling::as_select())
+        .first(conn)
+        .optional()
+        .context("Failed to query public key sibling")
+}
+
+/// Record every standard address form of an exposed public key, so that sibling addresses
+/// seen later are created exposed, and expose the existing siblings that were not yet.
+/// Returns the ids of the sibling addresses newly exposed, including those exposed by the same
+/// input when its block is reprocessed.
+fn expose_public_key_siblings(
+    conn: &mut PgConnection,
+    source_address_id_val: i64,
+    key: &[u8],
+    format: PubkeyFormat,
+    exposed_height_val: i32,
+    exposure_input_id_val: Option<i64>,
+) -> Result<Vec<i64>> {
+    use diesel::{insert_into, update};
+    use schema::addresses;
+    use schema::public_key_siblings;
+
+    let siblings = sibling_addresses(key, format);
+    if siblings.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let records: Vec<models::PublicKeySibling> = siblings
+        .iter()
+        .map(|(sibling_type, sibling_hash)| models::PublicKeySibling {
+            address_hash: sibling_hash.clone(),
+            script_type: *sibling_type,
+            public_key: key.to_vec(),
+            public_key_format: format,
+            source_address_id: source_address_id_val,
+            exposure_input_id: exposure_input_id_val,
+            exposed_block_height: exposed_height_val,
+        })
+        .collect();
+
+    // The first exposure of a key is kept
+    // DB INSERT!
+    insert_into(public_key_siblings::table)
+        .values(&records)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert public key siblings")?;
+
+    // P2PKH and P2WPKH share the key hash, so each form is matched with its script type
+    let mut newly_exposed = Vec::new();
+    for (sibling_type, sibling_hash) in &siblings {
+        // Siblings first seen from the exposing block on were created exposed through the input.
+        // Those first seen earlier in that block cannot be told apart from them, and are left
+        // out when it is reprocessed.
+        let exposed_by_input = addresses::public_key_exposure_input_id
+            .eq(exposure_input_id_val)
+            .and(addresses::first_seen_block_height.lt(exposed_height_val));
+
+        // DB UPDATE!
+        let exposed: Vec<i64> = update(
+            addresses::table
+                .filter(addresses::address_hash.eq(sibling_hash))
+                .filter(addresses::script_type.eq(sibling_type))
+                .filter(
+                    addresses::is_public_key_exposed
+                        .eq(false)
+                        .or(exposed_by_input),
+                ),
+        )
+        .set((
+            addresses::public_key.eq(key),
+            addresses::public_key_format.eq(format),
+            addresses::is_public_key_exposed.eq(true),
+            addresses::public_key_exposed_height.eq(exposed_height_val),
+            addresses::public_key_exposure_source.eq(ExposureSource::Sibling),
+            addresses::public_key_exposure_input_id.eq(exposure_input_id_val),
+            addresses::public_key_exposed_via_address_id.eq(source_address_id_val),
+        ))
+        .returning(addresses::address_id)
+        .get_results(conn)
+        .context("Failed to expose sibling addresses")?;
+        newly_exposed.extend(exposed);
+    }
+
+    Ok(newly_exposed)
+}
+
+/// Exposed address: its id, first seen height, exposed height and single public key
+type ExposedAddressRow = (i64, i32, Option<i32>, Option<Vec<u8>>, Option<PubkeyFormat>);
+
+/// Links the public keys exposed before sibling addresses were tracked: the keys of the next
+/// `batch_size` exposed addresses after `after_address_id`, in address order. Returns the last
+/// address id of the batch and the number of sibling addresses newly exposed, or None when done.
+pub fn link_exposed_public_keys(
+    conn: &mut PgConnection,
+    after_address_id: i64,
+    batch_size: i64,
+) -> Result<Option<(i64, usize)>> {
+    use schema::{addresses, multisig_keys};
+
+    // DB QUERY!
+    let exposed: Vec<ExposedAddressRow> = addresses::table
+        .filter(addresses::address_id.gt(after_address_id))
+        .filter(addresses::is_public_key_exposed.eq(true))
+        .order(addresses::address_id)
+        .limit(batch_size)
+        .select((
+            addresses::address_id,
+            addresses::first_seen_block_height,
+            addresses::public_key_exposed_height,
+            addresses::public_key,
+            addresses::public_key_format,
+        ))
+        .load(conn)
+        .context("Failed to query exposed addresses")?;
+
+    let last_address_id = match exposed.last() {
+        Some((id, ..)) => *id,
+        None => return Ok(None),
+    };
+
+    // Bare multisig addresses keep their keys in multisig_keys
+    let ids: Vec<i64> = exposed.iter().map(|(id, ..)| *id).collect();
+    // DB QUERY!
+    let multisig: Vec<(i64, Vec<u8>, PubkeyFormat)> = multisig_keys::table
+        .filter(multisig_keys::address_id.eq_any(&ids))
+        .select((
+            multisig_keys::address_id,
+            multisig_keys::public_key,
+            multisig_keys::public_key_format,
+        ))
+        .load(conn)
+        .context("Failed to query multisig keys")?;
+
+    let mut newly_exposed = 0;
+    for (id, first_seen, exposed_height, key, format) in &exposed {
+        let height = exposed_height.unwrap_or(*first_seen);
+        let keys = match (key, format) {
+            (Some(key), Some(format)) => vec![(key, *format)],
+            _ => multisig
+                .iter()
+                .filter(|(multisig_id, ..)| multisig_id == id)
+                .map(|(_, key, format)| (key, *format))
+                .collect(),
+        };
+
+        for (key, format) in keys {
+            newly_exposed +=
+                expose_public_key_siblings(conn, *id, key, format, height, None)?.len();
+        }
+    }
+
+    Ok(Some((last_address_id, newly_exposed)))
+}
+
+/// Get the total value of the unspent outputs held by an address
+pub fn get_address_unspent_value(conn: &mut PgConnection, address_id_val: i64) -> Result<i64> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::BigInt;
+    use schema::address_outputs::dsl::*;
+
+    // SUM over BIGINT yields NUMERIC in Postgres, so cast back to BIGINT
+    // DB QUERY!
+    address_outputs
+        .filter(address_id.eq(address_id_val))
+        .filter(is_spent.eq(false))
+        .filter(is_unspendable.eq(false))
+        .select(sql::<BigInt>("COALESCE(SUM(value_satoshis), 0)::BIGINT"))
+        .first::<i64>(conn)
+        .context("Failed to query address unspent value")
+}
+
+/// Find an address by its string form, which is decoded to the address hash and the script
+/// types it may be stored under. When the hash is stored under more than one of them, the
+/// first in the order `decode_address` gives is returned. Returns None for strings that are
+/// not addresses.
+pub fn get_address(
+    conn: &mut PgConnection,
+    address_string_val: &str,
+) -> Result<Option<models::Address>> {
+    use schema::addresses::dsl::*;
+
+    let (address_hash_val, script_types) = match decode_address(address_string_val) {
+        Some(decoded) => decoded,
+        None => return Ok(None),
+    };
+
+    // DB QUERY!
+    let matches = addresses
+        .filter(address_hash.eq(address_hash_val))
+        .filter(script_type.eq_any(script_types))
+        .order(address_id)
+        .select(models::Address::as_select())
+        .load::<models::Address>(conn)
+        .context("Failed to query address")?;
+
+    // A hash can be stored under several of the script types, e.g. a P2SH address and a bare
+    // multisig script that hashes the same, so take the type the address string stands for
+    Ok(matches.into_iter().min_by_key(|address| {
+        script_types
+            .iter()
+            .position(|candidate| *candidate == address.script_type)
+    }))
+}
+
+/// Get the balance of an address at the end of the block at `block_height_val`: what it
+/// received up to then, less what it spent and what duplicate coinbases overwrote (BIP30)
+/// up to then. Matches the running balance of `get_address_history`.
+pub fn get_address_balance_at(
+    conn: &mut PgConnection,
+    address_id_val: i64,
+    block_height_val: i32,
+) -> Result<i64> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::BigInt;
+    use schema::{address_inputs, address_outputs, transactions};
+
+    // DB QUERY!
+    let received = address_outputs::table
+        .filter(address_outputs::address_id.eq(address_id_val))
+        .filter(address_outputs::block_height.le(block_height_val))
+        .select(sql::<BigInt>("COALESCE(SUM(value_satoshis), 0)::BIGINT"))
+        .first::<i64>(conn)
+        .context("Failed to query address received value")?;
+
+    // Unspendable outputs were held until the later copy of their transaction overwrote them
+    // DB QUERY!
+    let overwritten = address_outputs::table
+        .inner_join(
+            transactions::table.on(transactions::transaction_id
+                .eq(address_outputs::transaction_id)
+                .and(transactions::block_height.gt(address_outputs::block_height))),
+        )
+        .filter(address_outputs::address_id.eq(address_id_val))
+        .filter(address_outputs::is_unspendable.eq(true))
+        .filter(transactions::block_height.le(block_height_val))
+        .select(sql::<BigInt>(
+            "COALESCE(SUM(address_outputs.value_satoshis), 0)::BIGINT",
+        ))
+        .first::<i64>(conn)
+        .context("Failed to query address overwritten value")?;
+
+    // DB QUERY!
+    let spent = address_inputs::table
+        .filter(address_inputs::address_id.eq(address_id_val))
+        .filter(address_inputs::block_height.le(block_height_val))
+        .select(sql::<BigInt>("COALESCE(SUM(value_satoshis), 0)::BIGINT"))
+        .first::<i64>(conn)
+        .context("Failed to query address spent value")?;
+
+    Ok(received - spent - overwritten)
+}
+
+/// Get the unspent outputs of an address, oldest first
+pub fn get_address_utxos(
+    conn: &mut PgConnection,
+    address_id_val: i64,
+) -> Result<Vec<models::AddressOutput>> {
+    use schema::address_outputs::dsl::*;
+
+    // DB QUERY!
+    address_outputs
+        .filter(address_id.eq(address_id_val))
+        .filter(is_spent.eq(false))
+        .filter(is_unspendable.eq(false))
+        .order((block_height, output_id))
+        .select(models::AddressOutput::as_select())
+        .load(conn)
+        .context("Failed to query address UTXOs")
+}
+
+/// Get what happened at an address between two heights (inclusive), in chain order:
+/// the outputs it received, the inputs spending from it and the outputs overwritten by a
+/// duplicate coinbase (BIP30), with their block timestamps
+pub fn get_address_history(
+    conn: &mut PgConnection,
+    address_id_val: i64,
+    from_height: i32,
+    to_height: i32,
+) -> Result<Vec<AddressEvent>> {
+    use schema::{address_inputs, address_outputs, blocks, transactions};
+
+    // DB QUERY!
+    let receives: Vec<(i32, chrono::NaiveDateTime, i32, Vec<u8>, i32, i64)> =
+        address_outputs::table
+            .inner_join(blocks::table.on(blocks::block_height.eq(address_outputs::block_height)))
+            .inner_join(
+                transactions::table.on(transactions::transaction_id
+                    .eq(address_outputs::transaction_id)
+                    .and(transactions::block_height.eq(address_outputs::block_height))),
+            )
+            .filter(address_outputs::address_id.eq(address_id_val))
+            .filter(address_outputs::block_height.between(from_height, to_height))
+            .select((
+                address_outputs::block_height,
+                blocks::block_timestamp,
+                transactions::transaction_index,
+                address_outputs::transaction_id,
+                address_outputs::output_index,
+                address_outputs::value_satoshis,
+            ))
+            .load(conn)
+            .context("Failed to query address receives")?;
+
+    // DB QUERY!
+    let spends: Vec<(i32, chrono::NaiveDateTime, i32, Vec<u8>, i32, i64)> = address_inputs::table
+        .inner_join(blocks::table.on(blocks::block_height.eq(address_inputs::block_height)))
+        .inner_join(
+            transactions::table.on(transactions::transaction_id
+                .eq(address_inputs::transaction_id)
+                .and(transactions::block_height.eq(address_inputs::block_height))),
+        )
+        .filter(address_inputs::address_id.eq(address_id_val))
+        .filter(address_inputs::block_height.between(from_height, to_height))
+        .select((
+            address_inputs::block_height,
+            blocks::block_timestamp,
+            transactions::transaction_index,
+            address_inputs::transaction_id,
+            address_inputs::input_index,
+            address_inputs::value_satoshis,
+        ))
+        .load(conn)
+        .context("Failed to query address spends")?;
+
+    // Unspendable outputs leave the balance when the later copy of their transaction is mined
+    // DB QUERY!
+    let overwrites: Vec<(i32, chrono::NaiveDateTime, i32, Vec<u8>, i32, i64)> =
+        address_outputs::table
+            .inner_join(
+                transactions::table.on(transactions::transaction_id
+                    .eq(address_outputs::transaction_id)
+                    .and(transactions::block_height.gt(address_outputs::block_height))),
+            )
+            .inner_join(blocks::table.on(blocks::block_height.eq(transactions::block_height)))
+            .filter(address_outputs::address_id.eq(address_id_val))
+            .filter(address_outputs::is_unspendable.eq(true))
+            .filter(transactions::block_height.between(from_height, to_height))
+            .select((
+                transactions::block_height,
+                blocks::block_timestamp,
+                transactions::transaction_index,
+                address_outputs::transaction_id,
+                address_outputs::output_index,
+                address_outputs::value_satoshis,
+            ))
+            .load(conn)
+            .context("Failed to query address overwritten outputs")?;
+
+    let to_event = |kind: AddressEventKind| {
+        move |(height, timestamp, tx_index, txid, index, value): (
+            i32,
+            chrono::NaiveDateTime,
+            i32,
+            Vec<u8>,
+            i32,
+            i64,
+        )| AddressEvent {
+            block_height: height,
+            block_timestamp: timestamp,
+            transaction_index: tx_index,
+            transaction_id: txid,
+            kind,
+            index,
+            value_satoshis: value,
+        }
+    };
+
+    let mut events: Vec<AddressEvent> = receives
+        .into_iter()
+        .map(to_event(AddressEventKind::Receive))
+        .chain(spends.into_iter().map(to_event(AddressEventKind::Spend)))
+        .chain(
+            overwrites
+                .into_iter()
+                .map(to_event(AddressEventKind::Overwritten)),
+        )
+        .collect();
+
+    // A transaction spends its inputs, or overwrites earlier outputs, before it creates its outputs
+    events.sort_by_key(|e| {
+        (
+            e.block_height,
+            e.transaction_index,
+            e.kind == AddressEventKind::Receive,
+            e.index,
+        )
+    });
+
+    Ok(events)
+}
+
+/// Get the first and last activity of an address, and how many times it received and spent
+pub fn get_address_activity(
+    conn: &mut PgConnection,
+    address: &models::Address,
+) -> Result<AddressActivity> {
+    use schema::{address_inputs, address_outputs, blocks};
+
+    // DB QUERY!
+    let last_receive_height = address_outputs::table
+        .filter(address_outputs::address_id.eq(address.address_id))
+        .select(diesel::dsl::max(address_outputs::block_height))
+        .first::<Option<i32>>(conn)
+        .context("Failed to query last receive")?;
+
+    // DB QUERY!
+    let last_spend_height = address_inputs::table
+        .filter(address_inputs::address_id.eq(address.address_id))
+        .select(diesel::dsl::max(address_inputs::block_height))
+        .first::<Option<i32>>(conn)
+        .context("Failed to query last spend")?;
+
+    let last_height = last_receive_height
+        .max(last_spend_height)
+        .unwrap_or(address.first_seen_block_height);
+
+    // DB QUERY!
+    let timestamps: Vec<(i32, chrono::NaiveDateTime)> = blocks::table
+        .filter(blocks::block_height.eq_any([address.first_seen_block_height, last_height]))
+        .select((blocks::block_height, blocks::block_timestamp))
+        .load(conn)
+        .context("Failed to query activity block timestamps")?;
+    let timestamp_at = |height: i32| {
+        timestamps
+            .iter()
+            .find(|(h, _)| *h == height)
+            .map(|(_, timestamp)| *timestamp)
+    };
+
+    Ok(AddressActivity {
+        first_height: address.first_seen_block_height,
+        first_timestamp: timestamp_at(address.first_seen_block_height),
+        last_height,
+        last_timestamp: timestamp_at(last_height),
+        last_receive_height,
+        last_spend_height,
+        receive_count: address.total_receive_count,
+        spend_count: address.total_spend_count,
+    })
+}
+
+/// Stores the aggregate statistics for a block, replacing any previous values
+/// so that reprocessing a block leaves a single, up to date set of rows
+pub fn store_block_stats(
+    conn: &mut PgConnection,
+    block_stats_val: &models::NewBlockStats,
+    script_type_stats_val: &[models::NewBlockScriptTypeStats],
+) -> Result<()> {
+    use diesel::{delete, insert_into};
+    use schema::{block_script_type_stats, block_stats};
+
+    insert_into(block_stats::table)
+        .values(block_stats_val)
+        .on_conflict(block_stats::block_height)
+        .do_update()
+        .set(block_stats_val)
+        .execute(conn)
+        .context("Failed to store block stats")?;
+
+    delete(
+        block_script_type_stats::table
+            .filter(block_script_type_stats::block_height.eq(block_stats_val.block_height)),
+    )
+    .execute(conn)
+    .context("Failed to clear block script type stats")?;
+
+    insert_into(block_script_type_stats::table)
+        .values(script_type_stats_val)
+        .execute(conn)
+        .context("Failed to store block script type stats")?;
+
+    Ok(())
+}
+
+/// Stores the coinbase data of a block and the miner it was attributed to, replacing any
+/// previous values so that reprocessing a block with an updated pool list re-attributes it
+pub fn store_coinbase(
+    conn: &mut PgConnection,
+    block_height_val: i32,
+    txid_str: &str,
+    coinbase: &CoinbaseInfo,
+    payout_addresses_val: &[String],
+    attribution: Option<&MinerAttribution>,
+) -> Result<()> {
+    use diesel::{delete, insert_into};
+    use schema::{block_miners, coinbase_transactions};
+
+    let record = models::CoinbaseTransaction {
+        block_height: block_height_val,
+        transaction_id: hex::decode(txid_str)
+            .context("Failed to decode transaction ID hex string")?,
+        script_sig: coinbase.script_sig.clone(),
+        bip34_height: coinbase.bip34_height,
+        ascii_tags: coinbase.ascii_tags.clone(),
+        extranonce: coinbase.extranonce.clone(),
+        has_witness_commitment: coinbase.has_witness_commitment,
+        payout_addresses: payout_addresses_val.to_vec(),
+        output_value_satoshis: coinbase.output_value_satoshis,
+        subsidy_satoshis: coinbase.subsidy_satoshis,
+    };
+
+    // DB UPSERT!
+    insert_into(coinbase_transactions::table)
+        .values(&record)
+        .on_conflict(coinbase_transactions::block_height)
+        .do_update()
+        .set(&record)
         .execute(conn)
-        .context("Failed to update address public key")?;
+        .context("Failed to store coinbase transaction")?;
+
+    delete(block_miners::table.filter(block_miners::block_height.eq(block_height_val)))
+        .execute(conn)
+        .context("Failed to clear block miner")?;
+
+    if let Some(attribution) = attribution {
+        // DB INSERT!
+        insert_into(block_miners::table)
+            .values(&models::BlockMiner {
+                block_height: block_height_val,
+                miner_name: attribution.miner_name.to_string(),
+                matched_by: attribution.matched_by,
+                matched_value: attribution.matched_value.to_string(),
+            })
+            .execute(conn)
+            .context("Failed to store block miner")?;
+    }
+
+    Ok(())
+}
+
+/// Store a transaction detected as a CoinJoin, replacing any previous record
+pub fn store_coinjoin(
+    conn: &mut PgConnection,
+    txid_str: &str,
+    block_height_val: i32,
+    coinjoin: &CoinJoin,
+) -> Result<()> {
+    use diesel::insert_into;
+    use schema::coinjoin_transactions::dsl::*;
+
+    let record = models::CoinJoinTransaction {
+        transaction_id: hex::decode(txid_str)
+            .context("Failed to decode transaction ID hex string")?,
+        block_height: block_height_val,
+        coinjoin_type: coinjoin.kind,
+        denomination_satoshis: coinjoin.denomination_satoshis as i64,
+        anonymity_set: coinjoin.anonymity_set as i32,
+        mixed_value_satoshis: coinjoin.mixed_value_satoshis() as i64,
+        coordinator_fee_satoshis: coinjoin.coordinator_fee_satoshis.map(|fee| fee as i64),
+    };
+
+    // DB UPSERT!
+    insert_into(coinjoin_transactions)
+        .values(&record)
+        .on_conflict((transaction_id, block_height))
+        .do_update()
+        .set(&record)
+        .execute(conn)
+        .context("Failed to store CoinJoin transaction")?;
+
+    Ok(())
+}
+
+/// Brings the unspent value by creation day up to date with a processed block: its outputs are
+/// added to its day, and the outputs it spends, or overwrites with a duplicate coinbase (BIP30),
+/// are taken from the days they were created on
+pub fn update_utxo_creation_days(conn: &mut PgConnection, block_height_val: i32) -> Result<()> {
+    use diesel::sql_query;
+    use diesel::sql_types::Integer;
+
+    // DB UPSERT!
+    sql_query(
+        "INSERT INTO utxo_creation_days (creation_date, output_count, value_satoshis)
+         SELECT b.block_timestamp::DATE, COUNT(*), SUM(o.value_satoshis)
+         FROM address_outputs o
+         JOIN blocks b ON b.block_height = o.block_height
+         WHERE o.block_height = $1
+         GROUP BY b.block_timestamp::DATE
+         ON CONFLICT (creation_date) DO UPDATE SET
+             output_count = utxo_creation_days.output_count + EXCLUDED.output_count,
+             value_satoshis = utxo_creation_days.value_satoshis + EXCLUDED.value_satoshis",
+    )
+    .bind::<Integer, _>(block_height_val)
+    .execute(conn)
+    .context("Failed to add outputs to UTXO creation days")?;
+
+    // DB UPDATE!
+    sql_query(
+        "WITH removed AS (
+             SELECT o.block_height, o.value_satoshis
+             FROM address_inputs i
+             JOIN address_outputs o
+               ON o.output_id = i.spent_output_id
+              AND o.block_height = i.spent_output_block_height
+             WHERE i.block_height = $1
+             UNION ALL
+             SELECT o.block_height, o.value_satoshis
+             FROM address_outputs o
+             JOIN transactions t ON t.transaction_id = o.transaction_id
+             WHERE t.block_height = $1
+               AND t.is_coinbase = TRUE
+               AND o.block_height < $1
+               AND o.is_spent = FALSE
+               AND o.is_unspendable = TRUE
+         )
+         UPDATE utxo_creation_days d
+         SET output_count = d.output_count - r.output_count,
+             value_satoshis = d.value_satoshis - r.value_satoshis
+         FROM (
+             SELECT b.block_timestamp::DATE AS creation_date,
+                    COUNT(*) AS output_count,
+                    SUM(removed.value_satoshis) AS value_satoshis
+             FROM removed
+             JOIN blocks b ON b.block_height = removed.block_height
+             GROUP BY b.block_timestamp::DATE
+         ) r
+         WHERE d.creation_date = r.creation_date",
+    )
+    .bind::<Integer, _>(block_height_val)
+    .execute(conn)
+    .context("Failed to take spent outputs from UTXO creation days")?;
+
+    Ok(())
+}
+
+/// Get the current unspent value by creation day, aged at the timestamp of the given block.
+/// Only meaningful for the last processed block, as the days hold the current unspent set.
+pub fn get_utxo_age_days(
+    conn: &mut PgConnection,
+    block_height_val: i32,
+) -> Result<Vec<UtxoAgeDay>> {
+    use diesel::sql_query;
+    use diesel::sql_types::Integer;
+
+    // DB QUERY!
+    sql_query(
+        "SELECT
+             ((SELECT block_timestamp::DATE FROM blocks WHERE block_height = $1) - creation_date)::INTEGER AS age_days,
+             output_count,
+             value_satoshis
+         FROM utxo_creation_days
+         WHERE output_count > 0",
+    )
+    .bind::<Integer, _>(block_height_val)
+    .load::<UtxoAgeDay>(conn)
+    .context("Failed to query UTXO creation days")
+}
+
+/// Stores the age distribution of the unspent set at a height, replacing any previous snapshot
+pub fn store_utxo_age_snapshot(
+    conn: &mut PgConnection,
+    block_height_val: i32,
+    bands: &[AgeBandTotals],
+) -> Result<()> {
+    use diesel::{delete, insert_into};
+    use schema::utxo_age_snapshots::dsl::*;
+
+    let snapshot: Vec<models::UtxoAgeSnapshot> = bands
+        .iter()
+        .map(|band| models::UtxoAgeSnapshot {
+            block_height: block_height_val,
+            age_band: band.age_band.to_string(),
+            band_index: band.band_index as i16,
+            output_count: band.output_count,
+            value_satoshis: band.value_satoshis,
+        })
+        .collect();
+
+    delete(utxo_age_snapshots.filter(block_height.eq(block_height_val)))
+        .execute(conn)
+        .context("Failed to clear UTXO age snapshot")?;
+
+    // DB INSERT!
+    insert_into(utxo_age_snapshots)
+        .values(&snapshot)
+        .execute(conn)
+        .context("Failed to store UTXO age snapshot")?;
+
+    Ok(())
+}
+
+/// Record an output using a script template, creating the template the first time it is seen
+pub fn record_script_template(
+    conn: &mut PgConnection,
+    template: &ScriptTemplate,
+    script_type_val: ScriptType,
+    block_height_val: i32,
+    txid_str: &str,
+) -> Result<()> {
+    use diesel::sql_query;
+    use diesel::sql_types::{Bytea, Integer, Text};
+
+    let txid_bytes = hex::decode(txid_str).context("Failed to decode transaction ID hex string")?;
+
+    // Only the first few transactions using a template are kept as examples
+    // DB UPSERT!
+    sql_query(
+        "INSERT INTO script_templates (
+            template_hash, template, script_type, occurrence_count,
+            first_seen_block_height, last_seen_block_height, example_txids
+        )
+        VALUES ($1, $2, $3, 1, $4, $4, ARRAY[$5])
+        ON CONFLICT (template_hash) DO UPDATE SET
+            occurrence_count = script_templates.occurrence_count + 1,
+            first_seen_block_height = LEAST(script_templates.first_seen_block_height, EXCLUDED.first_seen_block_height),
+            last_seen_block_height = GREATEST(script_templates.last_seen_block_height, EXCLUDED.last_seen_block_height),
+            example_txids = CASE
+                WHEN cardinality(script_templates.example_txids) < $6
+                     AND NOT $5 = ANY(script_templates.example_txids)
+                THEN array_append(script_templates.example_txids, $5)
+                ELSE script_templates.example_txids
+            END",
+    )
+    .bind::<Bytea, _>(&template.template_hash)
+    .bind::<Text, _>(&template.template)
+    .bind::<Text, _>(script_type_val.as_str())
+    .bind::<Integer, _>(block_height_val)
+    .bind::<Bytea, _>(&txid_bytes)
+    .bind::<Integer, _>(MAX_TEMPLATE_EXAMPLES)
+    .execute(conn)
+    .context("Failed to record script template")?;
 
     Ok(())
 }
 
+/// Name of the partition of `table` holding the blocks from `first_height`
+fn partition_name(table: &str, first_height: i32) -> String {
+    format!("{}_p{:07}", table, first_height)
+}
+
+/// Quotes an identifier for use in a DDL statement
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Creates the partitions holding the block at `block_height_val` in each table partitioned
+/// by height, if they don't exist yet. Returns the first height after them.
+pub fn ensure_height_partitions(conn: &mut PgConnection, block_height_val: i32) -> Result<i32> {
+    use diesel::sql_query;
+
+    let first_height = block_height_val - block_height_val % PARTITION_BLOCKS;
+    let end_height = first_height + PARTITION_BLOCKS;
+
+    for table in PARTITIONED_TABLES {
+        sql_query(format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ({}) TO ({})",
+            partition_name(table, first_height),
+            table,
+            first_height,
+            end_height
+        ))
+        .execute(conn)
+        .with_context(|| {
+            format!(
+                "Failed to create partition of {} from {}",
+                table, first_height
+            )
+        })?;
+    }
+
+    Ok(end_height)
+}
+
+/// Get the partitions of the tables partitioned by height, by table and first height
+pub fn get_height_partitions(conn: &mut PgConnection) -> Result<Vec<HeightPartition>> {
+    use diesel::sql_query;
+    use diesel::sql_types::{Array, Text};
+
+    // DB QUERY!
+    sql_query(
+        r"SELECT
+              parent.relname::TEXT AS table_name,
+              child.relname::TEXT AS partition_name,
+              substring(pg_get_expr(child.relpartbound, child.oid) FROM 'FROM \((\d+)\)')::INTEGER AS from_height,
+              substring(pg_get_expr(child.relpartbound, child.oid) FROM 'TO \((\d+)\)')::INTEGER AS to_height,
+              GREATEST(child.reltuples, 0)::BIGINT AS estimated_rows,
+              pg_total_relation_size(child.oid) AS total_bytes
+          FROM pg_inherits i
+          JOIN pg_class parent ON parent.oid = i.inhparent
+          JOIN pg_class child ON child.oid = i.inhrelid
+          WHERE parent.relnamespace = 'public'::REGNAMESPACE
+            AND parent.relname = ANY($1)
+          ORDER BY parent.relname, from_height",
+    )
+    .bind::<Array<Text>, _>(PARTITIONED_TABLES.to_vec())
+    .load::<HeightPartition>(conn)
+    .context("Failed to query height partitions")
+}
+
+/// Whether any outputs created from `from_height` up to (but excluding) `to_height` are still
+/// unspent and spendable, which the processor looks up when they are spent
+pub fn has_unspent_outputs(
+    conn: &mut PgConnection,
+    from_height: i32,
+    to_height: i32,
+) -> Result<bool> {
+    use diesel::dsl::exists;
+    use schema::address_outputs::dsl::*;
+
+    // DB QUERY!
+    diesel::select(exists(
+        address_outputs
+            .filter(block_height.ge(from_height))
+            .filter(block_height.lt(to_height))
+            .filter(is_spent.eq(false))
+            .filter(is_unspendable.eq(false)),
+    ))
+    .get_result(conn)
+    .context("Failed to query unspent outputs")
+}
+
+/// Detaches a partition from its table and moves it, with its rows, to the archive schema and
+/// optionally to another tablespace (such as one on cheaper storage), indexes included.
+/// From there it can be dumped and dropped, or attached again.
+pub fn archive_height_partition(
+    conn: &mut PgConnection,
+    partition: &HeightPartition,
+    tablespace: Option<&str>,
+) -> Result<()> {
+    use diesel::sql_query;
+
+    let archived = format!(
+        "{}.{}",
+        ARCHIVE_SCHEMA,
+        quote_identifier(&partition.partition_name)
+    );
+
+    conn.transaction(|conn| {
+        sql_query(format!(
+            "ALTER TABLE {} DETACH PARTITION {}",
+            quote_identifier(&partition.table_name),
+            quote_identifier(&partition.partition_name)
+        ))
+        .execute(conn)?;
+        sql_query(format!("CREATE SCHEMA IF NOT EXISTS {}", ARCHIVE_SCHEMA)).execute(conn)?;
+        sql_query(format!(
+            "ALTER TABLE {} SET SCHEMA {}",
+            quote_identifier(&partition.partition_name),
+            ARCHIVE_SCHEMA
+        ))
+        .execute(conn)?;
+
+        if let Some(tablespace) = tablespace {
+            let tablespace = quote_identifier(tablespace);
+            sql_query(format!(
+                "ALTER TABLE {} SET TABLESPACE {}",
+                archived, tablespace
+            ))
+            .execute(conn)?;
+
+            // DB QUERY!
+            let indexes = sql_query(
+                "SELECT indexrelid::REGCLASS::TEXT AS index_name FROM pg_index
+                 WHERE indrelid = $1::REGCLASS",
+            )
+            .bind::<diesel::sql_types::Text, _>(&archived)
+            .load::<IndexNameRow>(conn)?;
+            for index in indexes {
+                sql_query(format!(
+                    "ALTER INDEX {} SET TABLESPACE {}",
+                    index.index_name, tablespace
+                ))
+                .execute(conn)?;
+            }
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })
+    .with_context(|| format!("Failed to archive partition {}", partition.partition_name))
+}
+
 /// Structure to return output information
 pub struct OutputInfo {
     pub output_id: i64,
     pub address_id: i64,
     pub value_satoshis: i64,
+    pub script_type: ScriptType,
+    pub block_height: i32, // Block the output was created in
+    pub block_timestamp: chrono::NaiveDateTime,
+}
+
+/// Structure to return the result of an address lookup
+pub struct AddressLookup {
+    pub address_id: i64,
+    pub created: bool, // True if the address was first seen in this call
+    pub first_seen_block_height: i32,
+    pub first_output_id: Option<i64>, // Its first output, if stored in the block being processed
+    pub is_public_key_exposed: bool,
+    pub exposed_by_output_script: bool, // Created exposed, as its output script holds a valid key
+    pub exposed_by_sibling: bool,       // Created exposed in this call, through a sibling's key
+    pub newly_exposed_siblings: Vec<i64>, // Sibling addresses exposed by its output script
+}
+
+impl AddressLookup {
+    /// Whether the stored output `output_id` is the first the address received, on the first
+    /// pass over its block or when the block is reprocessed
+    pub fn is_first_output(&self, output_id: i64) -> bool {
+        self.created || self.first_output_id == Some(output_id)
+    }
+}
+
+/// Whether an address history event received or spent value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressEventKind {
+    Receive,
+    Spend,
+    Overwritten, // An unspendable output, overwritten by a duplicate coinbase (BIP30)
+}
+
+/// Structure to return one receive or spend in an address history
+pub struct AddressEvent {
+    pub block_height: i32,
+    pub block_timestamp: chrono::NaiveDateTime,
+    pub transaction_index: i32,
+    pub transaction_id: Vec<u8>,
+    pub kind: AddressEventKind,
+    pub index: i32, // Output index of a receive or overwritten output, input index of a spend
+    pub value_satoshis: i64,
+}
+
+/// Structure to return the first and last activity of an address
+pub struct AddressActivity {
+    pub first_height: i32,
+    pub first_timestamp: Option<chrono::NaiveDateTime>,
+    pub last_height: i32,
+    pub last_timestamp: Option<chrono::NaiveDateTime>,
+    pub last_receive_height: Option<i32>,
+    pub last_spend_height: Option<i32>,
+    pub receive_count: i32,
+    pub spend_count: i32,
+}
+
+/// Structure to return one partition of a table partitioned by height
+#[derive(QueryableByName, Debug)]
+pub struct HeightPartition {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub table_name: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub partition_name: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub from_height: i32, // Inclusive
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub to_height: i32, // Exclusive
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub estimated_rows: i64, // As of the last ANALYZE
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub total_bytes: i64, // Table and indexes
+}
+
+/// Name of an index, as a qualified and quoted identifier
+#[derive(QueryableByName)]
+struct IndexNameRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    index_name: String,
+}
+
+/// Structure to return the result of storing an output
+pub struct StoredOutput {
+    pub output_id: i64,
+    pub received_after_exposure: bool, // As stored the first time the output was processed
+}
+
+/// Structure to return the result of storing an input
+pub struct StoredInput {
+    pub input_id: i64,
+    pub public_key_newly_exposed: bool,
+    pub newly_exposed_siblings: Vec<i64>, // Sibling addresses exposed by the revealed key
 }
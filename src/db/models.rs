@@ -1,13 +1,22 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use serde_json::Value;
+
+use crate::change::ChangeLikelihood;
+use crate::classifier::encode_address;
+use crate::coinbase::miners::MinerMatch;
+use crate::coinjoin::CoinJoinKind;
+use crate::script_type::{ExposureSource, PubkeyFormat, ScriptExtraData, ScriptType};
+use crate::timelock::{LockTimeKind, RelativeLockKind};
 
 use super::schema::{
-    address_inputs, address_outputs, addresses, blocks, transactions, txid_block_index,
+    address_inputs, address_outputs, addresses, block_miners, block_script_type_stats, block_stats,
+    blocks, clusters, coinbase_transactions, coinjoin_transactions, inscriptions, multisig_keys,
+    multisig_scripts, public_key_siblings, signatures, transactions, txid_block_index,
+    utxo_age_snapshots,
 };
 
 // Model for querying and inserting into 'blocks' table
-#[derive(Queryable, Selectable, Insertable)]
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
 #[diesel(table_name = blocks)]
 pub struct Block {
     pub block_height: i32,
@@ -27,10 +36,23 @@ pub struct NewTransaction {
     pub fee_satoshis: Option<i64>,
     pub input_count: i32,
     pub output_count: i32,
+    pub base_size: i32,
+    pub total_size: i32,
+    pub witness_size: i32,
+    pub weight: i32,
+    pub vsize: i32,
+    pub segwit_input_count: i32,
+    pub taproot_input_count: i32, // Set once the spent outputs are known
+    pub version: i32,
+    pub lock_time: i64,               // nLockTime (u32)
+    pub lock_time_type: LockTimeKind, // VARCHAR(10)
+    pub is_lock_time_enforced: bool,
+    pub is_anti_fee_sniping: bool,
+    pub signals_rbf: bool,
 }
 
 // Model for querying 'transactions' table
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = transactions)]
 #[diesel(primary_key(transaction_id, block_height))]
 pub struct Transaction {
@@ -41,32 +63,66 @@ pub struct Transaction {
     pub fee_satoshis: Option<i64>,
     pub input_count: i32,
     pub output_count: i32,
+    pub base_size: Option<i32>,
+    pub total_size: Option<i32>,
+    pub witness_size: Option<i32>,
+    pub weight: Option<i32>,
+    pub vsize: Option<i32>,
+    pub segwit_input_count: Option<i32>,
+    pub taproot_input_count: Option<i32>,
+    pub version: Option<i32>,
+    pub lock_time: Option<i64>,
+    pub lock_time_type: Option<LockTimeKind>,
+    pub is_lock_time_enforced: Option<bool>,
+    pub is_anti_fee_sniping: Option<bool>,
+    pub signals_rbf: Option<bool>,
 }
 
 // Model for inserting into the 'addresses' table
 #[derive(Insertable)]
 #[diesel(table_name = addresses)]
 pub struct NewAddress {
-    pub address_string: String, // VARCHAR(255)
-    pub script_type: String,    // VARCHAR(20)
+    pub address_hash: Vec<u8>,   // BYTEA
+    pub script_type: ScriptType, // VARCHAR(20)
     pub first_seen_block_height: i32,
-    pub script_extra_data: Option<Value>, // JSONB
-    pub public_key: Option<Vec<u8>>,      // BYTEA
+    pub script_extra_data: Option<ScriptExtraData>, // JSONB
+    pub public_key: Option<Vec<u8>>,                // BYTEA
+    pub is_public_key_exposed: bool,
+    pub public_key_exposed_height: Option<i32>,
+    pub public_key_exposure_source: Option<ExposureSource>, // VARCHAR(20)
+    pub script_template_hash: Option<Vec<u8>>,              // BYTEA
+    pub public_key_format: Option<PubkeyFormat>,            // VARCHAR(12)
+    pub public_key_exposure_input_id: Option<i64>,
+    pub public_key_exposed_via_address_id: Option<i64>,
 }
 
 // Model for querying 'addresses' table
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = addresses)]
 pub struct Address {
     pub address_id: i64,
-    pub address_string: String,
-    pub script_type: String,
+    pub address_hash: Vec<u8>,
+    pub script_type: ScriptType,
     pub first_seen_block_height: i32,
     pub total_receive_count: i32,
     pub total_spend_count: i32,
     pub is_public_key_exposed: bool,
     pub public_key: Option<Vec<u8>>,
-    pub script_extra_data: Option<Value>,
+    pub script_extra_data: Option<ScriptExtraData>,
+    pub public_key_exposed_height: Option<i32>,
+    pub public_key_exposure_source: Option<ExposureSource>,
+    pub script_template_hash: Option<Vec<u8>>,
+    pub public_key_format: Option<PubkeyFormat>,
+    pub cluster_id: Option<i64>,
+    pub public_key_exposure_input_id: Option<i64>, // The spend that revealed the key
+    pub public_key_exposed_via_address_id: Option<i64>, // The sibling it was exposed through
+}
+
+impl Address {
+    /// The human-readable address, derived from the script type and address hash
+    pub fn address_string(&self) -> String {
+        encode_address(self.script_type, &self.address_hash)
+    }
 }
 
 // Model for inserting into the 'address_outputs' table
@@ -79,10 +135,11 @@ pub struct NewAddressOutput {
     pub output_index: i32,
     pub value_satoshis: i64,
     pub spending_input_id: Option<i64>,
+    pub received_after_exposure: bool,
 }
 
 // Model for querying 'address_outputs' table
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = address_outputs)]
 pub struct AddressOutput {
     pub output_id: i64,
@@ -93,6 +150,10 @@ pub struct AddressOutput {
     pub value_satoshis: i64,
     pub is_spent: bool,
     pub spending_input_id: Option<i64>,
+    pub received_after_exposure: bool,
+    pub is_unspendable: bool, // Overwritten by a duplicate coinbase (BIP30)
+    pub change_likelihood: Option<ChangeLikelihood>, // VARCHAR(10), NULL if not analysed
+    pub change_heuristics: Option<Vec<String>>, // TEXT[]
 }
 
 // Model for inserting into the 'address_inputs' table
@@ -106,10 +167,18 @@ pub struct NewAddressInput {
     pub spent_output_id: i64,
     pub value_satoshis: i64,
     pub public_key_revealed: Option<Vec<u8>>, // BYTEA
+    pub public_key_revealed_format: Option<PubkeyFormat>, // VARCHAR(12)
+    pub sequence: i64,                        // nSequence (u32)
+    pub relative_lock_type: Option<RelativeLockKind>, // VARCHAR(10)
+    pub relative_lock_value: Option<i32>,
+    pub spent_output_block_height: i32,
+    pub spent_output_timestamp: NaiveDateTime,
+    pub coin_age_blocks: i32,
+    pub coin_age_seconds: i64,
 }
 
 // Model for querying 'address_inputs' table
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = address_inputs)]
 pub struct AddressInput {
     pub input_id: i64,
@@ -120,6 +189,14 @@ pub struct AddressInput {
     pub spent_output_id: i64,
     pub value_satoshis: i64,
     pub public_key_revealed: Option<Vec<u8>>,
+    pub public_key_revealed_format: Option<PubkeyFormat>,
+    pub sequence: Option<i64>,
+    pub relative_lock_type: Option<RelativeLockKind>,
+    pub relative_lock_value: Option<i32>,
+    pub spent_output_block_height: Option<i32>, // NULL for inputs stored before coin ages
+    pub spent_output_timestamp: Option<NaiveDateTime>,
+    pub coin_age_blocks: Option<i32>,
+    pub coin_age_seconds: Option<i64>,
 }
 
 // Model for inserting into the 'txid_block_index' table
@@ -130,10 +207,197 @@ pub struct NewTxidBlockIndex {
     pub block_height: i32,
 }
 
-// Model for querying 'txid_block_index' table
-#[derive(Queryable, Selectable)]
-#[diesel(table_name = txid_block_index)]
-pub struct TxidBlockIndex {
-    pub transaction_id: Vec<u8>,
+// Model for inserting into (and upserting) the 'block_stats' table
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = block_stats)]
+#[diesel(primary_key(block_height))]
+pub struct NewBlockStats {
+    pub block_height: i32,
+    pub new_address_count: i32,
+    pub reused_address_count: i32,
+    pub newly_exposed_public_key_count: i32,
+    pub newly_exposed_value_satoshis: i64,
+    pub coinbase_value_satoshis: i64,
+    pub coinjoin_count: i32,
+    pub coinjoin_value_satoshis: i64,
+    pub coin_days_destroyed: f64,
+    pub average_spent_age_days: f64,
+}
+
+// Model for querying 'block_stats' table
+#[derive(Queryable, QueryableByName, Selectable)]
+#[diesel(table_name = block_stats)]
+pub struct BlockStats {
+    pub block_height: i32,
+    pub new_address_count: i32,
+    pub reused_address_count: i32,
+    pub newly_exposed_public_key_count: i32,
+    pub newly_exposed_value_satoshis: i64,
+    pub coinbase_value_satoshis: i64,
+    pub coinjoin_count: Option<i32>, // NULL for blocks processed before CoinJoins were detected
+    pub coinjoin_value_satoshis: Option<i64>,
+    pub coin_days_destroyed: f64,
+    pub average_spent_age_days: f64,
+}
+
+// Model for inserting into the 'block_script_type_stats' table
+#[derive(Insertable)]
+#[diesel(table_name = block_script_type_stats)]
+pub struct NewBlockScriptTypeStats {
+    pub block_height: i32,
+    pub script_type: ScriptType, // VARCHAR(20)
+    pub output_count: i32,
+    pub output_value_satoshis: i64,
+    pub input_count: i32,
+    pub input_value_satoshis: i64,
+    pub reused_output_count: i32,
+    pub post_exposure_output_count: i32,
+    pub post_exposure_value_satoshis: i64,
+}
+
+// Model for querying 'block_script_type_stats' table
+#[derive(Queryable, QueryableByName, Selectable)]
+#[diesel(table_name = block_script_type_stats)]
+#[diesel(primary_key(block_height, script_type))]
+pub struct BlockScriptTypeStats {
+    pub block_height: i32,
+    pub script_type: ScriptType,
+    pub output_count: i32,
+    pub output_value_satoshis: i64,
+    pub input_count: i32,
+    pub input_value_satoshis: i64,
+    pub reused_output_count: i32,
+    pub post_exposure_output_count: i32,
+    pub post_exposure_value_satoshis: i64,
+}
+
+// Model for querying 'clusters' table
+#[derive(Queryable, QueryableByName, Selectable)]
+#[diesel(table_name = clusters)]
+#[diesel(primary_key(cluster_id))]
+pub struct Cluster {
+    pub cluster_id: i64, // address_id of the representative address
+    pub address_count: i32,
+    pub first_block_height: i32,
+}
+
+// Model for querying and inserting into (and upserting) the 'coinbase_transactions' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = coinbase_transactions)]
+#[diesel(primary_key(block_height))]
+#[diesel(treat_none_as_null = true)]
+pub struct CoinbaseTransaction {
+    pub block_height: i32,
+    pub transaction_id: Vec<u8>, // BYTEA
+    pub script_sig: Vec<u8>,     // BYTEA
+    pub bip34_height: Option<i32>,
+    pub ascii_tags: Vec<String>, // TEXT[]
+    pub extranonce: Option<Vec<u8>>,
+    pub has_witness_commitment: bool,
+    pub payout_addresses: Vec<String>, // TEXT[]
+    pub output_value_satoshis: i64,
+    pub subsidy_satoshis: i64,
+}
+
+// Model for querying and inserting into 'block_miners' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = block_miners)]
+#[diesel(primary_key(block_height))]
+pub struct BlockMiner {
     pub block_height: i32,
+    pub miner_name: String,
+    pub matched_by: MinerMatch, // VARCHAR(20)
+    pub matched_value: String,
+}
+
+// Model for inserting into the 'inscriptions' table
+#[derive(Insertable)]
+#[diesel(table_name = inscriptions)]
+pub struct NewInscription {
+    pub input_id: i64,
+    pub transaction_id: Vec<u8>, // BYTEA
+    pub block_height: i32,
+    pub input_index: i32,
+    pub envelope_index: i16,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub body_size: i32,
+    pub envelope_size: i32,
+}
+
+// Model for querying and inserting into 'multisig_scripts' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = multisig_scripts)]
+#[diesel(primary_key(address_id))]
+pub struct MultisigScript {
+    pub address_id: i64,
+    pub required_signatures: i16, // m
+    pub total_keys: i16,          // n
+}
+
+// Model for querying and inserting into 'multisig_keys' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = multisig_keys)]
+#[diesel(primary_key(address_id, key_index))]
+pub struct MultisigKey {
+    pub address_id: i64,
+    pub key_index: i16,
+    pub public_key: Vec<u8>, // BYTEA
+    pub public_key_format: PubkeyFormat,
+}
+
+// Model for inserting into the 'signatures' table
+#[derive(Insertable)]
+#[diesel(table_name = signatures)]
+pub struct NewSignature {
+    pub input_id: i64,
+    pub block_height: i32,
+    pub signature_index: i16,
+    pub in_witness: bool,
+    pub public_key: Option<Vec<u8>>, // BYTEA
+    pub r_value: Vec<u8>,            // BYTEA
+    pub s_value: Vec<u8>,            // BYTEA
+    pub sighash_type: i16,
+    pub is_low_r: bool,
+    pub is_low_s: bool,
+    pub is_strict_der: bool,
+}
+
+// Model for querying and inserting into 'coinjoin_transactions' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = coinjoin_transactions)]
+#[diesel(primary_key(transaction_id, block_height))]
+#[diesel(treat_none_as_null = true)]
+pub struct CoinJoinTransaction {
+    pub transaction_id: Vec<u8>, // BYTEA
+    pub block_height: i32,
+    pub coinjoin_type: CoinJoinKind, // VARCHAR(12)
+    pub denomination_satoshis: i64,
+    pub anonymity_set: i32,
+    pub mixed_value_satoshis: i64,
+    pub coordinator_fee_satoshis: Option<i64>,
+}
+
+// Model for querying and inserting into 'utxo_age_snapshots' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = utxo_age_snapshots)]
+pub struct UtxoAgeSnapshot {
+    pub block_height: i32,
+    pub age_band: String, // VARCHAR(8)
+    pub band_index: i16,
+    pub output_count: i64,
+    pub value_satoshis: i64,
+}
+
+// Model for querying and inserting into 'public_key_siblings' table
+#[derive(Queryable, QueryableByName, Selectable, Insertable)]
+#[diesel(table_name = public_key_siblings)]
+pub struct PublicKeySibling {
+    pub address_hash: Vec<u8>,           // BYTEA
+    pub script_type: ScriptType,         // VARCHAR(20)
+    pub public_key: Vec<u8>,             // BYTEA
+    pub public_key_format: PubkeyFormat, // VARCHAR(12)
+    pub source_address_id: i64,
+    pub exposure_input_id: Option<i64>,
+    pub exposed_block_height: i32,
 }
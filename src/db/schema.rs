@@ -1,7 +1,7 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    address_inputs (input_id) {
+    address_inputs (input_id, block_height) {
         input_id -> Int8,
         address_id -> Int8,
         transaction_id -> Bytea,
@@ -10,11 +10,21 @@ diesel::table! {
         spent_output_id -> Int8,
         value_satoshis -> Int8,
         public_key_revealed -> Nullable<Bytea>,
+        #[max_length = 12]
+        public_key_revealed_format -> Nullable<Varchar>,
+        sequence -> Nullable<Int8>,
+        #[max_length = 10]
+        relative_lock_type -> Nullable<Varchar>,
+        relative_lock_value -> Nullable<Int4>,
+        spent_output_block_height -> Nullable<Int4>,
+        spent_output_timestamp -> Nullable<Timestamp>,
+        coin_age_blocks -> Nullable<Int4>,
+        coin_age_seconds -> Nullable<Int8>,
     }
 }
 
 diesel::table! {
-    address_outputs (output_id) {
+    address_outputs (output_id, block_height) {
         output_id -> Int8,
         address_id -> Int8,
         transaction_id -> Bytea,
@@ -23,14 +33,17 @@ diesel::table! {
         value_satoshis -> Int8,
         is_spent -> Bool,
         spending_input_id -> Nullable<Int8>,
+        received_after_exposure -> Bool,
+        is_unspendable -> Bool,
+        #[max_length = 10]
+        change_likelihood -> Nullable<Varchar>,
+        change_heuristics -> Nullable<Array<Text>>,
     }
 }
 
 diesel::table! {
     addresses (address_id) {
         address_id -> Int8,
-        #[max_length = 255]
-        address_string -> Varchar,
         #[max_length = 20]
         script_type -> Varchar,
         first_seen_block_height -> Int4,
@@ -39,6 +52,57 @@ diesel::table! {
         is_public_key_exposed -> Bool,
         public_key -> Nullable<Bytea>,
         script_extra_data -> Nullable<Jsonb>,
+        public_key_exposed_height -> Nullable<Int4>,
+        #[max_length = 20]
+        public_key_exposure_source -> Nullable<Varchar>,
+        script_template_hash -> Nullable<Bytea>,
+        #[max_length = 12]
+        public_key_format -> Nullable<Varchar>,
+        cluster_id -> Nullable<Int8>,
+        public_key_exposure_input_id -> Nullable<Int8>,
+        public_key_exposed_via_address_id -> Nullable<Int8>,
+        address_hash -> Bytea,
+    }
+}
+
+diesel::table! {
+    block_miners (block_height) {
+        block_height -> Int4,
+        #[max_length = 100]
+        miner_name -> Varchar,
+        #[max_length = 20]
+        matched_by -> Varchar,
+        matched_value -> Text,
+    }
+}
+
+diesel::table! {
+    block_script_type_stats (block_height, script_type) {
+        block_height -> Int4,
+        #[max_length = 20]
+        script_type -> Varchar,
+        output_count -> Int4,
+        output_value_satoshis -> Int8,
+        input_count -> Int4,
+        input_value_satoshis -> Int8,
+        reused_output_count -> Int4,
+        post_exposure_output_count -> Int4,
+        post_exposure_value_satoshis -> Int8,
+    }
+}
+
+diesel::table! {
+    block_stats (block_height) {
+        block_height -> Int4,
+        new_address_count -> Int4,
+        reused_address_count -> Int4,
+        newly_exposed_public_key_count -> Int4,
+        newly_exposed_value_satoshis -> Int8,
+        coinbase_value_satoshis -> Int8,
+        coinjoin_count -> Nullable<Int4>,
+        coinjoin_value_satoshis -> Nullable<Int8>,
+        coin_days_destroyed -> Float8,
+        average_spent_age_days -> Float8,
     }
 }
 
@@ -51,6 +115,103 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    clusters (cluster_id) {
+        cluster_id -> Int8,
+        address_count -> Int4,
+        first_block_height -> Int4,
+        last_merged_block_height -> Int4,
+    }
+}
+
+diesel::table! {
+    coinbase_transactions (block_height) {
+        block_height -> Int4,
+        transaction_id -> Bytea,
+        script_sig -> Bytea,
+        bip34_height -> Nullable<Int4>,
+        ascii_tags -> Array<Text>,
+        extranonce -> Nullable<Bytea>,
+        has_witness_commitment -> Bool,
+        payout_addresses -> Array<Text>,
+        output_value_satoshis -> Int8,
+        subsidy_satoshis -> Int8,
+    }
+}
+
+diesel::table! {
+    coinjoin_transactions (transaction_id, block_height) {
+        transaction_id -> Bytea,
+        block_height -> Int4,
+        #[max_length = 12]
+        coinjoin_type -> Varchar,
+        denomination_satoshis -> Int8,
+        anonymity_set -> Int4,
+        mixed_value_satoshis -> Int8,
+        coordinator_fee_satoshis -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    inscriptions (inscription_id) {
+        inscription_id -> Int8,
+        input_id -> Int8,
+        transaction_id -> Bytea,
+        block_height -> Int4,
+        input_index -> Int4,
+        envelope_index -> Int2,
+        content_type -> Nullable<Text>,
+        content_encoding -> Nullable<Text>,
+        body_size -> Int4,
+        envelope_size -> Int4,
+    }
+}
+
+diesel::table! {
+    multisig_keys (address_id, key_index) {
+        address_id -> Int8,
+        key_index -> Int2,
+        public_key -> Bytea,
+        #[max_length = 12]
+        public_key_format -> Varchar,
+    }
+}
+
+diesel::table! {
+    multisig_scripts (address_id) {
+        address_id -> Int8,
+        required_signatures -> Int2,
+        total_keys -> Int2,
+    }
+}
+
+diesel::table! {
+    public_key_siblings (address_hash, script_type) {
+        #[max_length = 20]
+        script_type -> Varchar,
+        public_key -> Bytea,
+        #[max_length = 12]
+        public_key_format -> Varchar,
+        source_address_id -> Int8,
+        exposure_input_id -> Nullable<Int8>,
+        exposed_block_height -> Int4,
+        address_hash -> Bytea,
+    }
+}
+
+diesel::table! {
+    script_templates (template_hash) {
+        template_hash -> Bytea,
+        template -> Text,
+        #[max_length = 20]
+        script_type -> Varchar,
+        occurrence_count -> Int8,
+        first_seen_block_height -> Int4,
+        last_seen_block_height -> Int4,
+        example_txids -> Array<Bytea>,
+    }
+}
+
 diesel::table! {
     script_types (script_type) {
         #[max_length = 20]
@@ -60,6 +221,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    signatures (signature_id) {
+        signature_id -> Int8,
+        input_id -> Int8,
+        block_height -> Int4,
+        signature_index -> Int2,
+        in_witness -> Bool,
+        public_key -> Nullable<Bytea>,
+        r_value -> Bytea,
+        s_value -> Bytea,
+        sighash_type -> Int2,
+        is_low_r -> Bool,
+        is_low_s -> Bool,
+        is_strict_der -> Bool,
+    }
+}
+
 diesel::table! {
     transactions (transaction_id, block_height) {
         transaction_id -> Bytea,
@@ -69,6 +247,20 @@ diesel::table! {
         fee_satoshis -> Nullable<Int8>,
         input_count -> Int4,
         output_count -> Int4,
+        base_size -> Nullable<Int4>,
+        total_size -> Nullable<Int4>,
+        witness_size -> Nullable<Int4>,
+        weight -> Nullable<Int4>,
+        vsize -> Nullable<Int4>,
+        segwit_input_count -> Nullable<Int4>,
+        taproot_input_count -> Nullable<Int4>,
+        version -> Nullable<Int4>,
+        lock_time -> Nullable<Int8>,
+        #[max_length = 10]
+        lock_time_type -> Nullable<Varchar>,
+        is_lock_time_enforced -> Nullable<Bool>,
+        is_anti_fee_sniping -> Nullable<Bool>,
+        signals_rbf -> Nullable<Bool>,
     }
 }
 
@@ -79,18 +271,63 @@ diesel::table! {
     }
 }
 
-diesel::joinable!(address_inputs -> address_outputs (spent_output_id));
+diesel::table! {
+    utxo_age_snapshots (block_height, age_band) {
+        block_height -> Int4,
+        #[max_length = 8]
+        age_band -> Varchar,
+        band_index -> Int2,
+        output_count -> Int8,
+        value_satoshis -> Int8,
+    }
+}
+
+diesel::table! {
+    utxo_creation_days (creation_date) {
+        creation_date -> Date,
+        output_count -> Int8,
+        value_satoshis -> Int8,
+    }
+}
+
 diesel::joinable!(address_inputs -> addresses (address_id));
 diesel::joinable!(address_outputs -> addresses (address_id));
+diesel::joinable!(addresses -> clusters (cluster_id));
+diesel::joinable!(addresses -> script_templates (script_template_hash));
 diesel::joinable!(addresses -> script_types (script_type));
+diesel::joinable!(block_miners -> blocks (block_height));
+diesel::joinable!(block_script_type_stats -> blocks (block_height));
+diesel::joinable!(block_script_type_stats -> script_types (script_type));
+diesel::joinable!(block_stats -> blocks (block_height));
+diesel::joinable!(coinbase_transactions -> blocks (block_height));
+diesel::joinable!(multisig_keys -> multisig_scripts (address_id));
+diesel::joinable!(multisig_scripts -> addresses (address_id));
+diesel::joinable!(public_key_siblings -> addresses (source_address_id));
+diesel::joinable!(public_key_siblings -> script_types (script_type));
+diesel::joinable!(script_templates -> script_types (script_type));
 diesel::joinable!(transactions -> blocks (block_height));
+diesel::joinable!(utxo_age_snapshots -> blocks (block_height));
 
 diesel::allow_tables_to_appear_in_same_query!(
     address_inputs,
     address_outputs,
     addresses,
+    block_miners,
+    block_script_type_stats,
+    block_stats,
     blocks,
+    clusters,
+    coinbase_transactions,
+    coinjoin_transactions,
+    inscriptions,
+    multisig_keys,
+    multisig_scripts,
+    public_key_siblings,
+    script_templates,
     script_types,
+    signatures,
     transactions,
     txid_block_index,
+    utxo_age_snapshots,
+    utxo_creation_days,
 );
@@ -0,0 +1,703 @@
+use anyhow::{Context, Result};
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Float64Array, Int32Array, Int64Array, StringArray,
+    TimestampSecondArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use clap::{Args, ValueEnum};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::QueryableByName;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::db::{self, models};
+
+/// Export the analytics tables to Parquet or CSV files, partitioned by block height range
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Parquet)]
+    format: ExportFormat,
+    /// Tables to export (defaults to all)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    tables: Vec<ExportTable>,
+    /// First block height to export
+    #[arg(long, default_value_t = 0)]
+    from: u32,
+    /// Last block height to export (defaults to the last processed block)
+    #[arg(long)]
+    to: Option<u32>,
+    /// Number of blocks per output file
+    #[arg(long, default_value_t = 10_000)]
+    partition_size: u32,
+    /// Encoding of txids, hashes and public keys. CSV always writes bytes as hex
+    #[arg(long, value_enum, default_value_t = BytesEncoding::Hex)]
+    txid_encoding: BytesEncoding,
+    /// Number of rows fetched from the database at a time
+    #[arg(long, default_value_t = 50_000)]
+    batch_size: u32,
+    /// Directory to write the files to, one sub-directory per table
+    #[arg(long, default_value = "export")]
+    output_dir: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Parquet,
+    Csv,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// How byte columns (txids, hashes, public keys) are written
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BytesEncoding {
+    Hex,
+    Binary,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportTable {
+    Blocks,
+    Transactions,
+    Addresses,
+    AddressOutputs,
+    AddressInputs,
+    BlockStats,
+    BlockScriptTypeStats,
+}
+
+impl ExportTable {
+    const ALL: [ExportTable; 7] = [
+        ExportTable::Blocks,
+        ExportTable::Transactions,
+        ExportTable::Addresses,
+        ExportTable::AddressOutputs,
+        ExportTable::AddressInputs,
+        ExportTable::BlockStats,
+        ExportTable::BlockScriptTypeStats,
+    ];
+
+    fn table_name(self) -> &'static str {
+        match self {
+            ExportTable::Blocks => "blocks",
+            ExportTable::Transactions => "transactions",
+            ExportTable::Addresses => "addresses",
+            ExportTable::AddressOutputs => "address_outputs",
+            ExportTable::AddressInputs => "address_inputs",
+            ExportTable::BlockStats => "block_stats",
+            ExportTable::BlockScriptTypeStats => "block_script_type_stats",
+        }
+    }
+
+    /// Column used to partition the table by height
+    fn height_column(self) -> &'static str {
+        match self {
+            ExportTable::Addresses => "first_seen_block_height",
+            _ => "block_height",
+        }
+    }
+
+    /// Stable row order within a partition
+    fn order_by(self) -> &'static str {
+        match self {
+            ExportTable::Blocks | ExportTable::BlockStats => "block_height",
+            ExportTable::Transactions => "block_height, transaction_index",
+            ExportTable::Addresses => "first_seen_block_height, address_id",
+            ExportTable::AddressOutputs => "block_height, output_id",
+            ExportTable::AddressInputs => "block_height, input_id",
+            ExportTable::BlockScriptTypeStats => "block_height, script_type",
+        }
+    }
+}
+
+/// Runs the export
+pub fn run_export(conn: &mut PgConnection, args: ExportArgs) -> Result<()> {
+    if args.partition_size == 0 || args.batch_size == 0 {
+        anyhow::bail!("Partition size and batch size must be greater than zero");
+    }
+
+    let to = match args.to {
+        Some(h) => h,
+        None => db::get_last_processed_height(conn)?
+            .ok_or_else(|| anyhow::anyhow!("No blocks have been processed yet"))?,
+    };
+    let tables = if args.tables.is_empty() {
+        ExportTable::ALL.to_vec()
+    } else {
+        args.tables.clone()
+    };
+
+    info!(
+        "Exporting {} table(s) for heights {} to {} as {:?}",
+        tables.len(),
+        args.from,
+        to,
+        args.format
+    );
+
+    for table in tables {
+        let table_dir = args.output_dir.join(table.table_name());
+        fs::create_dir_all(&table_dir)
+            .with_context(|| format!("Failed to create directory {}", table_dir.display()))?;
+
+        // Partitions are aligned to multiples of the partition size so file names are stable
+        let mut partition_start = (args.from / args.partition_size) * args.partition_size;
+        while partition_start <= to {
+            let partition_end = partition_start.saturating_add(args.partition_size - 1);
+            let range = (partition_start.max(args.from), partition_end.min(to));
+            let path = table_dir.join(format!(
+                "{}_{:07}_{:07}.{}",
+                table.table_name(),
+                partition_start,
+                partition_end,
+                args.format.extension()
+            ));
+
+            let rows = match table {
+                ExportTable::Blocks => {
+                    export_partition::<models::Block>(conn, table, range, &path, &args)?
+                }
+                ExportTable::Transactions => {
+                    export_partition::<models::Transaction>(conn, table, range, &path, &args)?
+                }
+                ExportTable::Addresses => {
+                    export_partition::<models::Address>(conn, table, range, &path, &args)?
+                }
+                ExportTable::AddressOutputs => {
+                    export_partition::<models::AddressOutput>(conn, table, range, &path, &args)?
+                }
+                ExportTable::AddressInputs => {
+                    export_partition::<models::AddressInput>(conn, table, range, &path, &args)?
+                }
+                ExportTable::BlockStats => {
+                    export_partition::<models::BlockStats>(conn, table, range, &path, &args)?
+                }
+                ExportTable::BlockScriptTypeStats => {
+                    export_partition::<models::BlockScriptTypeStats>(
+                        conn, table, range, &path, &args,
+                    )?
+                }
+            };
+
+            if rows > 0 {
+                info!("Wrote {} rows to {}", rows, path.display());
+            }
+
+            partition_start = match partition_start.checked_add(args.partition_size) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+
+    info!("Export complete");
+    Ok(())
+}
+
+/// Streams one height range of a table to a file through a server-side cursor,
+/// so only `batch_size` rows are held in memory at a time.
+/// Returns the number of rows written; no file is created for an empty range.
+fn export_partition<R: ExportRow>(
+    conn: &mut PgConnection,
+    table: ExportTable,
+    (start, end): (u32, u32),
+    path: &Path,
+    args: &ExportArgs,
+) -> Result<usize> {
+    let schema = Arc::new(Schema::new(R::fields(args.txid_encoding)));
+
+    // Cursors only live inside a transaction
+    conn.transaction(|conn| {
+        // DB QUERY!
+        sql_query(format!(
+            "DECLARE export_cursor NO SCROLL CURSOR FOR SELECT * FROM {} WHERE {} BETWEEN {} AND {} ORDER BY {}",
+            table.table_name(),
+            table.height_column(),
+            start,
+            end,
+            table.order_by()
+        ))
+        .execute(conn)
+        .context("Failed to declare export cursor")?;
+
+        let mut writer: Option<BatchWriter> = None;
+        let mut total_rows = 0;
+
+        loop {
+            // DB QUERY!
+            let rows = sql_query(format!("FETCH FORWARD {} FROM export_cursor", args.batch_size))
+                .load::<R>(conn)
+                .with_context(|| format!("Failed to fetch rows from {}", table.table_name()))?;
+            if rows.is_empty() {
+                break;
+            }
+
+            let batch = RecordBatch::try_new(schema.clone(), R::columns(&rows, args.txid_encoding))
+                .context("Failed to build record batch")?;
+
+            if writer.is_none() {
+                writer = Some(BatchWriter::create(path, args.format, schema.clone())?);
+            }
+            if let Some(w) = writer.as_mut() {
+                w.write(&batch)?;
+            }
+            total_rows += rows.len();
+        }
+
+        if let Some(w) = writer {
+            w.close()?;
+        }
+
+        sql_query("CLOSE export_cursor")
+            .execute(conn)
+            .context("Failed to close export cursor")?;
+
+        Ok(total_rows)
+    })
+}
+
+/// Writes record batches to a Parquet or CSV file
+enum BatchWriter {
+    Parquet(Box<ArrowWriter<File>>),
+    Csv(Box<arrow::csv::Writer<File>>),
+}
+
+impl BatchWriter {
+    fn create(path: &Path, format: ExportFormat, schema: SchemaRef) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+
+        match format {
+            ExportFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(file, schema, Some(props))
+                    .context("Failed to create Parquet writer")?;
+                Ok(BatchWriter::Parquet(Box::new(writer)))
+            }
+            ExportFormat::Csv => Ok(BatchWriter::Csv(Box::new(
+                arrow::csv::WriterBuilder::new()
+                    .with_header(true)
+                    .build(file),
+            ))),
+        }
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match self {
+            BatchWriter::Parquet(w) => w.write(batch).context("Failed to write Parquet batch"),
+            BatchWriter::Csv(w) => w.write(batch).context("Failed to write CSV batch"),
+        }
+    }
+
+    fn close(self) -> Result<()> {
+        match self {
+            BatchWriter::Parquet(w) => {
+                w.close().context("Failed to finish Parquet file")?;
+            }
+            BatchWriter::Csv(_) => {} // Flushed when dropped
+        }
+        Ok(())
+    }
+}
+
+/// A table row that can be converted into Arrow columns
+trait ExportRow: QueryableByName<Pg> + Sized + 'static {
+    fn fields(encoding: BytesEncoding) -> Vec<Field>;
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef>;
+}
+
+fn bytes_field(name: &str, encoding: BytesEncoding, nullable: bool) -> Field {
+    match encoding {
+        BytesEncoding::Hex => Field::new(name, DataType::Utf8, nullable),
+        BytesEncoding::Binary => Field::new(name, DataType::Binary, nullable),
+    }
+}
+
+fn bytes_column<'a>(
+    values: impl Iterator<Item = Option<&'a [u8]>>,
+    encoding: BytesEncoding,
+) -> ArrayRef {
+    match encoding {
+        BytesEncoding::Hex => Arc::new(values.map(|v| v.map(hex::encode)).collect::<StringArray>()),
+        BytesEncoding::Binary => Arc::new(values.collect::<BinaryArray>()),
+    }
+}
+
+fn i32_column(values: impl Iterator<Item = i32>) -> ArrayRef {
+    Arc::new(Int32Array::from_iter_values(values))
+}
+
+fn i64_column(values: impl Iterator<Item = i64>) -> ArrayRef {
+    Arc::new(Int64Array::from_iter_values(values))
+}
+
+fn optional_i32_column(values: impl Iterator<Item = Option<i32>>) -> ArrayRef {
+    Arc::new(values.collect::<Int32Array>())
+}
+
+fn optional_i64_column(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(values.collect::<Int64Array>())
+}
+
+fn f64_column(values: impl Iterator<Item = f64>) -> ArrayRef {
+    Arc::new(Float64Array::from_iter_values(values))
+}
+
+fn optional_timestamp_column(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(values.collect::<TimestampSecondArray>())
+}
+
+fn bool_column(values: impl Iterator<Item = bool>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<BooleanArray>())
+}
+
+fn optional_bool_column(values: impl Iterator<Item = Option<bool>>) -> ArrayRef {
+    Arc::new(values.collect::<BooleanArray>())
+}
+
+fn string_column<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<StringArray>())
+}
+
+impl ExportRow for models::Block {
+    fn fields(encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("block_height", DataType::Int32, false),
+            bytes_field("block_hash", encoding, false),
+            Field::new(
+                "block_timestamp",
+                DataType::Timestamp(TimeUnit::Second, None),
+                false,
+            ),
+            Field::new("transaction_count", DataType::Int32, false),
+        ]
+    }
+
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef> {
+        vec![
+            i32_column(rows.iter().map(|r| r.block_height)),
+            bytes_column(rows.iter().map(|r| Some(r.block_hash.as_slice())), encoding),
+            Arc::new(TimestampSecondArray::from_iter_values(
+                rows.iter().map(|r| r.block_timestamp.and_utc().timestamp()),
+            )),
+            i32_column(rows.iter().map(|r| r.transaction_count)),
+        ]
+    }
+}
+
+impl ExportRow for models::Transaction {
+    fn fields(encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            bytes_field("transaction_id", encoding, false),
+            Field::new("block_height", DataType::Int32, false),
+            Field::new("transaction_index", DataType::Int32, false),
+            Field::new("is_coinbase", DataType::Boolean, false),
+            Field::new("fee_satoshis", DataType::Int64, true),
+            Field::new("input_count", DataType::Int32, false),
+            Field::new("output_count", DataType::Int32, false),
+            Field::new("base_size", DataType::Int32, true),
+            Field::new("total_size", DataType::Int32, true),
+            Field::new("witness_size", DataType::Int32, true),
+            Field::new("weight", DataType::Int32, true),
+            Field::new("vsize", DataType::Int32, true),
+            Field::new("segwit_input_count", DataType::Int32, true),
+            Field::new("taproot_input_count", DataType::Int32, true),
+            Field::new("version", DataType::Int32, true),
+            Field::new("lock_time", DataType::Int64, true),
+            Field::new("lock_time_type", DataType::Utf8, true),
+            Field::new("is_lock_time_enforced", DataType::Boolean, true),
+            Field::new("is_anti_fee_sniping", DataType::Boolean, true),
+            Field::new("signals_rbf", DataType::Boolean, true),
+        ]
+    }
+
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef> {
+        vec![
+            bytes_column(
+                rows.iter().map(|r| Some(r.transaction_id.as_slice())),
+                encoding,
+            ),
+            i32_column(rows.iter().map(|r| r.block_height)),
+            i32_column(rows.iter().map(|r| r.transaction_index)),
+            bool_column(rows.iter().map(|r| r.is_coinbase)),
+            optional_i64_column(rows.iter().map(|r| r.fee_satoshis)),
+            i32_column(rows.iter().map(|r| r.input_count)),
+            i32_column(rows.iter().map(|r| r.output_count)),
+            optional_i32_column(rows.iter().map(|r| r.base_size)),
+            optional_i32_column(rows.iter().map(|r| r.total_size)),
+            optional_i32_column(rows.iter().map(|r| r.witness_size)),
+            optional_i32_column(rows.iter().map(|r| r.weight)),
+            optional_i32_column(rows.iter().map(|r| r.vsize)),
+            optional_i32_column(rows.iter().map(|r| r.segwit_input_count)),
+            optional_i32_column(rows.iter().map(|r| r.taproot_input_count)),
+            optional_i32_column(rows.iter().map(|r| r.version)),
+            optional_i64_column(rows.iter().map(|r| r.lock_time)),
+            string_column(
+                rows.iter()
+                    .map(|r| r.lock_time_type.as_ref().map(|k| k.as_str())),
+            ),
+            optional_bool_column(rows.iter().map(|r| r.is_lock_time_enforced)),
+            optional_bool_column(rows.iter().map(|r| r.is_anti_fee_sniping)),
+            optional_bool_column(rows.iter().map(|r| r.signals_rbf)),
+        ]
+    }
+}
+
+impl ExportRow for models::Address {
+    fn fields(encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("address_id", DataType::Int64, false),
+            Field::new("address_string", DataType::Utf8, false), // Derived from the hash
+            bytes_field("address_hash", encoding, false),
+            Field::new("script_type", DataType::Utf8, false),
+            Field::new("first_seen_block_height", DataType::Int32, false),
+            Field::new("total_receive_count", DataType::Int32, false),
+            Field::new("total_spend_count", DataType::Int32, false),
+            Field::new("is_public_key_exposed", DataType::Boolean, false),
+            bytes_field("public_key", encoding, true),
+            Field::new("script_extra_data", DataType::Utf8, true), // JSON text
+            Field::new("public_key_exposed_height", DataType::Int32, true),
+            Field::new("public_key_exposure_source", DataType::Utf8, true),
+            bytes_field("script_template_hash", encoding, true),
+            Field::new("public_key_format", DataType::Utf8, true),
+            Field::new("cluster_id", DataType::Int64, true),
+            Field::new("public_key_exposure_input_id", DataType::Int64, true),
+            Field::new("public_key_exposed_via_address_id", DataType::Int64, true),
+        ]
+    }
+
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef> {
+        let extra_data: Vec<Option<String>> = rows
+            .iter()
+            .map(|r| {
+                r.script_extra_data
+                    .as_ref()
+                    .and_then(|v| serde_json::to_string(v).ok())
+            })
+            .collect();
+        let address_strings: Vec<String> = rows.iter().map(|r| r.address_string()).collect();
+
+        vec![
+            i64_column(rows.iter().map(|r| r.address_id)),
+            string_column(address_strings.iter().map(|a| Some(a.as_str()))),
+            bytes_column(
+                rows.iter().map(|r| Some(r.address_hash.as_slice())),
+                encoding,
+            ),
+            string_column(rows.iter().map(|r| Some(r.script_type.as_str()))),
+            i32_column(rows.iter().map(|r| r.first_seen_block_height)),
+            i32_column(rows.iter().map(|r| r.total_receive_count)),
+            i32_column(rows.iter().map(|r| r.total_spend_count)),
+            bool_column(rows.iter().map(|r| r.is_public_key_exposed)),
+            bytes_column(rows.iter().map(|r| r.public_key.as_deref()), encoding),
+            string_column(extra_data.iter().map(|v| v.as_deref())),
+            optional_i32_column(rows.iter().map(|r| r.public_key_exposed_height)),
+            string_column(
+                rows.iter()
+                    .map(|r| r.public_key_exposure_source.as_ref().map(|s| s.as_str())),
+            ),
+            bytes_column(
+                rows.iter().map(|r| r.script_template_hash.as_deref()),
+                encoding,
+            ),
+            string_column(
+                rows.iter()
+                    .map(|r| r.public_key_format.as_ref().map(|f| f.as_str())),
+            ),
+            optional_i64_column(rows.iter().map(|r| r.cluster_id)),
+            optional_i64_column(rows.iter().map(|r| r.public_key_exposure_input_id)),
+            optional_i64_column(rows.iter().map(|r| r.public_key_exposed_via_address_id)),
+        ]
+    }
+}
+
+impl ExportRow for models::AddressOutput {
+    fn fields(encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("output_id", DataType::Int64, false),
+            Field::new("address_id", DataType::Int64, false),
+            bytes_field("transaction_id", encoding, false),
+            Field::new("block_height", DataType::Int32, false),
+            Field::new("output_index", DataType::Int32, false),
+            Field::new("value_satoshis", DataType::Int64, false),
+            Field::new("is_spent", DataType::Boolean, false),
+            Field::new("spending_input_id", DataType::Int64, true),
+            Field::new("received_after_exposure", DataType::Boolean, false),
+            Field::new("is_unspendable", DataType::Boolean, false),
+            Field::new("change_likelihood", DataType::Utf8, true),
+            Field::new("change_heuristics", DataType::Utf8, true),
+        ]
+    }
+
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef> {
+        // Heuristics are exported as a comma separated list
+        let change_heuristics: Vec<Option<String>> = rows
+            .iter()
+            .map(|r| r.change_heuristics.as_ref().map(|h| h.join(",")))
+            .collect();
+
+        vec![
+            i64_column(rows.iter().map(|r| r.output_id)),
+            i64_column(rows.iter().map(|r| r.address_id)),
+            bytes_column(
+                rows.iter().map(|r| Some(r.transaction_id.as_slice())),
+                encoding,
+            ),
+            i32_column(rows.iter().map(|r| r.block_height)),
+            i32_column(rows.iter().map(|r| r.output_index)),
+            i64_column(rows.iter().map(|r| r.value_satoshis)),
+            bool_column(rows.iter().map(|r| r.is_spent)),
+            optional_i64_column(rows.iter().map(|r| r.spending_input_id)),
+            bool_column(rows.iter().map(|r| r.received_after_exposure)),
+            bool_column(rows.iter().map(|r| r.is_unspendable)),
+            string_column(
+                rows.iter()
+                    .map(|r| r.change_likelihood.as_ref().map(|l| l.as_str())),
+            ),
+            string_column(change_heuristics.iter().map(|h| h.as_deref())),
+        ]
+    }
+}
+
+impl ExportRow for models::AddressInput {
+    fn fields(encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("input_id", DataType::Int64, false),
+            Field::new("address_id", DataType::Int64, false),
+            bytes_field("transaction_id", encoding, false),
+            Field::new("block_height", DataType::Int32, false),
+            Field::new("input_index", DataType::Int32, false),
+            Field::new("spent_output_id", DataType::Int64, false),
+            Field::new("value_satoshis", DataType::Int64, false),
+            bytes_field("public_key_revealed", encoding, true),
+            Field::new("public_key_revealed_format", DataType::Utf8, true),
+            Field::new("sequence", DataType::Int64, true),
+            Field::new("relative_lock_type", DataType::Utf8, true),
+            Field::new("relative_lock_value", DataType::Int32, true),
+            Field::new("spent_output_block_height", DataType::Int32, true),
+            Field::new(
+                "spent_output_timestamp",
+                DataType::Timestamp(TimeUnit::Second, None),
+                true,
+            ),
+            Field::new("coin_age_blocks", DataType::Int32, true),
+            Field::new("coin_age_seconds", DataType::Int64, true),
+        ]
+    }
+
+    fn columns(rows: &[Self], encoding: BytesEncoding) -> Vec<ArrayRef> {
+        vec![
+            i64_column(rows.iter().map(|r| r.input_id)),
+            i64_column(rows.iter().map(|r| r.address_id)),
+            bytes_column(
+                rows.iter().map(|r| Some(r.transaction_id.as_slice())),
+                encoding,
+            ),
+            i32_column(rows.iter().map(|r| r.block_height)),
+            i32_column(rows.iter().map(|r| r.input_index)),
+            i64_column(rows.iter().map(|r| r.spent_output_id)),
+            i64_column(rows.iter().map(|r| r.value_satoshis)),
+            bytes_column(
+                rows.iter().map(|r| r.public_key_revealed.as_deref()),
+                encoding,
+            ),
+            string_column(
+                rows.iter()
+                    .map(|r| r.public_key_revealed_format.as_ref().map(|f| f.as_str())),
+            ),
+            optional_i64_column(rows.iter().map(|r| r.sequence)),
+            string_column(
+                rows.iter()
+                    .map(|r| r.relative_lock_type.as_ref().map(|k| k.as_str())),
+            ),
+            optional_i32_column(rows.iter().map(|r| r.relative_lock_value)),
+            optional_i32_column(rows.iter().map(|r| r.spent_output_block_height)),
+            optional_timestamp_column(
+                rows.iter()
+                    .map(|r| r.spent_output_timestamp.map(|t| t.and_utc().timestamp())),
+            ),
+            optional_i32_column(rows.iter().map(|r| r.coin_age_blocks)),
+            optional_i64_column(rows.iter().map(|r| r.coin_age_seconds)),
+        ]
+    }
+}
+
+impl ExportRow for models::BlockStats {
+    fn fields(_encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("block_height", DataType::Int32, false),
+            Field::new("new_address_count", DataType::Int32, false),
+            Field::new("reused_address_count", DataType::Int32, false),
+            Field::new("newly_exposed_public_key_count", DataType::Int32, false),
+            Field::new("newly_exposed_value_satoshis", DataType::Int64, false),
+            Field::new("coinbase_value_satoshis", DataType::Int64, false),
+            Field::new("coinjoin_count", DataType::Int32, true),
+            Field::new("coinjoin_value_satoshis", DataType::Int64, true),
+            Field::new("coin_days_destroyed", DataType::Float64, false),
+            Field::new("average_spent_age_days", DataType::Float64, false),
+        ]
+    }
+
+    fn columns(rows: &[Self], _encoding: BytesEncoding) -> Vec<ArrayRef> {
+        vec![
+            i32_column(rows.iter().map(|r| r.block_height)),
+            i32_column(rows.iter().map(|r| r.new_address_count)),
+            i32_column(rows.iter().map(|r| r.reused_address_count)),
+            i32_column(rows.iter().map(|r| r.newly_exposed_public_key_count)),
+            i64_column(rows.iter().map(|r| r.newly_exposed_value_satoshis)),
+            i64_column(rows.iter().map(|r| r.coinbase_value_satoshis)),
+            optional_i32_column(rows.iter().map(|r| r.coinjoin_count)),
+            optional_i64_column(rows.iter().map(|r| r.coinjoin_value_satoshis)),
+            f64_column(rows.iter().map(|r| r.coin_days_destroyed)),
+            f64_column(rows.iter().map(|r| r.average_spent_age_days)),
+        ]
+    }
+}
+
+impl ExportRow for models::BlockScriptTypeStats {
+    fn fields(_encoding: BytesEncoding) -> Vec<Field> {
+        vec![
+            Field::new("block_height", DataType::Int32, false),
+            Field::new("script_type", DataType::Utf8, false),
+            Field::new("output_count", DataType::Int32, false),
+            Field::new("output_value_satoshis", DataType::Int64, false),
+            Field::new("input_count", DataType::Int32, false),
+            Field::new("input_value_satoshis", DataType::Int64, false),
+            Field::new("reused_output_count", DataType::Int32, false),
+            Field::new("post_exposure_output_count", DataType::Int32, false),
+            Field::new("post_exposure_value_satoshis", DataType::Int64, false),
+        ]
+    }
+
+    fn columns(rows: &[Self], _encoding: BytesEncoding) -> Vec<ArrayRef> {
+        vec![
+            i32_column(rows.iter().map(|r| r.block_height)),
+            string_column(rows.iter().map(|r| Some(r.script_type.as_str()))),
+            i32_column(rows.iter().map(|r| r.output_count)),
+            i64_column(rows.iter().map(|r| r.output_value_satoshis)),
+            i32_column(rows.iter().map(|r| r.input_count)),
+            i64_column(rows.iter().map(|r| r.input_value_satoshis)),
+            i32_column(rows.iter().map(|r| r.reused_output_count)),
+            i32_column(rows.iter().map(|r| r.post_exposure_output_count)),
+            i64_column(rows.iter().map(|r| r.post_exposure_value_satoshis)),
+        ]
+    }
+}
@@ -0,0 +1,254 @@
+use bitcoin::blockdata::opcodes::all::{OP_ENDIF, OP_IF, OP_PUSHNUM_1, OP_PUSHNUM_16};
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::taproot::LeafVersion;
+use bitcoin::Witness;
+
+/// Protocol identifier pushed at the start of an ordinals envelope
+const ORD_PROTOCOL_ID: &[u8] = b"ord";
+
+/// Envelope field tags (odd tags can be ignored by parsers that do not know them)
+const CONTENT_TYPE_TAG: &[u8] = &[1];
+const CONTENT_ENCODING_TAG: &[u8] = &[9];
+
+/// An `OP_FALSE OP_IF "ord" ... OP_ENDIF` envelope found in the tapscript of a script-path spend
+pub struct Envelope {
+    pub envelope_index: i16, // Position among the envelopes of the input
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub body_size: usize,     // Bytes of content, summed over the body pushes
+    pub envelope_size: usize, // Bytes of tapscript from OP_FALSE to OP_ENDIF
+}
+
+/// Finds the inscription envelopes in the witness of an input spending a P2TR output.
+/// Key-path spends, and script-path spends with a leaf version other than tapscript, have none.
+pub fn parse_envelopes(witness: &Witness) -> Vec<Envelope> {
+    let tapscript = match witness.taproot_leaf_script() {
+        Some(leaf) if leaf.version == LeafVersion::TapScript => leaf.script,
+        _ => return Vec::new(),
+    };
+
+    // Envelopes are only made of pushes, so a script that fails to parse ends the search
+    let instructions: Vec<(usize, Instruction)> = tapscript
+        .instruction_indices()
+        .map_while(Result::ok)
+        .collect();
+
+    let mut envelopes = Vec::new();
+    let mut position = 0;
+    while position + 2 < instructions.len() {
+        let is_envelope_start = matches!(
+            &instructions[position..position + 3],
+            [
+                (_, Instruction::PushBytes(falsy)),
+                (_, Instruction::Op(OP_IF)),
+                (_, Instruction::PushBytes(protocol)),
+            ] if falsy.is_empty() && protocol.as_bytes() == ORD_PROTOCOL_ID
+        );
+        if !is_envelope_start {
+            position += 1;
+            continue;
+        }
+
+        match parse_envelope_payload(&instructions[position + 3..]) {
+            Some((payload, end)) => {
+                let start_offset = instructions[position].0;
+                let end_index = position + 3 + end;
+                let end_offset = instructions
+                    .get(end_index + 1)
+                    .map(|(offset, _)| *offset)
+                    .unwrap_or(tapscript.len());
+
+                envelopes.push(envelope_from_payload(
+                    envelopes.len() as i16,
+                    &payload,
+                    end_offset - start_offset,
+                ));
+                position = end_index + 1;
+            }
+            None => position += 1,
+        }
+    }
+
+    envelopes
+}
+
+/// Collects the pushes of an envelope up to its OP_ENDIF, returning them with the index of the
+/// OP_ENDIF. Small integer opcodes count as pushes of their value. Returns None if the envelope
+/// contains any other opcode or is not closed.
+fn parse_envelope_payload(instructions: &[(usize, Instruction)]) -> Option<(Vec<Vec<u8>>, usize)> {
+    let mut payload = Vec::new();
+    for (index, (_, instruction)) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::PushBytes(bytes) => payload.push(bytes.as_bytes().to_vec()),
+            Instruction::Op(OP_ENDIF) => return Some((payload, index)),
+            Instruction::Op(op)
+                if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&op.to_u8()) =>
+            {
+                payload.push(vec![op.to_u8() - OP_PUSHNUM_1.to_u8() + 1]);
+            }
+            Instruction::Op(_) => return None,
+        }
+    }
+    None
+}
+
+/// Reads the tag/value fields of an envelope, then the body after the empty push separating them
+fn envelope_from_payload(
+    envelope_index: i16,
+    payload: &[Vec<u8>],
+    envelope_size: usize,
+) -> Envelope {
+    let mut content_type = None;
+    let mut content_encoding = None;
+    let mut body_size = 0;
+
+    let mut fields = payload.iter();
+    while let Some(tag) = fields.next() {
+        if tag.is_empty() {
+            body_size = fields.map(Vec::len).sum();
+            break;
+        }
+        let value = match fields.next() {
+            Some(value) => value,
+            None => break,
+        };
+
+        // Only the first occurrence of a field counts
+        if tag == CONTENT_TYPE_TAG && content_type.is_none() {
+            content_type = Some(field_text(value));
+        } else if tag == CONTENT_ENCODING_TAG && content_encoding.is_none() {
+            content_encoding = Some(field_text(value));
+        }
+    }
+
+    Envelope {
+        envelope_index,
+        content_type,
+        content_encoding,
+        body_size,
+        envelope_size,
+    }
+}
+
+/// A field value as text, without the NUL bytes Postgres cannot store in TEXT
+fn field_text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).replace('\0', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::opcodes::all::{OP_CHECKSIG, OP_PUSHBYTES_0};
+    use bitcoin::script::{Builder, PushBytesBuf, ScriptBuf};
+
+    /// The x-only key of the secp256k1 generator point
+    const INTERNAL_KEY: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn push(bytes: &[u8]) -> PushBytesBuf {
+        PushBytesBuf::try_from(bytes.to_vec()).unwrap()
+    }
+
+    /// A reveal tapscript as ord builds it: a key check, then the envelope
+    /// `OP_FALSE OP_IF "ord" <fields> OP_0 <body pushes> OP_ENDIF`. `fields` are pushed as
+    /// given, so that tags can be written as `OP_PUSHNUM_1` or as a one byte push.
+    fn reveal_script(fields: Builder, body: &[&[u8]]) -> ScriptBuf {
+        let mut builder = Builder::new()
+            .push_slice(push(&hex::decode(INTERNAL_KEY).unwrap()))
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_PUSHBYTES_0)
+            .push_opcode(OP_IF)
+            .push_slice(push(ORD_PROTOCOL_ID));
+        for instruction in fields.as_script().instructions() {
+            builder = match instruction.unwrap() {
+                Instruction::PushBytes(bytes) => builder.push_slice(bytes),
+                Instruction::Op(op) => builder.push_opcode(op),
+            };
+        }
+        builder = builder.push_opcode(OP_PUSHBYTES_0);
+        for chunk in body {
+            builder = builder.push_slice(push(chunk));
+        }
+        builder.push_opcode(OP_ENDIF).into_script()
+    }
+
+    /// A script-path spend witness: <signature> <tapscript> <control block>
+    fn script_path_witness(tapscript: &ScriptBuf) -> Witness {
+        let mut control_block = vec![0xc0];
+        control_block.extend(hex::decode(INTERNAL_KEY).unwrap());
+        Witness::from_slice(&[vec![0x55; 64], tapscript.to_bytes(), control_block])
+    }
+
+    #[test]
+    fn reads_content_type_with_pushnum_tag() {
+        // Minimal pushes turn the one byte tag 1 into OP_PUSHNUM_1, as current ord writes it
+        let fields = Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice(push(b"text/plain;charset=utf-8"));
+        let tapscript = reveal_script(fields, &[b"Hello, world!"]);
+
+        let envelopes = parse_envelopes(&script_path_witness(&tapscript));
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].envelope_index, 0);
+        assert_eq!(
+            envelopes[0].content_type.as_deref(),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(envelopes[0].content_encoding, None);
+        assert_eq!(envelopes[0].body_size, 13);
+        // Everything after the 33 byte key push and OP_CHECKSIG
+        assert_eq!(envelopes[0].envelope_size, tapscript.len() - 34);
+    }
+
+    #[test]
+    fn sums_body_split_over_pushes() {
+        // Pushes are limited to 520 bytes, so larger bodies are split
+        let fields = Builder::new()
+            .push_slice(push(&[1]))
+            .push_slice(push(b"image/png"))
+            .push_slice(push(&[9]))
+            .push_slice(push(b"br"));
+        let tapscript = reveal_script(fields, &[&[0xaa; 520], &[0xbb; 520], &[0xcc; 100]]);
+
+        let envelopes = parse_envelopes(&script_path_witness(&tapscript));
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].content_type.as_deref(), Some("image/png"));
+        assert_eq!(envelopes[0].content_encoding.as_deref(), Some("br"));
+        assert_eq!(envelopes[0].body_size, 1140);
+    }
+
+    #[test]
+    fn finds_every_envelope_in_a_script() {
+        let first = reveal_script(
+            Builder::new()
+                .push_opcode(OP_PUSHNUM_1)
+                .push_slice(push(b"text/plain")),
+            &[b"a"],
+        );
+        let mut both = first.to_bytes();
+        // The second envelope alone, without the key check
+        both.extend_from_slice(&first.as_bytes()[34..]);
+
+        let envelopes = parse_envelopes(&script_path_witness(&ScriptBuf::from_bytes(both)));
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(envelopes[1].envelope_index, 1);
+        assert_eq!(envelopes[1].body_size, 1);
+    }
+
+    #[test]
+    fn finds_no_envelope() {
+        // A key-path spend is a single signature
+        assert!(parse_envelopes(&Witness::from_slice(&[vec![0x55; 64]])).is_empty());
+
+        // A script-path spend of a plain key check
+        let key_check = Builder::new()
+            .push_slice(push(&hex::decode(INTERNAL_KEY).unwrap()))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        assert!(parse_envelopes(&script_path_witness(&key_check)).is_empty());
+
+        // An envelope that is never closed
+        let mut unclosed = reveal_script(Builder::new(), &[b"a"]).to_bytes();
+        unclosed.pop();
+        assert!(parse_envelopes(&script_path_witness(&ScriptBuf::from_bytes(unclosed))).is_empty());
+    }
+}
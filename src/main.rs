@@ -1,15 +1,65 @@
 use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use diesel::{Connection, PgConnection};
 use dotenv::dotenv;
 use std::env;
 use std::time::Duration;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
+mod address;
+mod bip30;
 mod bitcoin_client;
+mod change;
+mod classifier;
+mod cluster;
+mod coin_age;
+mod coinbase;
+mod coinjoin;
 mod db;
+mod export;
+mod inscription;
+mod partitions;
 mod processor;
+mod pubkey;
+mod reports;
+mod script_type;
+mod signature;
+mod timelock;
+mod utxo_age;
 
-fn run() -> Result<()> {
+/// Bitcoin block and transaction processor and analytics
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Sync blocks from the Bitcoin node into the database (default)
+    Sync,
+    /// Print an analytics report from the database
+    #[command(subcommand)]
+    Report(reports::ReportCommand),
+    /// Look up the balance, unspent outputs and history of an address
+    #[command(subcommand)]
+    Address(address::AddressCommand),
+    /// Export the analytics tables to Parquet or CSV files by height range
+    Export(export::ExportArgs),
+    /// List the block height partitions of the largest tables, or archive old ones
+    #[command(subcommand)]
+    Partitions(partitions::PartitionCommand),
+    /// Link the public keys exposed before sibling addresses were tracked to those addresses
+    LinkPublicKeys {
+        /// Exposed addresses linked per transaction
+        #[arg(long, default_value_t = 10_000)]
+        batch_size: i64,
+    },
+}
+
+fn run(cli: Cli) -> Result<()> {
     info!("Entered run function");
 
     // Load environment variables
@@ -35,11 +85,54 @@ fn run() -> Result<()> {
     db::run_migrations(&mut conn).context("Failed to run database migrations")?;
     info!("Rust app migrations completed");
 
+    db::verify_script_types(&mut conn).context("Script type check failed")?;
+
+    match cli.command.unwrap_or(Command::Sync) {
+        Command::Sync => run_sync(db_pool),
+        Command::Report(report) => report.run(&mut conn),
+        Command::Address(lookup) => lookup.run(&mut conn),
+        Command::Export(args) => export::run_export(&mut conn, args),
+        Command::Partitions(command) => command.run(&mut conn),
+        Command::LinkPublicKeys { batch_size } => link_public_keys(&mut conn, batch_size),
+    }
+}
+
+/// Records the sibling addresses of every exposed public key, exposing the existing ones.
+/// Batches are committed as they go, and linking again skips keys already recorded.
+fn link_public_keys(conn: &mut PgConnection, batch_size: i64) -> Result<()> {
+    let mut after_address_id = 0;
+    let mut total_exposed = 0;
+
+    while let Some((last_address_id, newly_exposed)) =
+        conn.transaction(|conn| db::link_exposed_public_keys(conn, after_address_id, batch_size))?
+    {
+        after_address_id = last_address_id;
+        total_exposed += newly_exposed;
+        info!(
+            "Linked public keys up to address {}, {} sibling addresses exposed",
+            after_address_id, total_exposed
+        );
+    }
+
+    println!("{} sibling addresses exposed", total_exposed);
+    Ok(())
+}
+
+/// Syncs the database with the Bitcoin node, then keeps processing new blocks
+fn run_sync(db_pool: db::DbPool) -> Result<()> {
     // Init Bitcoin REST client
     let bitcoin_rest_url =
         env::var("BITCOIN_REST_URL").unwrap_or_else(|_| "http://127.0.0.1:8332".to_string());
     info!("Bitcoin REST URL: {}", bitcoin_rest_url);
 
+    // Order of the output script classifiers, configurable with SCRIPT_CLASSIFIERS
+    let classifiers = classifier::ClassifierRegistry::from_env()
+        .context("Failed to configure script classifiers")?;
+
+    // Mining pools that blocks are attributed to, listed in MINING_POOLS_FILE
+    let miners =
+        coinbase::miners::MinerRegistry::from_env().context("Failed to load mining pool list")?;
+
     // Start tokio runtime for async operations
     info!("Creating tokio runtime");
     let rt = tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
@@ -76,7 +169,7 @@ fn run() -> Result<()> {
 
         // Init and run the block processor
         info!("Initialising block processor");
-        let processor = processor::BlockProcessor::new(bitcoin_client, db_pool.clone());
+        let processor = processor::BlockProcessor::new(bitcoin_client, db_pool.clone(), classifiers, miners);
 
         // Phase 1: Catch-up to the current chain tip
         // Sync up to the current blockchain tip before proceeding
@@ -157,12 +250,16 @@ fn run() -> Result<()> {
 }
 
 fn main() {
+    let cli = Cli::parse();
+
     info!("Starting Bitcoin block and transaction processor");
+    // Log to stderr so that report output on stdout can be piped
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
-    if let Err(e) = run() {
+    if let Err(e) = run(cli) {
         error!("Application error: {:#}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use clap::Subcommand;
+use diesel::PgConnection;
+
+use crate::db::{self, HeightPartition};
+
+/// Management of the block height partitions of the largest tables
+#[derive(Subcommand, Debug)]
+pub enum PartitionCommand {
+    /// Partitions of each table with their height ranges, estimated rows and sizes
+    List,
+    /// Detach the partitions of blocks below a height into the archive schema
+    Archive {
+        /// Partitions ending at or below this height are archived
+        #[arg(long)]
+        before: u32,
+        /// Tablespace to move the archived partitions and their indexes to
+        #[arg(long)]
+        tablespace: Option<String>,
+    },
+}
+
+impl PartitionCommand {
+    /// Runs the command, printing its output to stdout
+    pub fn run(self, conn: &mut PgConnection) -> Result<()> {
+        match self {
+            PartitionCommand::List => print_partitions(conn),
+            PartitionCommand::Archive { before, tablespace } => {
+                archive_partitions(conn, before, tablespace.as_deref())
+            }
+        }
+    }
+}
+
+/// Prints the partitions of the tables partitioned by height
+fn print_partitions(conn: &mut PgConnection) -> Result<()> {
+    let partitions = db::get_height_partitions(conn)?;
+
+    println!(
+        "{} partitions of {} blocks",
+        partitions.len(),
+        db::PARTITION_BLOCKS
+    );
+    for partition in &partitions {
+        println!(
+            "  {:<32} {:>8}-{:<8} {:>14} rows {:>12}",
+            partition.partition_name,
+            partition.from_height,
+            partition.to_height - 1,
+            partition.estimated_rows,
+            format_bytes(partition.total_bytes)
+        );
+    }
+
+    Ok(())
+}
+
+/// Archives the partitions of blocks below `before`. Partitions of outputs that are still
+/// unspent are kept, as the processor looks them up when they are spent.
+fn archive_partitions(
+    conn: &mut PgConnection,
+    before: u32,
+    tablespace: Option<&str>,
+) -> Result<()> {
+    let next_height = db::get_last_processed_height(conn)?.map_or(0, |h| h + 1);
+    if before > next_height {
+        anyhow::bail!(
+            "Blocks below {} have not all been processed yet (next height {})",
+            before,
+            next_height
+        );
+    }
+
+    let partitions: Vec<HeightPartition> = db::get_height_partitions(conn)?
+        .into_iter()
+        .filter(|p| p.to_height <= before as i32)
+        .collect();
+    if partitions.is_empty() {
+        println!("No partitions end at or below height {}", before);
+        return Ok(());
+    }
+
+    let mut archived_bytes = 0;
+    for partition in &partitions {
+        // A later spend looks its output up in address_outputs
+        if partition.table_name == "address_outputs"
+            && db::has_unspent_outputs(conn, partition.from_height, partition.to_height)?
+        {
+            println!(
+                "  kept {}: its blocks still have unspent outputs",
+                partition.partition_name
+            );
+            continue;
+        }
+
+        db::archive_height_partition(conn, partition, tablespace)?;
+        archived_bytes += partition.total_bytes;
+        println!(
+            "  archived {} as {}.{}",
+            partition.partition_name,
+            db::ARCHIVE_SCHEMA,
+            partition.partition_name
+        );
+    }
+
+    println!("Archived {}", format_bytes(archived_bytes));
+    Ok(())
+}
+
+/// Formats a size in bytes with a binary unit
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -1,31 +1,58 @@
 use anyhow::{Context, Result};
 use diesel::Connection;
 use diesel::PgConnection;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use crate::bip30;
 use crate::bitcoin_client::BitcoinClient;
-use crate::db::{self, DbPool};
+use crate::change::{self, CreatedOutput};
+use crate::classifier::template::ScriptTemplate;
+use crate::classifier::{encode_address, ClassifierRegistry};
+use crate::coin_age::CoinAge;
+use crate::coinbase::miners::MinerRegistry;
+use crate::coinbase::CoinbaseInfo;
+use crate::coinjoin;
+use crate::db::{self, DbPool, OutputInfo};
+use crate::inscription;
+use crate::pubkey;
+use crate::script_type::{ScriptExtraData, ScriptType};
+use crate::signature;
+use crate::timelock::InputSequence;
+use crate::utxo_age;
+
+mod block_stats;
+use block_stats::BlockStatsCollector;
 
-use bech32::{hrp, segwit, Hrp};
-use bitcoin::base58;
-use bitcoin::blockdata::opcodes::all::*;
 use bitcoin::blockdata::script::Instruction;
-use bitcoin::hashes::{hash160, Hash};
 use bitcoin::script::Script;
 
 /// Processes Bitcoin blocks and extracts analytics data
 pub struct BlockProcessor {
     bitcoin_client: BitcoinClient,
     db_pool: DbPool,
+    classifiers: ClassifierRegistry,
+    miners: MinerRegistry,
+    partitioned_below: AtomicU64, // Heights below this have partitions, as far as this run knows
 }
 
 impl BlockProcessor {
     /// Creates a new block processor
-    pub fn new(bitcoin_client: BitcoinClient, db_pool: DbPool) -> Self {
+    /// Output scripts are classified by the first matching classifier of `classifiers`,
+    /// and blocks are attributed to the mining pools of `miners`
+    pub fn new(
+        bitcoin_client: BitcoinClient,
+        db_pool: DbPool,
+        classifiers: ClassifierRegistry,
+        miners: MinerRegistry,
+    ) -> Self {
         Self {
             bitcoin_client,
             db_pool,
+            classifiers,
+            miners,
+            partitioned_below: AtomicU64::new(0),
         }
     }
 
@@ -76,7 +103,7 @@ impl BlockProcessor {
             }
 
             // Periodically check for updated chain tip
-            if current_height % 100 == 0 {
+            if current_height.is_multiple_of(100) {
                 let new_tip = self.get_current_blockchain_tip().await?;
                 if new_tip > current_tip {
                     info!(
@@ -185,13 +212,43 @@ impl BlockProcessor {
             .get()
             .context("Failed to get database connection")?;
 
+        // Make sure the partitions to store the block in exist, outside the block's transaction
+        // so that they are kept if it fails
+        if height >= self.partitioned_below.load(Ordering::Relaxed) {
+            let end_height = db::ensure_height_partitions(&mut conn, height as i32)?;
+            self.partitioned_below
+                .store(end_height as u64, Ordering::Relaxed);
+        }
+
         // Use a database transaction to ensure atomicity
         conn.transaction(|tx_conn| {
             // 1. Store block data
-            db::store_processed_block(tx_conn, height as u32, &block_hash, timestamp, tx_count)?;
+            let reprocessing = db::store_processed_block(
+                tx_conn,
+                height as u32,
+                &block_hash,
+                timestamp,
+                tx_count,
+            )?;
 
             // 2. Process all transactions in the block
-            self.process_block_transactions(tx_conn, height as u32, &block_hash, &block.txdata)?;
+            self.process_block_transactions(
+                tx_conn,
+                height as u32,
+                &block_hash,
+                timestamp,
+                &block.txdata,
+                reprocessing,
+            )?;
+
+            // 3. Store the coinbase details and attribute the block to a miner
+            self.process_coinbase(tx_conn, height as u32, &block)?;
+
+            // 4. Age the unspent set, keeping its value by creation day current. The unspent set
+            // has moved on since a reprocessed block, which was aged the first time.
+            if !reprocessing {
+                self.process_utxo_age(tx_conn, height as u32)?;
+            }
 
             Ok::<(), anyhow::Error>(())
         })
@@ -205,12 +262,16 @@ impl BlockProcessor {
     }
 
     /// Process all transactions in a block with their inputs and outputs
+    /// `block_time` is the block's unix timestamp, and `reprocessing` is true if the block was
+    /// processed before
     fn process_block_transactions(
         &self,
         conn: &mut PgConnection,
         height: u32,
         block_hash: &str,
+        block_time: i64,
         txs: &[bitcoin::Transaction],
+        reprocessing: bool,
     ) -> Result<()> {
         debug!(
             "Processing {} transactions for block {} ({})",
@@ -219,11 +280,11 @@ impl BlockProcessor {
             block_hash
         );
 
+        let mut stats = BlockStatsCollector::new(height);
+
         for (tx_index, tx) in txs.iter().enumerate() {
             let txid = tx.compute_txid().to_string();
             let is_coinbase = tx.is_coinbase();
-            let input_count = tx.input.len() as i32;
-            let output_count = tx.output.len() as i32;
 
             let fee_satoshis = Some(0);
             // // Calculate transaction fee
@@ -263,85 +324,296 @@ impl BlockProcessor {
             // };
 
             // 1. Store transaction record
-            db::store_transaction(
+            db::store_transaction(conn, height, tx_index as u32, &txid, tx, fee_satoshis)?;
+
+            // 2. Process transaction outputs
+            let created_outputs = self.process_transaction_outputs(
                 conn,
                 height,
-                tx_index as u32,
                 &txid,
-                is_coinbase,
-                input_count,
-                output_count,
-                fee_satoshis,
+                tx,
+                reprocessing,
+                &mut stats,
             )?;
 
-            // 2. Process transaction outputs
-            self.process_transaction_outputs(conn, height, &txid, tx)?;
+            // A duplicate coinbase overwrites the outputs of the earlier copy (BIP30)
+            if is_coinbase {
+                let overwritten = db::mark_overwritten_outputs(conn, &txid, height as i32)?;
+                if overwritten > 0 {
+                    if bip30::is_known_duplicate_coinbase(&txid, height) {
+                        info!(
+                            "Coinbase {} at height {} overwrote {} earlier outputs",
+                            txid, height, overwritten
+                        );
+                    } else {
+                        warn!(
+                            "Unexpected duplicate coinbase {} at height {} overwrote {} earlier outputs",
+                            txid, height, overwritten
+                        );
+                    }
+                }
+            }
 
             // 3. Process transaction inputs (except for coinbase)
-            if !is_coinbase {
-                self.process_transaction_inputs(conn, height, &txid, tx)?;
+            if is_coinbase {
+                let coinbase_value: u64 = tx.output.iter().map(|o| o.value.to_sat()).sum();
+                stats.record_coinbase_value(coinbase_value);
+            } else {
+                let spent_outputs = self
+                    .process_transaction_inputs(conn, height, block_time, &txid, tx, &mut stats)?;
+
+                let taproot_input_count = spent_outputs
+                    .iter()
+                    .filter(|o| o.script_type == ScriptType::P2tr)
+                    .count() as i32;
+                if taproot_input_count > 0 {
+                    db::update_transaction_taproot_inputs(
+                        conn,
+                        height as i32,
+                        &txid,
+                        taproot_input_count,
+                    )?;
+                }
+
+                // Addresses spent together belong to the same wallet, unless several wallets
+                // joined in a CoinJoin
+                let coinjoin = coinjoin::detect(tx);
+                let mut input_address_ids: Vec<i64> =
+                    spent_outputs.iter().map(|o| o.address_id).collect();
+                input_address_ids.sort_unstable();
+                input_address_ids.dedup();
+                if input_address_ids.len() > 1 && coinjoin.is_none() {
+                    db::merge_address_clusters(conn, &input_address_ids, height as i32)?;
+                }
+
+                if let Some(coinjoin) = &coinjoin {
+                    debug!(
+                        "Transaction {} is a {} CoinJoin of {} x {} sats",
+                        txid, coinjoin.kind, coinjoin.anonymity_set, coinjoin.denomination_satoshis
+                    );
+                    db::store_coinjoin(conn, &txid, height as i32, coinjoin)?;
+                    stats.record_coinjoin(coinjoin.mixed_value_satoshis());
+                }
+
+                // Label which outputs are likely change going back to the sender
+                let change_labels = change::label_change_outputs(&created_outputs, &spent_outputs);
+                if !change_labels.is_empty() {
+                    db::store_change_labels(conn, height as i32, &change_labels)?;
+                }
             }
         }
 
+        // 4. Store the block's aggregate statistics
+        let (block_stats, script_type_stats) = stats.into_records();
+        db::store_block_stats(conn, &block_stats, &script_type_stats)?;
+
+        Ok(())
+    }
+
+    /// Extract the coinbase data of a block and identify its miner from the configured pools
+    fn process_coinbase(
+        &self,
+        conn: &mut PgConnection,
+        height: u32,
+        block: &bitcoin::Block,
+    ) -> Result<()> {
+        let (coinbase_tx, coinbase) =
+            match (block.coinbase(), CoinbaseInfo::from_block(block, height)) {
+                (Some(tx), Some(info)) => (tx, info),
+                _ => return Ok(()),
+            };
+
+        // Outputs carrying the reward, leaving out OP_RETURN commitments
+        let payout_addresses: Vec<String> = coinbase_tx
+            .output
+            .iter()
+            .filter(|output| output.value.to_sat() > 0 && !output.script_pubkey.is_op_return())
+            .filter_map(|output| self.classifiers.classify(&output.script_pubkey))
+            .map(|script_info| encode_address(script_info.script_type, &script_info.address_hash))
+            .collect();
+
+        let attribution = self
+            .miners
+            .identify(&coinbase.ascii_tags, &payout_addresses);
+        if let Some(attribution) = &attribution {
+            debug!(
+                "Block {} attributed to {} by {} '{}'",
+                height, attribution.miner_name, attribution.matched_by, attribution.matched_value
+            );
+        }
+
+        db::store_coinbase(
+            conn,
+            height as i32,
+            &coinbase_tx.compute_txid().to_string(),
+            &coinbase,
+            &payout_addresses,
+            attribution.as_ref(),
+        )
+    }
+
+    /// Update the unspent value by creation day with a processed block, and periodically
+    /// snapshot the age distribution of the unspent set
+    fn process_utxo_age(&self, conn: &mut PgConnection, height: u32) -> Result<()> {
+        db::update_utxo_creation_days(conn, height as i32)?;
+
+        if height.is_multiple_of(utxo_age::SNAPSHOT_INTERVAL) {
+            let days = db::get_utxo_age_days(conn, height as i32)?;
+            let bands = utxo_age::totals_by_age_band(&days);
+            db::store_utxo_age_snapshot(conn, height as i32, &bands)?;
+        }
+
         Ok(())
     }
 
     /// Process outputs for a transaction (creating address records as needed)
+    /// Returns the stored outputs, in output order
     fn process_transaction_outputs(
         &self,
         conn: &mut PgConnection,
         height: u32,
         txid: &str,
         tx: &bitcoin::Transaction,
-    ) -> Result<()> {
+        reprocessing: bool,
+        stats: &mut BlockStatsCollector,
+    ) -> Result<Vec<CreatedOutput>> {
+        let mut created_outputs = Vec::with_capacity(tx.output.len());
+
         // For each output in the transaction
         for (output_index, output) in tx.output.iter().enumerate() {
             // Extract address from scriptPubKey
-            if let Some(script_info) = extract_address_from_script(&output.script_pubkey) {
+            if let Some(script_info) = self.classifiers.classify(&output.script_pubkey) {
+                // Fingerprint non-standard scripts so that similar ones can be grouped
+                let script_template_hash = match script_info.script_type {
+                    ScriptType::NonStandard | ScriptType::Unknown => {
+                        let template = ScriptTemplate::from_script(&output.script_pubkey);
+                        // A reprocessed block's outputs were counted the first time
+                        if !reprocessing {
+                            db::record_script_template(
+                                conn,
+                                &template,
+                                script_info.script_type,
+                                height as i32,
+                                txid,
+                            )?;
+                        }
+                        Some(template.template_hash)
+                    }
+                    _ => None,
+                };
+
                 // Store or get address ID
-                let address_id = db::get_or_create_address(
+                let address = db::get_or_create_address(
                     conn,
-                    &script_info.address,
-                    &script_info.script_type,
+                    &script_info.address_hash,
+                    script_info.script_type,
                     height,
-                    script_info.extra_data,
+                    script_info.extra_data.clone(),
+                    &script_info.public_keys,
+                    script_template_hash,
                 )?;
 
+                // An address receiving after its public key was revealed, by this address or a
+                // sibling of it. Taproot output keys are not stored on the address, but are
+                // always exposed.
+                let received_after_exposure = (!address.created || address.exposed_by_sibling)
+                    && (address.is_public_key_exposed
+                        || script_info.script_type == ScriptType::P2tr);
+
                 // Store the output - convert Amount to u64
-                db::store_transaction_output(
+                let stored_output = db::store_transaction_output(
                     conn,
-                    address_id,
+                    address.address_id,
                     txid,
                     height as i32,
                     output_index as i32,
                     output.value.to_sat(),
+                    received_after_exposure,
                 )?;
+
+                // The output creating the address, also when its block is reprocessed
+                let is_new_address = address.is_first_output(stored_output.output_id);
+                if is_new_address {
+                    if let Some(ScriptExtraData::P2ms(multisig)) = &script_info.extra_data {
+                        db::store_multisig_script(
+                            conn,
+                            address.address_id,
+                            multisig,
+                            &script_info.public_keys,
+                        )?;
+                    }
+                    // P2PK and P2MS keys are exposed as soon as the output is created
+                    if address.exposed_by_output_script {
+                        stats.record_newly_exposed_public_key(output.value.to_sat() as i64);
+                    }
+
+                    // Value held by sibling addresses of those keys is exposed as well
+                    for sibling_address_id in &address.newly_exposed_siblings {
+                        let unspent_value =
+                            db::get_address_unspent_value(conn, *sibling_address_id)?;
+                        stats.record_newly_exposed_public_key(unspent_value);
+                    }
+                }
+
+                stats.record_output(
+                    script_info.script_type,
+                    &address,
+                    is_new_address,
+                    output.value.to_sat(),
+                    stored_output.received_after_exposure,
+                );
+
+                created_outputs.push(CreatedOutput {
+                    output_id: stored_output.output_id,
+                    address_id: address.address_id,
+                    script_type: script_info.script_type,
+                    value_satoshis: output.value.to_sat(),
+                    is_new_address,
+                });
             }
         }
 
-        Ok(())
+        Ok(created_outputs)
     }
 
     /// Process inputs for a transaction (linking to previous outputs)
+    /// Returns the outputs spent by the inputs, in input order
     fn process_transaction_inputs(
         &self,
         conn: &mut PgConnection,
         height: u32,
+        block_time: i64,
         txid: &str,
         tx: &bitcoin::Transaction,
-    ) -> Result<()> {
+        stats: &mut BlockStatsCollector,
+    ) -> Result<Vec<OutputInfo>> {
+        let mut spent_outputs = Vec::with_capacity(tx.input.len());
+
         // For each input in the transaction
         for (input_index, input) in tx.input.iter().enumerate() {
             let prev_txid = input.previous_output.txid.to_string();
             let prev_vout = input.previous_output.vout as i32;
 
             // Find the previous output - now without needing to specify height
-            if let Some(output_info) = db::find_output(conn, &prev_txid, prev_vout)? {
-                // Extract public key from input script if available
-                let public_key = extract_public_key_from_script(&input.script_sig);
+            if let Some(output_info) = db::find_output(
+                conn,
+                &prev_txid,
+                prev_vout,
+                txid,
+                height as i32,
+                input_index as i32,
+            )? {
+                // Extract public key from the witness or input script if available
+                let public_key = pubkey::witness_public_key(
+                    output_info.script_type,
+                    &input.script_sig,
+                    &input.witness,
+                )
+                .or_else(|| extract_public_key_from_script(&input.script_sig));
+                let coin_age = CoinAge::new(&output_info, height as i32, block_time);
 
                 // Store the input and mark the output as spent
-                let input_id = db::store_transaction_input(
+                let stored_input = db::store_transaction_input(
                     conn,
                     output_info.address_id,
                     txid,
@@ -350,348 +622,58 @@ impl BlockProcessor {
                     output_info.output_id,
                     output_info.value_satoshis,
                     public_key,
+                    &InputSequence::new(tx.version, input.sequence),
+                    &coin_age,
                 )?;
 
                 // Update the output to mark it as spent
-                db::mark_output_spent(conn, output_info.output_id, input_id)?;
-            }
-        }
-
-        Ok(())
-    }
-}
-
-/// Structure to represent script type and address
-pub struct ScriptInfo {
-    pub address: String,
-    pub script_type: String,
-    pub extra_data: Option<serde_json::Value>, // JSON for flexible additional data
-}
-
-/// Extract address and script type information from output script
-fn extract_address_from_script(script: &Script) -> Option<ScriptInfo> {
-    let instructions = script
-        .instructions()
-        .filter_map(Result::ok)
-        .collect::<Vec<_>>();
-
-    // P2PKH (Pay to Public Key Hash)
-    // P2PKH is of the form: OP_DUP OP_HASH160 <20-byte hash> OP_EQUALVERIFY OP_CHECKSIG
-    // Actually there is a OP_PUSHBYTES_20 before the 20-byte hash
-    // but this is included in the same element as the hash in the instructions vector
-    if script.is_p2pkh() {
-        // hash160 is the 20-byte hash of the public key
-        if let Some(Instruction::PushBytes(hash160)) = instructions.get(2) {
-            if hash160.len() == 20 {
-                // Create address from hash160
-                // https://learnmeabitcoin.com/technical/script/p2pkh/#address
-                let mut data = vec![0]; // mainnet prefix is 00, 6f for testnet
-                data.extend_from_slice(hash160.as_bytes());
-                let address = base58::encode_check(&data);
-                return Some(ScriptInfo {
-                    address,
-                    script_type: "p2pkh".to_string(),
-                    extra_data: None,
-                });
-            }
-        }
-    }
-    // P2SH (Pay to Script Hash)
-    // P2SH is of the form: OP_HASH160 <20-byte hash> OP_EQUAL
-    // Actually there is a OP_PUSHBYTES_20 before the 20-byte hash
-    // but this is included in the same element as the hash in the instructions vector
-    else if script.is_p2sh() {
-        if let Some(Instruction::PushBytes(hash160)) = instructions.get(1) {
-            if hash160.len() == 20 {
-                // Create address from hash160
-                // https://learnmeabitcoin.com/technical/script/p2sh/#address
-                let mut data = vec![5]; // mainnet p2sh prefix 05, c4 for testnet
-                data.extend_from_slice(hash160.as_bytes());
-                let address = base58::encode_check(&data);
-                return Some(ScriptInfo {
-                    address,
-                    script_type: "p2sh".to_string(),
-                    extra_data: None,
-                });
-            }
-        }
-    }
-    // P2PK (Pay to Public Key)
-    // P2PK is of the form: <pubkey> OP_CHECKSIG
-    else if instructions.len() == 2
-        && matches!(instructions[0], Instruction::PushBytes(_))
-        && (instructions[1].opcode() == Some(OP_CHECKSIG))
-    {
-        if let Instruction::PushBytes(pubkey_bytes) = &instructions[0] {
-            if pubkey_bytes.len() == 33 || pubkey_bytes.len() == 65 {
-                // Use the pubkey directly as the address - hex encode it for storage
-                let pubkey_hex = hex::encode(pubkey_bytes.as_bytes());
+                db::mark_output_spent(
+                    conn,
+                    output_info.output_id,
+                    output_info.block_height,
+                    stored_input.input_id,
+                )?;
 
-                // Store pubkey format as extra data
-                let extra_data = serde_json::json!({
-                    "pubkey_format": if pubkey_bytes.len() == 33 { "compressed" } else { "uncompressed" }
-                });
+                // Record the ECDSA signatures for auditing R value reuse
+                let signatures = signature::extract_signatures(&input.script_sig, &input.witness);
+                if !signatures.is_empty() {
+                    db::store_signatures(conn, stored_input.input_id, height as i32, &signatures)?;
+                }
 
-                return Some(ScriptInfo {
-                    address: pubkey_hex, // Use the pubkey hex directly as address
-                    script_type: "p2pk".to_string(),
-                    extra_data: Some(extra_data),
-                });
-            } else {
-                error!("Invalid P2PK public key length: {}", pubkey_bytes.len());
-            }
-        }
-    }
-    // All script types that use witness program: P2WPKH, P2WSH, P2TR
-    else if script.is_witness_program() {
-        // P2WPKH (Pay to Witness Public Key Hash)
-        // P2WPKH is of the form: OP_0 <20-byte hash>
-        // https://learnmeabitcoin.com/technical/script/p2wpkh/#address
-        if script.is_p2wpkh() {
-            // TODO: maybe remove these redundant checks?
-            if let Some(Instruction::PushBytes(witness_program)) = instructions.get(1) {
-                if witness_program.len() == 20 {
-                    match encode_bech32_address("bc", 0, witness_program.as_bytes()) {
-                        Ok(address) => {
-                            return Some(ScriptInfo {
-                                address,
-                                script_type: "p2wpkh".to_string(),
-                                extra_data: None,
-                            });
-                        }
-                        Err(e) => {
-                            error!("Failed to encode P2WPKH address: {}", e);
-                            return None; // Skip this script if we can't encode the address
-                        }
+                stats.record_input(output_info.script_type, output_info.value_satoshis);
+                stats.record_coin_age(&coin_age, output_info.value_satoshis);
+                if output_info.script_type == ScriptType::P2tr {
+                    // Script-path spends may reveal inscriptions
+                    let envelopes = inscription::parse_envelopes(&input.witness);
+                    if !envelopes.is_empty() {
+                        db::store_inscriptions(
+                            conn,
+                            stored_input.input_id,
+                            txid,
+                            height as i32,
+                            input_index as i32,
+                            &envelopes,
+                        )?;
                     }
                 }
-            }
-        }
-        // P2WSH (Pay to Witness Script Hash)
-        // P2WSH is of the form: OP_0 <32-byte hash>
-        // https://learnmeabitcoin.com/technical/script/p2wsh/#address
-        else if script.is_p2wsh() {
-            // TODO: maybe remove these redundant checks?
-            if let Some(Instruction::PushBytes(witness_program)) = instructions.get(1) {
-                if witness_program.len() == 32 {
-                    match encode_bech32_address("bc", 0, witness_program.as_bytes()) {
-                        Ok(address) => {
-                            return Some(ScriptInfo {
-                                address,
-                                script_type: "p2wsh".to_string(),
-                                extra_data: None,
-                            });
-                        }
-                        Err(e) => {
-                            error!("Failed to encode P2WSH address: {}", e);
-                            return None; // Skip this script if we can't encode the address
-                        }
-                    }
+
+                // Value left at the address is now protected only by an exposed public key
+                if stored_input.public_key_newly_exposed {
+                    let unspent_value =
+                        db::get_address_unspent_value(conn, output_info.address_id)?;
+                    stats.record_newly_exposed_public_key(unspent_value);
                 }
-            }
-        }
-        // P2TR (Pay to Taproot)
-        // P2TR is of the form: OP_1 <32-byte hash>
-        // https://learnmeabitcoin.com/technical/script/p2tr/#address
-        else if instructions.len() == 2 &&
-                instructions[0].opcode() == Some(bitcoin::opcodes::all::OP_PUSHNUM_1) && // Use OP_PUSHNUM_1 instead of OP_1
-                matches!(instructions[1], Instruction::PushBytes(bytes) if bytes.len() == 32)
-        {
-            if let Instruction::PushBytes(taproot_output_key) = &instructions[1] {
-                match encode_bech32_address("bc", 1, taproot_output_key.as_bytes()) {
-                    Ok(address) => {
-                        return Some(ScriptInfo {
-                            address,
-                            script_type: "p2tr".to_string(),
-                            extra_data: None,
-                        });
-                    }
-                    Err(e) => {
-                        error!("Failed to encode P2TR address: {}", e);
-                        return None; // Skip this script if we can't encode the address
-                    }
+                for sibling_address_id in &stored_input.newly_exposed_siblings {
+                    let unspent_value = db::get_address_unspent_value(conn, *sibling_address_id)?;
+                    stats.record_newly_exposed_public_key(unspent_value);
                 }
-            }
-        }
-    }
 
-    // P2MS (Pay to MultiSig)
-    // P2MS is of the form: <m> <pubkey1> ... <pubkeyN> <n> OP_CHECKMULTISIG
-    // https://learnmeabitcoin.com/technical/script/p2ms/#address
-    // P2MS is a locking script for up to 3 public keys (to meet standardness requirements)
-    // It's possible to create a multisig script with more public keys (up to 20)
-    // but it will be considered non-standard and will not be relayed by nodes.
-    // TODO: add support for more than 3 public keys
-    if instructions.len() >= 4
-        && instructions
-            .last()
-            .map_or(false, |i| i.opcode() == Some(OP_CHECKMULTISIG))
-    {
-        // Get first and second-to-last opcodes
-        let first_op = instructions.first().and_then(|i| i.opcode());
-        let n_op = instructions
-            .get(instructions.len() - 2)
-            .and_then(|i| i.opcode());
-
-        // Check if valid m-of-n pattern
-        if let (Some(first_op), Some(n_op)) = (first_op, n_op) {
-            // Extract m and n values
-            let m = match first_op {
-                bitcoin::opcodes::all::OP_PUSHNUM_1 => 1,
-                bitcoin::opcodes::all::OP_PUSHNUM_2 => 2,
-                bitcoin::opcodes::all::OP_PUSHNUM_3 => 3,
-                _ => return None, // Invalid m value for standard P2MS
-            };
-
-            let n = match n_op {
-                bitcoin::opcodes::all::OP_PUSHNUM_1 => 1,
-                bitcoin::opcodes::all::OP_PUSHNUM_2 => 2,
-                bitcoin::opcodes::all::OP_PUSHNUM_3 => 3,
-                _ => return None, // Invalid n value for standard P2MS
-            };
-
-            // Valid multisig must have m ≤ n and expected number of pubkeys
-            if m <= n && instructions.len() == n as usize + 3 {
-                // Create a hash of the script to use as an "address"
-                let script_hash = hash160::Hash::hash(&script.to_bytes());
-                let mut data = vec![5]; // Use same prefix as P2SH for consistency
-                data.extend_from_slice(&script_hash[..]);
-                let address = base58::encode_check(&data);
-
-                // Store m and n in the extra data
-                let extra_data = serde_json::json!({
-                    "m": m,
-                    "n": n
-                });
-
-                return Some(ScriptInfo {
-                    address,
-                    script_type: "p2ms".to_string(),
-                    extra_data: Some(extra_data),
-                });
-            }
-        }
-    }
-
-    // Non-standard scripts
-
-    // Non-standard: P2PKH with extra operations (like the OP_NOP case)
-    if instructions.len() > 5
-        && instructions[0].opcode() == Some(OP_DUP)
-        && instructions[1].opcode() == Some(OP_HASH160)
-        && matches!(instructions[2], Instruction::PushBytes(_))
-        && instructions[3].opcode() == Some(OP_EQUALVERIFY)
-        && instructions[4].opcode() == Some(OP_CHECKSIG)
-    {
-        if let Instruction::PushBytes(hash160) = &instructions[2] {
-            if hash160.len() == 20 {
-                // Create address from hash160
-                let mut data = vec![0]; // mainnet prefix
-                data.extend_from_slice(hash160.as_bytes());
-                let address = base58::encode_check(&data);
-
-                // Create extra data with script details
-                let script_ops: Vec<String> = instructions
-                    .iter()
-                    .map(|inst| match inst {
-                        Instruction::PushBytes(bytes) => format!("PUSH({} bytes)", bytes.len()),
-                        Instruction::Op(op) => format!("{:?}", op),
-                        _ => "Unknown".to_string(),
-                    })
-                    .collect();
-
-                let extra_ops = if instructions.len() > 5 {
-                    script_ops[5..].to_vec()
-                } else {
-                    Vec::new()
-                };
-
-                let extra_data = serde_json::json!({
-                    "pattern": "p2pkh-plus",
-                    "extra_ops": extra_ops
-                });
-
-                debug!("Found non-standard script: {}", script_ops.join(" "));
-                return Some(ScriptInfo {
-                    address,
-                    script_type: "non-standard".to_string(),
-                    extra_data: Some(extra_data),
-                });
-            }
-        }
-    }
-
-    // Generic non-standard script with a 20-byte hash (likely a pubkey hash)
-    for (i, instruction) in instructions.iter().enumerate() {
-        if matches!(instruction, Instruction::PushBytes(bytes) if bytes.len() == 20) {
-            if let Instruction::PushBytes(hash_bytes) = instruction {
-                // Create a hash160-based address
-                let mut data = vec![0]; // Use mainnet P2PKH prefix
-                data.extend_from_slice(hash_bytes.as_bytes());
-                let address = base58::encode_check(&data);
-
-                // Create extra data for analysis
-                let script_ops: Vec<String> = instructions
-                    .iter()
-                    .map(|inst| match inst {
-                        Instruction::PushBytes(bytes) => format!("PUSH({} bytes)", bytes.len()),
-                        Instruction::Op(op) => format!("{:?}", op),
-                        _ => "Unknown".to_string(),
-                    })
-                    .collect();
-
-                let extra_data = serde_json::json!({
-                    "pattern": "hash160-found",
-                    "hash_position": i,
-                    "script_ops": script_ops
-                });
-
-                debug!(
-                    "Processing non-standard script with 20-byte hash: {}",
-                    script_ops.join(" ")
-                );
-                return Some(ScriptInfo {
-                    address,
-                    script_type: "non-standard".to_string(),
-                    extra_data: Some(extra_data),
-                });
+                spent_outputs.push(output_info);
             }
         }
-    }
-
-    // If we still haven't found a match, hash the entire script
-    let script_bytes = script.to_bytes();
-    if !script_bytes.is_empty() {
-        let script_hash = hash160::Hash::hash(&script_bytes);
-        let mut data = vec![5]; // Use P2SH prefix
-        data.extend_from_slice(&script_hash[..]);
-        let address = base58::encode_check(&data);
-
-        // Log the script pattern
-        let script_ops: Vec<String> = instructions
-            .iter()
-            .map(|inst| match inst {
-                Instruction::PushBytes(bytes) => format!("PUSH({} bytes)", bytes.len()),
-                Instruction::Op(op) => format!("{:?}", op),
-                _ => "Unknown".to_string(),
-            })
-            .collect();
-
-        let extra_data = serde_json::json!({ "script_pattern": script_ops });
 
-        debug!(
-            "Handling unknown script with custom hash: {}",
-            script_ops.join(" ")
-        );
-        return Some(ScriptInfo {
-            address,
-            script_type: "unknown".to_string(),
-            extra_data: Some(extra_data),
-        });
+        Ok(spent_outputs)
     }
-
-    // Truly empty or invalid script
-    None
 }
 
 /// Extract public key from input script if available
@@ -717,24 +699,3 @@ fn extract_public_key_from_script(script: &Script) -> Option<Vec<u8>> {
     // No public key found
     None
 }
-
-/// Helper function to encode a bech32/bech32m address
-/// Returns Result<String, String> to properly handle encoding errors
-fn encode_bech32_address(hrp_str: &str, version_u8: u8, program: &[u8]) -> Result<String, String> {
-    // Parse the HRP (Human Readable Part)
-    let hrp = match hrp_str {
-        "bc" => hrp::BC,
-        _ => Hrp::parse(hrp_str).expect("Invalid HRP"),
-    };
-
-    // Handle known versions with constants
-    if version_u8 == 0 {
-        segwit::encode(hrp, segwit::VERSION_0, program)
-            .map_err(|e| format!("Failed to encode SegWit v0 address: {}", e))
-    } else if version_u8 == 1 {
-        segwit::encode(hrp, segwit::VERSION_1, program)
-            .map_err(|e| format!("Failed to encode SegWit v1 address: {}", e))
-    } else {
-        Err(format!("Unsupported witness version: {}", version_u8))
-    }
-}
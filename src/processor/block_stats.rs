@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::coin_age::{CoinAge, SATOSHIS_PER_BTC};
+use crate::db::models::{NewBlockScriptTypeStats, NewBlockStats};
+use crate::db::AddressLookup;
+use crate::script_type::ScriptType;
+
+/// Running totals for a single script type within a block
+#[derive(Default)]
+struct ScriptTypeTotals {
+    output_count: i32,
+    output_value_satoshis: i64,
+    input_count: i32,
+    input_value_satoshis: i64,
+    reused_output_count: i32,
+    post_exposure_output_count: i32,
+    post_exposure_value_satoshis: i64,
+}
+
+/// Accumulates the aggregate statistics of a block while its transactions are processed
+pub struct BlockStatsCollector {
+    block_height: u32,
+    new_address_count: i32,
+    reused_addresses: HashSet<i64>,
+    newly_exposed_public_key_count: i32,
+    newly_exposed_value_satoshis: i64,
+    coinbase_value_satoshis: i64,
+    coinjoin_count: i32,
+    coinjoin_value_satoshis: i64,
+    coin_days_destroyed: f64,
+    aged_spent_value_satoshis: i64,
+    // BTreeMap keeps the stored rows in a stable script type order
+    script_types: BTreeMap<ScriptType, ScriptTypeTotals>,
+}
+
+impl BlockStatsCollector {
+    pub fn new(block_height: u32) -> Self {
+        Self {
+            block_height,
+            new_address_count: 0,
+            reused_addresses: HashSet::new(),
+            newly_exposed_public_key_count: 0,
+            newly_exposed_value_satoshis: 0,
+            coinbase_value_satoshis: 0,
+            coinjoin_count: 0,
+            coinjoin_value_satoshis: 0,
+            coin_days_destroyed: 0.0,
+            aged_spent_value_satoshis: 0,
+            script_types: BTreeMap::new(),
+        }
+    }
+
+    /// Record an output paying to an address
+    /// `is_new_address` is true for the output that created the address, and
+    /// `received_after_exposure` is true if the address's public key was already exposed.
+    /// An address first seen in this block is new, however many outputs it receives in it, and
+    /// only one seen in an earlier block is reused.
+    pub fn record_output(
+        &mut self,
+        script_type: ScriptType,
+        address: &AddressLookup,
+        is_new_address: bool,
+        value_satoshis: u64,
+        received_after_exposure: bool,
+    ) {
+        let totals = self.script_types.entry(script_type).or_default();
+        totals.output_count += 1;
+        totals.output_value_satoshis += value_satoshis as i64;
+
+        if is_new_address {
+            self.new_address_count += 1;
+        } else if address.first_seen_block_height < self.block_height as i32 {
+            // The address already existed, so it is being reused
+            totals.reused_output_count += 1;
+            self.reused_addresses.insert(address.address_id);
+        }
+
+        if received_after_exposure {
+            totals.post_exposure_output_count += 1;
+            totals.post_exposure_value_satoshis += value_satoshis as i64;
+        }
+    }
+
+    /// Record an input spending an output of the given script type
+    pub fn record_input(&mut self, spent_script_type: ScriptType, value_satoshis: i64) {
+        let totals = self.script_types.entry(spent_script_type).or_default();
+        totals.input_count += 1;
+        totals.input_value_satoshis += value_satoshis;
+    }
+
+    /// Record an address whose public key was exposed for the first time in this block
+    /// `unspent_value_satoshis` is the value still held by the address after the exposing spend
+    pub fn record_newly_exposed_public_key(&mut self, unspent_value_satoshis: i64) {
+        self.newly_exposed_public_key_count += 1;
+        self.newly_exposed_value_satoshis += unspent_value_satoshis;
+    }
+
+    /// Record the total output value of the block's coinbase transaction
+    pub fn record_coinbase_value(&mut self, value_satoshis: u64) {
+        self.coinbase_value_satoshis += value_satoshis as i64;
+    }
+
+    /// Record a CoinJoin transaction and the value it mixed
+    pub fn record_coinjoin(&mut self, mixed_value_satoshis: u64) {
+        self.coinjoin_count += 1;
+        self.coinjoin_value_satoshis += mixed_value_satoshis as i64;
+    }
+
+    /// Record the age of the coins spent by an input
+    pub fn record_coin_age(&mut self, coin_age: &CoinAge, value_satoshis: i64) {
+        self.coin_days_destroyed += coin_age.coin_days_destroyed(value_satoshis);
+        self.aged_spent_value_satoshis += value_satoshis;
+    }
+
+    /// Convert the accumulated totals into database records
+    pub fn into_records(self) -> (NewBlockStats, Vec<NewBlockScriptTypeStats>) {
+        let block_height = self.block_height as i32;
+
+        // Weighted by value, the average age of the spent coins is the coin days destroyed per BTC
+        let average_spent_age_days = if self.aged_spent_value_satoshis > 0 {
+            self.coin_days_destroyed * SATOSHIS_PER_BTC / self.aged_spent_value_satoshis as f64
+        } else {
+            0.0
+        };
+
+        let block_stats = NewBlockStats {
+            block_height,
+            new_address_count: self.new_address_count,
+            reused_address_count: self.reused_addresses.len() as i32,
+            newly_exposed_public_key_count: self.newly_exposed_public_key_count,
+            newly_exposed_value_satoshis: self.newly_exposed_value_satoshis,
+            coinbase_value_satoshis: self.coinbase_value_satoshis,
+            coinjoin_count: self.coinjoin_count,
+            coinjoin_value_satoshis: self.coinjoin_value_satoshis,
+            coin_days_destroyed: self.coin_days_destroyed,
+            average_spent_age_days,
+        };
+
+        let script_type_stats = self
+            .script_types
+            .into_iter()
+            .map(|(script_type, totals)| NewBlockScriptTypeStats {
+                block_height,
+                script_type,
+                output_count: totals.output_count,
+                output_value_satoshis: totals.output_value_satoshis,
+                input_count: totals.input_count,
+                input_value_satoshis: totals.input_value_satoshis,
+                reused_output_count: totals.reused_output_count,
+                post_exposure_output_count: totals.post_exposure_output_count,
+                post_exposure_value_satoshis: totals.post_exposure_value_satoshis,
+            })
+            .collect();
+
+        (block_stats, script_type_stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An existing address, first seen at `first_seen_block_height`
+    fn address(address_id: i64, first_seen_block_height: i32) -> AddressLookup {
+        AddressLookup {
+            address_id,
+            created: false,
+            first_seen_block_height,
+            first_output_id: None,
+            is_public_key_exposed: false,
+            exposed_by_output_script: false,
+            exposed_by_sibling: false,
+            newly_exposed_siblings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn address_receiving_twice_in_its_first_block_is_only_new() {
+        let mut stats = BlockStatsCollector::new(100);
+        let new_address = address(1, 100);
+        stats.record_output(ScriptType::P2wpkh, &new_address, true, 1_000, false);
+        stats.record_output(ScriptType::P2wpkh, &new_address, false, 2_000, false);
+
+        let (block_stats, script_type_stats) = stats.into_records();
+        assert_eq!(block_stats.new_address_count, 1);
+        assert_eq!(block_stats.reused_address_count, 0);
+        assert_eq!(script_type_stats[0].output_count, 2);
+        assert_eq!(script_type_stats[0].reused_output_count, 0);
+    }
+
+    #[test]
+    fn address_seen_in_an_earlier_block_is_reused_once() {
+        let mut stats = BlockStatsCollector::new(100);
+        let old_address = address(1, 50);
+        stats.record_output(ScriptType::P2pkh, &old_address, false, 1_000, true);
+        stats.record_output(ScriptType::P2pkh, &old_address, false, 2_000, true);
+
+        let (block_stats, script_type_stats) = stats.into_records();
+        assert_eq!(block_stats.new_address_count, 0);
+        assert_eq!(block_stats.reused_address_count, 1);
+        assert_eq!(script_type_stats[0].reused_output_count, 2);
+        assert_eq!(script_type_stats[0].post_exposure_value_satoshis, 3_000);
+    }
+}
@@ -0,0 +1,300 @@
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::script::Script;
+use bitcoin::Witness;
+use secp256k1::PublicKey;
+
+use crate::script_type::{PubkeyFormat, ScriptType};
+
+/// Classifies a candidate public key by its encoding, checking that it is a point on the curve.
+/// 33 or 65 byte pushes that are not valid keys are usually data embedded in fake public keys.
+pub fn classify_public_key(bytes: &[u8]) -> PubkeyFormat {
+    let format = match (bytes.len(), bytes.first()) {
+        (33, Some(0x02 | 0x03)) => PubkeyFormat::Compressed,
+        (65, Some(0x04)) => PubkeyFormat::Uncompressed,
+        // Uncompressed key whose prefix also encodes the parity of y
+        (65, Some(0x06 | 0x07)) => PubkeyFormat::Hybrid,
+        _ => return PubkeyFormat::Invalid,
+    };
+
+    // libsecp256k1 also checks the parity of hybrid keys
+    match PublicKey::from_slice(bytes) {
+        Ok(_) => format,
+        Err(_) => PubkeyFormat::Invalid,
+    }
+}
+
+/// Script types of the addresses a public key backs, see `sibling_addresses`
+pub const SIBLING_SCRIPT_TYPES: [ScriptType; 4] = [
+    ScriptType::P2pk,
+    ScriptType::P2pkh,
+    ScriptType::P2wpkh,
+    ScriptType::P2sh,
+];
+
+/// The standard addresses a public key backs, as their script type and address hash:
+/// P2PK and P2PKH for any valid key, and P2WPKH and P2SH-P2WPKH for compressed keys,
+/// which are the only keys SegWit accepts. Revealing the key exposes every one of them.
+pub fn sibling_addresses(key: &[u8], format: PubkeyFormat) -> Vec<(ScriptType, Vec<u8>)> {
+    if !format.is_valid() {
+        return Vec::new();
+    }
+
+    let key_hash = hash160::Hash::hash(key).to_byte_array();
+    let mut siblings = vec![
+        (ScriptType::P2pk, key.to_vec()),
+        (ScriptType::P2pkh, key_hash.to_vec()),
+    ];
+
+    if format == PubkeyFormat::Compressed {
+        siblings.push((ScriptType::P2wpkh, key_hash.to_vec()));
+
+        // The P2SH redeem script is the P2WPKH output script: OP_0 <20-byte key hash>
+        let mut redeem_script = vec![0x00, 0x14];
+        redeem_script.extend_from_slice(&key_hash);
+        siblings.push((
+            ScriptType::P2sh,
+            hash160::Hash::hash(&redeem_script).to_byte_array().to_vec(),
+        ));
+    }
+
+    siblings
+}
+
+/// The public key revealed in the witness of an input spending a P2WPKH output, natively or
+/// nested in P2SH, whose witness is <signature> <compressed pubkey>
+pub fn witness_public_key(
+    spent_script_type: ScriptType,
+    script_sig: &Script,
+    witness: &Witness,
+) -> Option<Vec<u8>> {
+    let spends_p2wpkh = match spent_script_type {
+        ScriptType::P2wpkh => true,
+        // P2SH-P2WPKH input script: <redeem script>, the P2WPKH output script
+        ScriptType::P2sh => {
+            let mut instructions = script_sig.instructions();
+            match (instructions.next(), instructions.next()) {
+                (Some(Ok(Instruction::PushBytes(redeem_script))), None) => {
+                    Script::from_bytes(redeem_script.as_bytes()).is_p2wpkh()
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+
+    match witness.nth(1) {
+        Some(key) if spends_p2wpkh && witness.len() == 2 && key.len() == 33 => Some(key.to_vec()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::encode_address;
+    use bitcoin::script::{Builder, PushBytesBuf};
+
+    /// The secp256k1 generator point, the public key of private key 1
+    const GENERATOR_COMPRESSED: &str =
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const GENERATOR_UNCOMPRESSED: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    /// The addresses of `siblings`, encoded
+    fn encoded(siblings: &[(ScriptType, Vec<u8>)]) -> Vec<(ScriptType, String)> {
+        siblings
+            .iter()
+            .map(|(script_type, hash)| (*script_type, encode_address(*script_type, hash)))
+            .collect()
+    }
+
+    /// The format of a hex encoded key
+    fn classify_hex(key: &str) -> PubkeyFormat {
+        classify_public_key(&hex::decode(key).unwrap())
+    }
+
+    #[test]
+    fn valid_keys_are_classified_by_prefix() {
+        assert_eq!(classify_hex(GENERATOR_COMPRESSED), PubkeyFormat::Compressed);
+        assert_eq!(
+            classify_hex(GENERATOR_UNCOMPRESSED),
+            PubkeyFormat::Uncompressed
+        );
+        // The y of the generator is even, so its hybrid form takes the 0x06 prefix
+        assert_eq!(
+            classify_hex(&format!("06{}", &GENERATOR_UNCOMPRESSED[2..])),
+            PubkeyFormat::Hybrid
+        );
+    }
+
+    #[test]
+    fn keys_off_the_curve_are_invalid() {
+        // A hybrid prefix that disagrees with the parity of y
+        assert_eq!(
+            classify_hex(&format!("07{}", &GENERATOR_UNCOMPRESSED[2..])),
+            PubkeyFormat::Invalid
+        );
+        // x = 5, for which x^3 + 7 has no square root mod p
+        assert_eq!(
+            classify_hex("020000000000000000000000000000000000000000000000000000000000000005"),
+            PubkeyFormat::Invalid
+        );
+        // The generator with its y altered
+        let mut key = hex::decode(GENERATOR_UNCOMPRESSED).unwrap();
+        key[64] ^= 0x01;
+        assert_eq!(classify_public_key(&key), PubkeyFormat::Invalid);
+        // x at or above the field prime
+        assert_eq!(
+            classify_hex("02ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+            PubkeyFormat::Invalid
+        );
+    }
+
+    #[test]
+    fn keys_of_the_wrong_length_are_invalid() {
+        assert_eq!(
+            classify_hex(&GENERATOR_COMPRESSED[..64]),
+            PubkeyFormat::Invalid
+        );
+        assert_eq!(
+            classify_hex(&format!("{GENERATOR_COMPRESSED}00")),
+            PubkeyFormat::Invalid
+        );
+        assert_eq!(
+            classify_hex(&format!("02{}", &GENERATOR_UNCOMPRESSED[2..])),
+            PubkeyFormat::Invalid
+        );
+        assert_eq!(classify_public_key(&[]), PubkeyFormat::Invalid);
+    }
+
+    #[test]
+    fn compressed_key_backs_four_addresses() {
+        let key = hex::decode(GENERATOR_COMPRESSED).unwrap();
+        let siblings = sibling_addresses(&key, classify_public_key(&key));
+
+        assert_eq!(
+            encoded(&siblings),
+            vec![
+                (ScriptType::P2pk, GENERATOR_COMPRESSED.to_string()),
+                (
+                    ScriptType::P2pkh,
+                    "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH".to_string()
+                ),
+                // The BIP173 P2WPKH test vector
+                (
+                    ScriptType::P2wpkh,
+                    "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string()
+                ),
+                (
+                    ScriptType::P2sh,
+                    "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn uncompressed_key_backs_no_segwit_addresses() {
+        let key = hex::decode(GENERATOR_UNCOMPRESSED).unwrap();
+        let siblings = sibling_addresses(&key, classify_public_key(&key));
+
+        assert_eq!(
+            encoded(&siblings),
+            vec![
+                (ScriptType::P2pk, GENERATOR_UNCOMPRESSED.to_string()),
+                (
+                    ScriptType::P2pkh,
+                    "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_key_backs_no_addresses() {
+        let mut key = hex::decode(GENERATOR_COMPRESSED).unwrap();
+        key[0] = 0x05;
+        assert!(sibling_addresses(&key, classify_public_key(&key)).is_empty());
+    }
+
+    /// A P2WPKH witness: <signature> <public key>
+    fn witness(signature: &str, key: &str) -> Witness {
+        Witness::from_slice(&[hex::decode(signature).unwrap(), hex::decode(key).unwrap()])
+    }
+
+    /// An input script pushing `redeem_script`
+    fn redeem_script_sig(redeem_script: &str) -> bitcoin::ScriptBuf {
+        let push = PushBytesBuf::try_from(hex::decode(redeem_script).unwrap()).unwrap();
+        Builder::new().push_slice(push).into_script()
+    }
+
+    // The native P2WPKH input of the BIP143 example transaction
+    const P2WPKH_SIGNATURE: &str = "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01";
+    const P2WPKH_KEY: &str = "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357";
+    const P2WPKH_KEY_HASH: &str = "1d0f172a0ecb48aee1be1f2687d2963ae33f71a1";
+
+    // The P2SH-P2WPKH input of the BIP143 example transaction
+    const P2SH_P2WPKH_SIGNATURE: &str = "3044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb01";
+    const P2SH_P2WPKH_KEY: &str =
+        "03ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a26873";
+    const P2SH_P2WPKH_REDEEM_SCRIPT: &str = "001479091972186c449eb1ded22b78e40d009bdf0089";
+
+    #[test]
+    fn p2wpkh_spend_reveals_witness_key() {
+        let key = hex::decode(P2WPKH_KEY).unwrap();
+        assert_eq!(
+            hex::encode(hash160::Hash::hash(&key).to_byte_array()),
+            P2WPKH_KEY_HASH
+        );
+
+        let revealed = witness_public_key(
+            ScriptType::P2wpkh,
+            Script::new(),
+            &witness(P2WPKH_SIGNATURE, P2WPKH_KEY),
+        );
+        assert_eq!(revealed, Some(key));
+    }
+
+    #[test]
+    fn p2sh_p2wpkh_spend_reveals_witness_key() {
+        let key = hex::decode(P2SH_P2WPKH_KEY).unwrap();
+        assert_eq!(
+            hex::encode(hash160::Hash::hash(&key).to_byte_array()),
+            &P2SH_P2WPKH_REDEEM_SCRIPT[4..]
+        );
+
+        let revealed = witness_public_key(
+            ScriptType::P2sh,
+            &redeem_script_sig(P2SH_P2WPKH_REDEEM_SCRIPT),
+            &witness(P2SH_P2WPKH_SIGNATURE, P2SH_P2WPKH_KEY),
+        );
+        assert_eq!(revealed, Some(key));
+    }
+
+    #[test]
+    fn other_spends_reveal_no_witness_key() {
+        let p2wpkh_witness = witness(P2WPKH_SIGNATURE, P2WPKH_KEY);
+
+        // P2SH wrapping a P2WSH program
+        let p2wsh_redeem_script = format!("0020{}", "11".repeat(32));
+        assert_eq!(
+            witness_public_key(
+                ScriptType::P2sh,
+                &redeem_script_sig(&p2wsh_redeem_script),
+                &p2wpkh_witness,
+            ),
+            None
+        );
+        // Legacy spends carry the key in the input script
+        assert_eq!(
+            witness_public_key(ScriptType::P2pkh, Script::new(), &p2wpkh_witness),
+            None
+        );
+        // A witness without a key
+        let signature_only = Witness::from_slice(&[hex::decode(P2WPKH_SIGNATURE).unwrap()]);
+        assert_eq!(
+            witness_public_key(ScriptType::P2wpkh, Script::new(), &signature_only),
+            None
+        );
+    }
+}
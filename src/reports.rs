@@ -0,0 +1,185 @@
+use anyhow::Result;
+use bitcoin::{Amount, Denomination};
+use clap::Subcommand;
+use diesel::PgConnection;
+
+pub mod clusters;
+pub mod coinjoins;
+pub mod exposure;
+pub mod inscriptions;
+pub mod miners;
+pub mod reuse;
+pub mod signatures;
+pub mod templates;
+pub mod utxo_age;
+
+/// Number of blocks between subsidy halvings, used to group heights into eras
+pub const HALVING_INTERVAL: i32 = 210_000;
+
+/// Reports that can be printed from the analytics database
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Unspent value held by outputs whose public key is known (quantum-vulnerable supply)
+    Exposure {
+        /// Height to compute the report at (defaults to the last processed block)
+        #[arg(long)]
+        height: Option<u32>,
+    },
+    /// Quantum-vulnerable supply by script type over a range of heights, as CSV
+    ExposureSeries {
+        /// First height of the series
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the series (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+        /// Number of blocks between points
+        #[arg(long, default_value_t = 1_000)]
+        step: u32,
+    },
+    /// Addresses by reuse pattern, and value received after public key exposure
+    Reuse,
+    /// Address reuse rate by script type over buckets of blocks, as CSV
+    ReuseSeries {
+        /// First height of the series
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the series (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+        /// Number of blocks per bucket
+        #[arg(long, default_value_t = 1_000)]
+        bucket: u32,
+    },
+    /// Address clusters (common-input-ownership) with the largest balances, and their exposed public keys
+    Clusters {
+        /// Number of clusters to list
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// CoinJoin transactions by detected type, with their volume and anonymity sets
+    Coinjoins {
+        /// First height of the range
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the range (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+    /// Inscriptions revealed in Taproot script-path spends, and their share of block space and P2TR usage
+    Inscriptions {
+        /// First height of the range
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the range (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+        /// Number of content types to list
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Blocks per miner or pool, attributed by coinbase tags and payout addresses
+    Miners {
+        /// First height of the range
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the range (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+    /// Public keys that signed more than once with the same nonce (repeated R value)
+    NonceReuse,
+    /// Unspent value by age band (HODL waves) at a height
+    UtxoAge {
+        /// Height to compute the distribution at (defaults to the last processed block)
+        #[arg(long)]
+        height: Option<u32>,
+    },
+    /// Stored UTXO age snapshots over a range of heights, as CSV
+    UtxoAgeSeries {
+        /// First height of the series
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        /// Last height of the series (defaults to the last processed block)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+    /// Most used templates of non-standard and unknown scripts
+    Templates {
+        /// Only include templates first seen at or after this height
+        #[arg(long, default_value_t = 0)]
+        since: u32,
+        /// Number of templates to list
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+}
+
+impl ReportCommand {
+    /// Runs the report, printing its output to stdout
+    pub fn run(self, conn: &mut PgConnection) -> Result<()> {
+        match self {
+            ReportCommand::Exposure { height } => {
+                let height = resolve_height(conn, height)?;
+                exposure::print_exposure_report(conn, height)
+            }
+            ReportCommand::ExposureSeries { from, to, step } => {
+                let to = resolve_height(conn, to)?;
+                exposure::print_exposure_series(conn, from, to, step)
+            }
+            ReportCommand::Reuse => reuse::print_reuse_report(conn),
+            ReportCommand::ReuseSeries { from, to, bucket } => {
+                let to = resolve_height(conn, to)?;
+                reuse::print_reuse_series(conn, from, to, bucket)
+            }
+            ReportCommand::Clusters { limit } => clusters::print_clusters_report(conn, limit),
+            ReportCommand::Coinjoins { from, to } => {
+                let to = resolve_height(conn, to)?;
+                coinjoins::print_coinjoins_report(conn, from, to)
+            }
+            ReportCommand::Inscriptions { from, to, limit } => {
+                let to = resolve_height(conn, to)?;
+                inscriptions::print_inscriptions_report(conn, from, to, limit)
+            }
+            ReportCommand::Miners { from, to } => {
+                let to = resolve_height(conn, to)?;
+                miners::print_miners_report(conn, from, to)
+            }
+            ReportCommand::NonceReuse => signatures::print_nonce_reuse_report(conn),
+            ReportCommand::Templates { since, limit } => {
+                templates::print_templates_report(conn, since, limit)
+            }
+            ReportCommand::UtxoAge { height } => {
+                let height = resolve_height(conn, height)?;
+                utxo_age::print_utxo_age_report(conn, height)
+            }
+            ReportCommand::UtxoAgeSeries { from, to } => {
+                let to = resolve_height(conn, to)?;
+                utxo_age::print_utxo_age_series(conn, from, to)
+            }
+        }
+    }
+}
+
+/// Uses the requested height, or falls back to the last processed block height
+pub fn resolve_height(conn: &mut PgConnection, height: Option<u32>) -> Result<u32> {
+    match height {
+        Some(h) => Ok(h),
+        None => crate::db::get_last_processed_height(conn)?
+            .ok_or_else(|| anyhow::anyhow!("No blocks have been processed yet")),
+    }
+}
+
+/// Formats a satoshi value as BTC with full precision
+pub fn format_btc(satoshis: i64) -> String {
+    let amount = Amount::from_sat(satoshis.unsigned_abs());
+    let sign = if satoshis < 0 { "-" } else { "" };
+    format!("{}{} BTC", sign, amount.display_in(Denomination::Bitcoin))
+}
+
+/// Human readable label for a halving era
+pub fn era_label(era: i32) -> String {
+    let start = era * HALVING_INTERVAL;
+    let end = start + HALVING_INTERVAL - 1;
+    format!("era {} ({}-{})", era, start, end)
+}
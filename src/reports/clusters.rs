@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer};
+
+use super::format_btc;
+
+/// Number of clusters and the addresses in them
+const CLUSTER_SUMMARY_QUERY: &str = "
+    SELECT
+        COUNT(*)::BIGINT AS cluster_count,
+        COALESCE(SUM(address_count), 0)::BIGINT AS clustered_address_count,
+        COALESCE(MAX(address_count), 0)::BIGINT AS largest_cluster_size,
+        (SELECT COUNT(*) FROM addresses)::BIGINT AS address_count
+    FROM clusters";
+
+/// The clusters holding the most unspent value, with the share of their addresses (and value)
+/// whose public key is exposed. Taproot addresses always expose their output key.
+const TOP_CLUSTERS_QUERY: &str = "
+    WITH cluster_addresses AS (
+        SELECT
+            cluster_id,
+            COUNT(*) FILTER (WHERE script_type = 'p2tr' OR is_public_key_exposed)::BIGINT AS exposed_address_count
+        FROM addresses
+        WHERE cluster_id IS NOT NULL
+        GROUP BY cluster_id
+    ),
+    cluster_balances AS (
+        SELECT
+            a.cluster_id,
+            SUM(o.value_satoshis)::BIGINT AS balance_satoshis,
+            COALESCE(SUM(o.value_satoshis) FILTER (WHERE a.script_type = 'p2tr' OR a.is_public_key_exposed), 0)::BIGINT AS exposed_balance_satoshis
+        FROM addresses a
+        JOIN address_outputs o ON o.address_id = a.address_id
+        WHERE a.cluster_id IS NOT NULL
+          AND o.is_spent = FALSE
+          AND o.is_unspendable = FALSE
+        GROUP BY a.cluster_id
+    )
+    SELECT
+        c.cluster_id,
+        c.address_count,
+        c.first_block_height,
+        c.last_merged_block_height,
+        ca.exposed_address_count,
+        COALESCE(cb.balance_satoshis, 0)::BIGINT AS balance_satoshis,
+        COALESCE(cb.exposed_balance_satoshis, 0)::BIGINT AS exposed_balance_satoshis
+    FROM clusters c
+    JOIN cluster_addresses ca ON ca.cluster_id = c.cluster_id
+    LEFT JOIN cluster_balances cb ON cb.cluster_id = c.cluster_id
+    ORDER BY balance_satoshis DESC, c.address_count DESC, c.cluster_id
+    LIMIT $1";
+
+/// Totals over all clusters
+#[derive(QueryableByName, Debug)]
+pub struct ClusterSummaryRow {
+    #[diesel(sql_type = BigInt)]
+    pub cluster_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub clustered_address_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub largest_cluster_size: i64,
+    #[diesel(sql_type = BigInt)]
+    pub address_count: i64, // Every address, clustered or not
+}
+
+/// Address count, balance and public key exposure of one cluster
+#[derive(QueryableByName, Debug)]
+pub struct ClusterRow {
+    #[diesel(sql_type = BigInt)]
+    pub cluster_id: i64,
+    #[diesel(sql_type = Integer)]
+    pub address_count: i32,
+    #[diesel(sql_type = Integer)]
+    pub first_block_height: i32,
+    #[diesel(sql_type = Integer)]
+    pub last_merged_block_height: i32,
+    #[diesel(sql_type = BigInt)]
+    pub exposed_address_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub balance_satoshis: i64,
+    #[diesel(sql_type = BigInt)]
+    pub exposed_balance_satoshis: i64,
+}
+
+/// Loads the totals over all clusters
+pub fn cluster_summary(conn: &mut PgConnection) -> Result<ClusterSummaryRow> {
+    // DB QUERY!
+    sql_query(CLUSTER_SUMMARY_QUERY)
+        .get_result::<ClusterSummaryRow>(conn)
+        .context("Failed to query cluster summary")
+}
+
+/// Loads the `limit` clusters with the largest balance
+pub fn top_clusters(conn: &mut PgConnection, limit: u32) -> Result<Vec<ClusterRow>> {
+    // DB QUERY!
+    sql_query(TOP_CLUSTERS_QUERY)
+        .bind::<BigInt, _>(limit as i64)
+        .load::<ClusterRow>(conn)
+        .context("Failed to query clusters")
+}
+
+/// Prints the address clusters with the largest balances
+pub fn print_clusters_report(conn: &mut PgConnection, limit: u32) -> Result<()> {
+    let summary = cluster_summary(conn)?;
+    let rows = top_clusters(conn, limit)?;
+
+    println!(
+        "{} clusters covering {} of {} addresses (largest: {} addresses)",
+        summary.cluster_count,
+        summary.clustered_address_count,
+        summary.address_count,
+        summary.largest_cluster_size
+    );
+
+    for row in &rows {
+        println!();
+        println!(
+            "  cluster {} ({} addresses, heights {}-{})",
+            row.cluster_id, row.address_count, row.first_block_height, row.last_merged_block_height
+        );
+        println!(
+            "    balance: {}, exposed: {} ({:.2}%)",
+            format_btc(row.balance_satoshis),
+            format_btc(row.exposed_balance_satoshis),
+            ratio(row.exposed_balance_satoshis, row.balance_satoshis)
+        );
+        println!(
+            "    addresses with exposed public keys: {} ({:.2}%)",
+            row.exposed_address_count,
+            ratio(row.exposed_address_count, row.address_count as i64)
+        );
+    }
+
+    Ok(())
+}
+
+/// A part of a total as a percentage, zero for an empty total
+fn ratio(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    part as f64 * 100.0 / total as f64
+}
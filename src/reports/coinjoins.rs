@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Double, Integer, Text};
+
+use super::format_btc;
+
+/// CoinJoins per detected type over a range of heights
+const COINJOIN_TYPES_QUERY: &str = "
+    SELECT
+        coinjoin_type,
+        COUNT(*)::BIGINT AS coinjoin_count,
+        SUM(mixed_value_satoshis)::BIGINT AS mixed_value_satoshis,
+        AVG(anonymity_set)::DOUBLE PRECISION AS average_anonymity_set,
+        MAX(anonymity_set) AS largest_anonymity_set,
+        COALESCE(SUM(coordinator_fee_satoshis), 0)::BIGINT AS coordinator_fee_satoshis
+    FROM coinjoin_transactions
+    WHERE block_height BETWEEN $1 AND $2
+    GROUP BY coinjoin_type
+    ORDER BY coinjoin_count DESC, coinjoin_type";
+
+/// Transactions over a range of heights, to put the CoinJoin count in proportion
+const TRANSACTION_COUNT_QUERY: &str = "
+    SELECT COUNT(*)::BIGINT AS transaction_count
+    FROM transactions
+    WHERE block_height BETWEEN $1 AND $2";
+
+/// CoinJoins of one type
+#[derive(QueryableByName, Debug)]
+pub struct CoinJoinTypeRow {
+    #[diesel(sql_type = Text)]
+    pub coinjoin_type: String,
+    #[diesel(sql_type = BigInt)]
+    pub coinjoin_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub mixed_value_satoshis: i64,
+    #[diesel(sql_type = Double)]
+    pub average_anonymity_set: f64,
+    #[diesel(sql_type = Integer)]
+    pub largest_anonymity_set: i32,
+    #[diesel(sql_type = BigInt)]
+    pub coordinator_fee_satoshis: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct TransactionCountRow {
+    #[diesel(sql_type = BigInt)]
+    transaction_count: i64,
+}
+
+/// Loads the CoinJoins per type between `from` and `to` (inclusive)
+pub fn coinjoin_types(conn: &mut PgConnection, from: u32, to: u32) -> Result<Vec<CoinJoinTypeRow>> {
+    // DB QUERY!
+    sql_query(COINJOIN_TYPES_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .load::<CoinJoinTypeRow>(conn)
+        .context("Failed to query CoinJoin types")
+}
+
+/// Prints the CoinJoin volume by type, and the share of transactions that are CoinJoins
+pub fn print_coinjoins_report(conn: &mut PgConnection, from: u32, to: u32) -> Result<()> {
+    let rows = coinjoin_types(conn, from, to)?;
+
+    // DB QUERY!
+    let transactions = sql_query(TRANSACTION_COUNT_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .get_result::<TransactionCountRow>(conn)
+        .context("Failed to query transaction count")?;
+
+    let coinjoin_count: i64 = rows.iter().map(|r| r.coinjoin_count).sum();
+    let mixed_value: i64 = rows.iter().map(|r| r.mixed_value_satoshis).sum();
+    let share = if transactions.transaction_count > 0 {
+        coinjoin_count as f64 * 100.0 / transactions.transaction_count as f64
+    } else {
+        0.0
+    };
+
+    println!("CoinJoins in blocks {}-{}", from, to);
+    println!(
+        "  {} of {} transactions ({:.2}%), mixing {}",
+        coinjoin_count,
+        transactions.transaction_count,
+        share,
+        format_btc(mixed_value)
+    );
+
+    for row in &rows {
+        println!();
+        println!(
+            "  {} ({} transactions)",
+            row.coinjoin_type, row.coinjoin_count
+        );
+        println!("    mixed: {}", format_btc(row.mixed_value_satoshis));
+        println!(
+            "    anonymity set: {:.1} average, {} largest",
+            row.average_anonymity_set, row.largest_anonymity_set
+        );
+        if row.coordinator_fee_satoshis > 0 {
+            println!(
+                "    coordinator fees: {}",
+                format_btc(row.coordinator_fee_satoshis)
+            );
+        }
+    }
+
+    Ok(())
+}
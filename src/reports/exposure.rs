@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Varchar};
+use std::collections::BTreeMap;
+
+use crate::script_type::ScriptType;
+
+use super::{era_label, format_btc, HALVING_INTERVAL};
+
+/// Unspent outputs whose public key is known at a given height, grouped by the
+/// script type of the address and the halving era in which its key was first exposed.
+///
+/// P2TR outputs expose a tweaked public key in the scriptPubKey itself, so they are exposed
+/// from the block the address was first seen in. P2PK and P2MS addresses are flagged as exposed
+/// from that block when created, provided their keys are valid, and other addresses from the
+/// height at which a spend first revealed a valid public key.
+const EXPOSED_SUPPLY_QUERY: &str = "
+    WITH exposed AS (
+        SELECT
+            a.address_id,
+            a.script_type,
+            CASE
+                WHEN a.script_type = 'p2tr' THEN a.first_seen_block_height
+                ELSE a.public_key_exposed_height
+            END AS exposed_height
+        FROM addresses a
+        WHERE a.script_type = 'p2tr' OR a.is_public_key_exposed = TRUE
+    )
+    SELECT
+        e.script_type,
+        (e.exposed_height / $2)::INTEGER AS exposure_era,
+        COUNT(*)::BIGINT AS output_count,
+        COALESCE(SUM(o.value_satoshis), 0)::BIGINT AS value_satoshis
+    FROM exposed e
+    JOIN address_outputs o ON o.address_id = e.address_id
+    LEFT JOIN address_inputs i ON i.input_id = o.spending_input_id
+    WHERE e.exposed_height <= $1
+      AND o.block_height <= $1
+      AND (i.input_id IS NULL OR i.block_height > $1)
+      AND o.is_unspendable = FALSE
+    GROUP BY e.script_type, exposure_era
+    ORDER BY e.script_type, exposure_era";
+
+/// Unspent outputs at height H of P2PK and P2MS addresses whose embedded "public keys" are
+/// all invalid curve points (typically data embedded in fake keys), which are not exposed
+const INVALID_KEY_SUPPLY_QUERY: &str = "
+    SELECT
+        a.script_type,
+        COUNT(*)::BIGINT AS output_count,
+        COALESCE(SUM(o.value_satoshis), 0)::BIGINT AS value_satoshis
+    FROM addresses a
+    JOIN address_outputs o ON o.address_id = a.address_id
+    LEFT JOIN address_inputs i ON i.input_id = o.spending_input_id
+    WHERE a.script_type IN ('p2pk', 'p2ms')
+      AND a.is_public_key_exposed = FALSE
+      AND o.block_height <= $1
+      AND (i.input_id IS NULL OR i.block_height > $1)
+      AND o.is_unspendable = FALSE
+    GROUP BY a.script_type
+    ORDER BY a.script_type";
+
+/// Number of spends up to height H that pushed an invalid public key in their scriptSig
+const INVALID_REVEALED_KEY_QUERY: &str = "
+    SELECT COUNT(*)::BIGINT AS input_count
+    FROM address_inputs
+    WHERE public_key_revealed_format = 'invalid' AND block_height <= $1";
+
+/// Unspent exposed value for one script type and exposure era
+#[derive(QueryableByName, Debug)]
+pub struct ExposedSupplyRow {
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = Integer)]
+    pub exposure_era: i32,
+    #[diesel(sql_type = BigInt)]
+    pub output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub value_satoshis: i64,
+}
+
+/// Unspent value behind invalid embedded public keys for one script type
+#[derive(QueryableByName, Debug)]
+pub struct InvalidKeySupplyRow {
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = BigInt)]
+    pub output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub value_satoshis: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct InputCountRow {
+    #[diesel(sql_type = BigInt)]
+    input_count: i64,
+}
+
+/// Computes the quantum-vulnerable supply at the given height
+pub fn exposed_supply_at(conn: &mut PgConnection, height: u32) -> Result<Vec<ExposedSupplyRow>> {
+    // DB QUERY!
+    sql_query(EXPOSED_SUPPLY_QUERY)
+        .bind::<Integer, _>(height as i32)
+        .bind::<Integer, _>(HALVING_INTERVAL)
+        .load::<ExposedSupplyRow>(conn)
+        .with_context(|| format!("Failed to query exposed supply at height {}", height))
+}
+
+/// Computes the unspent value of P2PK and P2MS outputs with only invalid keys at the given height
+pub fn invalid_key_supply_at(
+    conn: &mut PgConnection,
+    height: u32,
+) -> Result<Vec<InvalidKeySupplyRow>> {
+    // DB QUERY!
+    sql_query(INVALID_KEY_SUPPLY_QUERY)
+        .bind::<Integer, _>(height as i32)
+        .load::<InvalidKeySupplyRow>(conn)
+        .with_context(|| format!("Failed to query invalid key supply at height {}", height))
+}
+
+/// Counts the spends that pushed an invalid public key up to the given height
+pub fn invalid_revealed_key_count(conn: &mut PgConnection, height: u32) -> Result<i64> {
+    // DB QUERY!
+    let row = sql_query(INVALID_REVEALED_KEY_QUERY)
+        .bind::<Integer, _>(height as i32)
+        .get_result::<InputCountRow>(conn)
+        .with_context(|| format!("Failed to count invalid revealed keys at height {}", height))?;
+    Ok(row.input_count)
+}
+
+/// Sums rows by a key, returning (output_count, value_satoshis) per key
+fn totals_by<K: Ord>(
+    rows: &[ExposedSupplyRow],
+    key: impl Fn(&ExposedSupplyRow) -> K,
+) -> BTreeMap<K, (i64, i64)> {
+    let mut totals = BTreeMap::new();
+    for row in rows {
+        let entry = totals.entry(key(row)).or_insert((0, 0));
+        entry.0 += row.output_count;
+        entry.1 += row.value_satoshis;
+    }
+    totals
+}
+
+/// Prints the quantum-vulnerable supply at a height, by script type and by exposure era
+pub fn print_exposure_report(conn: &mut PgConnection, height: u32) -> Result<()> {
+    let rows = exposed_supply_at(conn, height)?;
+
+    let total_outputs: i64 = rows.iter().map(|r| r.output_count).sum();
+    let total_value: i64 = rows.iter().map(|r| r.value_satoshis).sum();
+
+    println!("Quantum-vulnerable supply at height {}", height);
+    println!(
+        "Total: {} in {} unspent outputs",
+        format_btc(total_value),
+        total_outputs
+    );
+
+    println!();
+    println!("By script type:");
+    for (script_type, (count, value)) in totals_by(&rows, |r| r.script_type) {
+        println!(
+            "  {:<14} {:>12} outputs  {}",
+            script_type,
+            count,
+            format_btc(value)
+        );
+    }
+
+    println!();
+    println!("By era of first exposure:");
+    for (era, (count, value)) in totals_by(&rows, |r| r.exposure_era) {
+        println!(
+            "  {:<24} {:>12} outputs  {}",
+            era_label(era),
+            count,
+            format_btc(value)
+        );
+    }
+
+    println!();
+    println!("Invalid public keys (not points on the curve, excluded above):");
+    for row in invalid_key_supply_at(conn, height)? {
+        println!(
+            "  {:<14} {:>12} outputs  {}",
+            row.script_type,
+            row.output_count,
+            format_btc(row.value_satoshis)
+        );
+    }
+    println!(
+        "  {:<14} {:>12} spends",
+        "revealed",
+        invalid_revealed_key_count(conn, height)?
+    );
+
+    Ok(())
+}
+
+/// Prints the quantum-vulnerable supply by script type at every `step` blocks between
+/// `from` and `to` (inclusive) as CSV, suitable for plotting
+pub fn print_exposure_series(conn: &mut PgConnection, from: u32, to: u32, step: u32) -> Result<()> {
+    if step == 0 {
+        anyhow::bail!("Series step must be greater than zero");
+    }
+
+    println!("height,script_type,output_count,value_satoshis");
+
+    let mut height = from;
+    while height <= to {
+        let rows = exposed_supply_at(conn, height)?;
+        for (script_type, (count, value)) in totals_by(&rows, |r| r.script_type) {
+            println!("{},{},{},{}", height, script_type, count, value);
+        }
+
+        height = match height.checked_add(step) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    Ok(())
+}
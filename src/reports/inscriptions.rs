@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Text};
+
+/// Inscriptions over a range of heights, against the block space and Taproot usage they sit in.
+/// Reveal transactions spend a P2TR commit output by script path and usually create a P2TR
+/// output for the inscription, so both sides are counted.
+const INSCRIPTION_SUMMARY_QUERY: &str = "
+    WITH range_inscriptions AS (
+        SELECT * FROM inscriptions WHERE block_height BETWEEN $1 AND $2
+    ),
+    reveals AS (
+        SELECT DISTINCT transaction_id, block_height FROM range_inscriptions
+    )
+    SELECT
+        (SELECT COUNT(*) FROM range_inscriptions)::BIGINT AS inscription_count,
+        (SELECT COUNT(*) FROM reveals)::BIGINT AS reveal_transaction_count,
+        (SELECT COALESCE(SUM(body_size), 0) FROM range_inscriptions)::BIGINT AS body_bytes,
+        (SELECT COALESCE(SUM(envelope_size), 0) FROM range_inscriptions)::BIGINT AS envelope_bytes,
+        (SELECT COALESCE(SUM(t.weight), 0)
+            FROM transactions t
+            JOIN reveals r ON r.transaction_id = t.transaction_id AND r.block_height = t.block_height
+        )::BIGINT AS reveal_weight,
+        (SELECT COALESCE(SUM(weight), 0)
+            FROM transactions
+            WHERE block_height BETWEEN $1 AND $2
+        )::BIGINT AS total_weight,
+        (SELECT COUNT(DISTINCT input_id) FROM range_inscriptions)::BIGINT AS commit_spend_count,
+        (SELECT COUNT(*)
+            FROM address_inputs i
+            JOIN addresses a ON a.address_id = i.address_id
+            WHERE a.script_type = 'p2tr' AND i.block_height BETWEEN $1 AND $2
+        )::BIGINT AS p2tr_spend_count,
+        (SELECT COUNT(*)
+            FROM address_outputs o
+            JOIN addresses a ON a.address_id = o.address_id
+            JOIN reveals r ON r.transaction_id = o.transaction_id AND r.block_height = o.block_height
+            WHERE a.script_type = 'p2tr'
+        )::BIGINT AS reveal_p2tr_output_count,
+        (SELECT COUNT(*)
+            FROM address_outputs o
+            JOIN addresses a ON a.address_id = o.address_id
+            WHERE a.script_type = 'p2tr' AND o.block_height BETWEEN $1 AND $2
+        )::BIGINT AS p2tr_output_count";
+
+/// Most common content types of the inscriptions over a range of heights
+const CONTENT_TYPES_QUERY: &str = "
+    SELECT
+        COALESCE(content_type, '(none)') AS content_type,
+        COUNT(*)::BIGINT AS inscription_count,
+        SUM(body_size)::BIGINT AS body_bytes
+    FROM inscriptions
+    WHERE block_height BETWEEN $1 AND $2
+    GROUP BY 1
+    ORDER BY inscription_count DESC, content_type
+    LIMIT $3";
+
+/// Inscription totals over a range of heights
+#[derive(QueryableByName, Debug)]
+pub struct InscriptionSummaryRow {
+    #[diesel(sql_type = BigInt)]
+    pub inscription_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub reveal_transaction_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub body_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub envelope_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub reveal_weight: i64, // Weight units of the reveal transactions
+    #[diesel(sql_type = BigInt)]
+    pub total_weight: i64, // Weight units of every transaction with a recorded weight
+    #[diesel(sql_type = BigInt)]
+    pub commit_spend_count: i64, // P2TR outputs spent by revealing inscriptions
+    #[diesel(sql_type = BigInt)]
+    pub p2tr_spend_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub reveal_p2tr_output_count: i64, // P2TR outputs created by reveal transactions
+    #[diesel(sql_type = BigInt)]
+    pub p2tr_output_count: i64,
+}
+
+/// Inscriptions of one content type
+#[derive(QueryableByName, Debug)]
+pub struct ContentTypeRow {
+    #[diesel(sql_type = Text)]
+    pub content_type: String,
+    #[diesel(sql_type = BigInt)]
+    pub inscription_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub body_bytes: i64,
+}
+
+/// Loads the inscription totals between `from` and `to` (inclusive)
+pub fn inscription_summary(
+    conn: &mut PgConnection,
+    from: u32,
+    to: u32,
+) -> Result<InscriptionSummaryRow> {
+    // DB QUERY!
+    sql_query(INSCRIPTION_SUMMARY_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .get_result::<InscriptionSummaryRow>(conn)
+        .context("Failed to query inscription summary")
+}
+
+/// Loads the `limit` most common inscription content types between `from` and `to` (inclusive)
+pub fn content_types(
+    conn: &mut PgConnection,
+    from: u32,
+    to: u32,
+    limit: u32,
+) -> Result<Vec<ContentTypeRow>> {
+    // DB QUERY!
+    sql_query(CONTENT_TYPES_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .bind::<BigInt, _>(limit as i64)
+        .load::<ContentTypeRow>(conn)
+        .context("Failed to query inscription content types")
+}
+
+/// Prints how much block space and Taproot usage is inscription related
+pub fn print_inscriptions_report(
+    conn: &mut PgConnection,
+    from: u32,
+    to: u32,
+    limit: u32,
+) -> Result<()> {
+    let summary = inscription_summary(conn, from, to)?;
+    let types = content_types(conn, from, to, limit)?;
+
+    println!("Inscriptions in blocks {}-{}", from, to);
+    println!();
+    println!(
+        "  {} inscriptions in {} reveal transactions",
+        summary.inscription_count, summary.reveal_transaction_count
+    );
+    println!(
+        "  content: {} bytes, envelopes: {} bytes",
+        summary.body_bytes, summary.envelope_bytes
+    );
+    println!(
+        "  reveal transaction weight: {} of {} WU ({})",
+        summary.reveal_weight,
+        summary.total_weight,
+        percentage(summary.reveal_weight, summary.total_weight)
+    );
+    println!(
+        "  P2TR spends revealing inscriptions: {} of {} ({})",
+        summary.commit_spend_count,
+        summary.p2tr_spend_count,
+        percentage(summary.commit_spend_count, summary.p2tr_spend_count)
+    );
+    println!(
+        "  P2TR outputs created by reveal transactions: {} of {} ({})",
+        summary.reveal_p2tr_output_count,
+        summary.p2tr_output_count,
+        percentage(summary.reveal_p2tr_output_count, summary.p2tr_output_count)
+    );
+
+    println!();
+    println!("Content types:");
+    for row in &types {
+        println!(
+            "  {:<40} {:>10} inscriptions {:>14} bytes",
+            row.content_type, row.inscription_count, row.body_bytes
+        );
+    }
+
+    Ok(())
+}
+
+/// Formats a part of a total as a percentage
+fn percentage(part: i64, total: i64) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.2}%", part as f64 * 100.0 / total as f64)
+}
@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+
+use super::format_btc;
+
+/// Blocks per attributed miner over a range of heights, with the reward claimed
+/// beyond the subsidy (fees) and any subsidy left unclaimed
+const MINER_SHARE_QUERY: &str = "
+    SELECT
+        m.miner_name,
+        COUNT(*)::BIGINT AS block_count,
+        SUM(GREATEST(c.output_value_satoshis - c.subsidy_satoshis, 0))::BIGINT AS fees_claimed_satoshis,
+        SUM(GREATEST(c.subsidy_satoshis - c.output_value_satoshis, 0))::BIGINT AS unclaimed_satoshis,
+        MIN(c.block_height) AS first_block_height,
+        MAX(c.block_height) AS last_block_height
+    FROM coinbase_transactions c
+    LEFT JOIN block_miners m ON m.block_height = c.block_height
+    WHERE c.block_height BETWEEN $1 AND $2
+    GROUP BY m.miner_name
+    ORDER BY block_count DESC, m.miner_name";
+
+/// Blocks attributed to one miner, or the unattributed blocks when `miner_name` is None
+#[derive(QueryableByName, Debug)]
+pub struct MinerShareRow {
+    #[diesel(sql_type = Nullable<Text>)]
+    pub miner_name: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub block_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub fees_claimed_satoshis: i64,
+    #[diesel(sql_type = BigInt)]
+    pub unclaimed_satoshis: i64,
+    #[diesel(sql_type = Integer)]
+    pub first_block_height: i32,
+    #[diesel(sql_type = Integer)]
+    pub last_block_height: i32,
+}
+
+/// Loads the number of blocks mined by each miner between `from` and `to` (inclusive)
+pub fn miner_share(conn: &mut PgConnection, from: u32, to: u32) -> Result<Vec<MinerShareRow>> {
+    // DB QUERY!
+    sql_query(MINER_SHARE_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .load::<MinerShareRow>(conn)
+        .context("Failed to query miner share")
+}
+
+/// Prints the share of blocks mined by each configured miner
+pub fn print_miners_report(conn: &mut PgConnection, from: u32, to: u32) -> Result<()> {
+    let rows = miner_share(conn, from, to)?;
+    let total_blocks: i64 = rows.iter().map(|r| r.block_count).sum();
+
+    println!("Miners of blocks {}-{} ({} blocks)", from, to, total_blocks);
+    for row in &rows {
+        let share = if total_blocks > 0 {
+            row.block_count as f64 * 100.0 / total_blocks as f64
+        } else {
+            0.0
+        };
+
+        println!();
+        println!(
+            "  {} {} blocks ({:.2}%), heights {}-{}",
+            row.miner_name.as_deref().unwrap_or("unattributed"),
+            row.block_count,
+            share,
+            row.first_block_height,
+            row.last_block_height
+        );
+        println!(
+            "    fees claimed: {}",
+            format_btc(row.fees_claimed_satoshis)
+        );
+        if row.unclaimed_satoshis > 0 {
+            println!(
+                "    subsidy unclaimed: {}",
+                format_btc(row.unclaimed_satoshis)
+            );
+        }
+    }
+
+    Ok(())
+}
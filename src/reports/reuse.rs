@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Varchar};
+
+use crate::script_type::ScriptType;
+
+use super::format_btc;
+
+/// Classifies every address by its reuse pattern:
+/// - received-after-exposure: received an output after its public key was revealed
+/// - single-use: received at most once
+/// - receive-many-spend-once: received more than once, spent in at most one transaction
+/// - continuously-reused: received more than once and spent in several transactions
+///
+/// Spending transactions are counted distinctly (a sweep of several outputs is one spend),
+/// using the (address_id, transaction_id) index, and only for addresses that received more than once.
+const REUSE_CLASS_QUERY: &str = "
+    SELECT
+        a.script_type,
+        CASE
+            WHEN EXISTS (
+                SELECT 1 FROM address_outputs o
+                WHERE o.address_id = a.address_id AND o.received_after_exposure = TRUE
+            ) THEN 'received-after-exposure'
+            WHEN a.total_receive_count <= 1 THEN 'single-use'
+            WHEN spends.spend_tx_count <= 1 THEN 'receive-many-spend-once'
+            ELSE 'continuously-reused'
+        END AS reuse_class,
+        COUNT(*)::BIGINT AS address_count,
+        COALESCE(SUM(a.total_receive_count), 0)::BIGINT AS receive_count
+    FROM addresses a
+    LEFT JOIN LATERAL (
+        SELECT COUNT(DISTINCT i.transaction_id) AS spend_tx_count
+        FROM address_inputs i
+        WHERE i.address_id = a.address_id AND a.total_receive_count > 1
+    ) spends ON TRUE
+    GROUP BY a.script_type, reuse_class
+    ORDER BY a.script_type, reuse_class";
+
+/// Value received by addresses after their public key was already revealed, by script type
+const POST_EXPOSURE_QUERY: &str = "
+    SELECT
+        a.script_type,
+        COUNT(*)::BIGINT AS output_count,
+        COALESCE(SUM(o.value_satoshis), 0)::BIGINT AS value_satoshis,
+        COALESCE(SUM(o.value_satoshis) FILTER (WHERE o.is_spent = FALSE AND o.is_unspendable = FALSE), 0)::BIGINT AS unspent_value_satoshis
+    FROM address_outputs o
+    JOIN addresses a ON a.address_id = o.address_id
+    WHERE o.received_after_exposure = TRUE
+    GROUP BY a.script_type
+    ORDER BY a.script_type";
+
+/// Reuse rate per script type over buckets of blocks, from the per-block statistics
+const REUSE_SERIES_QUERY: &str = "
+    SELECT
+        ((block_height / $3) * $3)::INTEGER AS bucket_start_height,
+        script_type,
+        COALESCE(SUM(output_count), 0)::BIGINT AS output_count,
+        COALESCE(SUM(reused_output_count), 0)::BIGINT AS reused_output_count,
+        COALESCE(SUM(post_exposure_output_count), 0)::BIGINT AS post_exposure_output_count,
+        COALESCE(SUM(post_exposure_value_satoshis), 0)::BIGINT AS post_exposure_value_satoshis
+    FROM block_script_type_stats
+    WHERE block_height BETWEEN $1 AND $2
+    GROUP BY bucket_start_height, script_type
+    ORDER BY bucket_start_height, script_type";
+
+/// Number of addresses in one reuse class for a script type
+#[derive(QueryableByName, Debug)]
+pub struct ReuseClassRow {
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = Varchar)]
+    pub reuse_class: String,
+    #[diesel(sql_type = BigInt)]
+    pub address_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub receive_count: i64,
+}
+
+/// Outputs received after exposure for a script type
+#[derive(QueryableByName, Debug)]
+pub struct PostExposureRow {
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = BigInt)]
+    pub output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub value_satoshis: i64,
+    #[diesel(sql_type = BigInt)]
+    pub unspent_value_satoshis: i64,
+}
+
+/// Reuse counts for a script type over a bucket of blocks
+#[derive(QueryableByName, Debug)]
+pub struct ReuseSeriesRow {
+    #[diesel(sql_type = Integer)]
+    pub bucket_start_height: i32,
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = BigInt)]
+    pub output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub reused_output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub post_exposure_output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub post_exposure_value_satoshis: i64,
+}
+
+/// Counts addresses by script type and reuse class
+pub fn reuse_classes(conn: &mut PgConnection) -> Result<Vec<ReuseClassRow>> {
+    // DB QUERY!
+    sql_query(REUSE_CLASS_QUERY)
+        .load::<ReuseClassRow>(conn)
+        .context("Failed to query address reuse classes")
+}
+
+/// Totals the outputs received after exposure by script type
+pub fn post_exposure_receipts(conn: &mut PgConnection) -> Result<Vec<PostExposureRow>> {
+    // DB QUERY!
+    sql_query(POST_EXPOSURE_QUERY)
+        .load::<PostExposureRow>(conn)
+        .context("Failed to query outputs received after exposure")
+}
+
+/// Loads reuse counts per script type in buckets of `bucket_size` blocks
+pub fn reuse_series(
+    conn: &mut PgConnection,
+    from: u32,
+    to: u32,
+    bucket_size: u32,
+) -> Result<Vec<ReuseSeriesRow>> {
+    // DB QUERY!
+    sql_query(REUSE_SERIES_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .bind::<Integer, _>(bucket_size as i32)
+        .load::<ReuseSeriesRow>(conn)
+        .context("Failed to query address reuse series")
+}
+
+/// Prints the address reuse classification and the value received after exposure
+pub fn print_reuse_report(conn: &mut PgConnection) -> Result<()> {
+    let classes = reuse_classes(conn)?;
+
+    println!("Address reuse by script type");
+    println!(
+        "  {:<14} {:<26} {:>14} {:>14}",
+        "script_type", "reuse_class", "addresses", "receives"
+    );
+    for row in &classes {
+        println!(
+            "  {:<14} {:<26} {:>14} {:>14}",
+            row.script_type, row.reuse_class, row.address_count, row.receive_count
+        );
+    }
+
+    let receipts = post_exposure_receipts(conn)?;
+
+    println!();
+    println!("Value received after public key exposure");
+    for row in &receipts {
+        println!(
+            "  {:<14} {:>12} outputs  {} ({} unspent)",
+            row.script_type,
+            row.output_count,
+            format_btc(row.value_satoshis),
+            format_btc(row.unspent_value_satoshis)
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the reuse rate per script type over buckets of blocks as CSV
+pub fn print_reuse_series(conn: &mut PgConnection, from: u32, to: u32, bucket: u32) -> Result<()> {
+    if bucket == 0 {
+        anyhow::bail!("Series bucket size must be greater than zero");
+    }
+
+    println!("bucket_start_height,script_type,output_count,reused_output_count,reuse_rate,post_exposure_output_count,post_exposure_value_satoshis");
+    for row in reuse_series(conn, from, to, bucket)? {
+        let reuse_rate = if row.output_count > 0 {
+            row.reused_output_count as f64 / row.output_count as f64
+        } else {
+            0.0
+        };
+        println!(
+            "{},{},{},{},{:.6},{},{}",
+            row.bucket_start_height,
+            row.script_type,
+            row.output_count,
+            row.reused_output_count,
+            reuse_rate,
+            row.post_exposure_output_count,
+            row.post_exposure_value_satoshis
+        );
+    }
+
+    Ok(())
+}
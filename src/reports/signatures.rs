@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+
+/// Signatures that share an R value (and so the same nonce) for the same public key, in different
+/// inputs. Anyone can compute the private key of such a key from the two signatures.
+///
+/// A signature is keyed by the public key paired with it in the input, or else the public key
+/// stored on the spent address (P2PK). Signatures without any known key, such as those of bare
+/// multisig, are grouped by address instead.
+const NONCE_REUSE_QUERY: &str = "
+    WITH repeated AS (
+        SELECT r_value
+        FROM signatures
+        GROUP BY r_value
+        HAVING COUNT(*) > 1
+    ),
+    keyed AS (
+        SELECT
+            s.r_value,
+            s.block_height,
+            i.transaction_id,
+            i.input_index,
+            COALESCE(s.public_key, a.public_key) AS public_key,
+            CASE WHEN COALESCE(s.public_key, a.public_key) IS NULL THEN a.address_id END AS address_id
+        FROM signatures s
+        JOIN repeated r ON r.r_value = s.r_value
+        JOIN address_inputs i ON i.input_id = s.input_id
+        JOIN addresses a ON a.address_id = i.address_id
+    )
+    SELECT
+        encode(public_key, 'hex') AS public_key,
+        address_id,
+        encode(r_value, 'hex') AS r_value,
+        COUNT(*)::BIGINT AS signature_count,
+        MIN(block_height) AS first_block_height,
+        MAX(block_height) AS last_block_height,
+        string_agg(DISTINCT encode(transaction_id, 'hex'), ',') AS txids
+    FROM keyed
+    GROUP BY public_key, address_id, r_value
+    HAVING COUNT(DISTINCT (transaction_id, input_index)) > 1
+    ORDER BY first_block_height, r_value";
+
+/// A public key (or address) that signed more than once with the same R value
+#[derive(QueryableByName, Debug)]
+pub struct NonceReuseRow {
+    #[diesel(sql_type = Nullable<Text>)]
+    pub public_key: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub address_id: Option<i64>,
+    #[diesel(sql_type = Text)]
+    pub r_value: String,
+    #[diesel(sql_type = BigInt)]
+    pub signature_count: i64,
+    #[diesel(sql_type = Integer)]
+    pub first_block_height: i32,
+    #[diesel(sql_type = Integer)]
+    pub last_block_height: i32,
+    #[diesel(sql_type = Text)]
+    pub txids: String, // Comma separated hex txids
+}
+
+/// Finds every public key that reused a nonce across different inputs
+pub fn nonce_reuse(conn: &mut PgConnection) -> Result<Vec<NonceReuseRow>> {
+    // DB QUERY!
+    sql_query(NONCE_REUSE_QUERY)
+        .load::<NonceReuseRow>(conn)
+        .context("Failed to query nonce reuse")
+}
+
+/// Prints the public keys whose signatures reused a nonce
+pub fn print_nonce_reuse_report(conn: &mut PgConnection) -> Result<()> {
+    let rows = nonce_reuse(conn)?;
+
+    println!(
+        "Public keys with repeated signature R values: {}",
+        rows.len()
+    );
+    for row in &rows {
+        let signer = match (&row.public_key, row.address_id) {
+            (Some(key), _) => format!("key {}", key),
+            (None, Some(id)) => format!("address {}", id),
+            (None, None) => "unknown".to_string(),
+        };
+
+        println!();
+        println!(
+            "  {} ({} signatures, heights {}-{})",
+            signer, row.signature_count, row.first_block_height, row.last_block_height
+        );
+        println!("    r = {}", row.r_value);
+        println!("    txids: {}", row.txids);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Text, Varchar};
+
+use crate::script_type::ScriptType;
+
+/// The most used script templates, optionally only those first seen from a given height
+/// so that recently appeared patterns can be spotted
+const TOP_TEMPLATES_QUERY: &str = "
+    SELECT
+        encode(template_hash, 'hex') AS template_hash,
+        template,
+        script_type,
+        occurrence_count,
+        first_seen_block_height,
+        last_seen_block_height,
+        COALESCE((SELECT string_agg(encode(txid, 'hex'), ',') FROM unnest(example_txids) AS txid), '') AS example_txids
+    FROM script_templates
+    WHERE first_seen_block_height >= $1
+    ORDER BY occurrence_count DESC, first_seen_block_height
+    LIMIT $2";
+
+/// Usage of one script template
+#[derive(QueryableByName, Debug)]
+pub struct TemplateRow {
+    #[diesel(sql_type = Text)]
+    pub template_hash: String,
+    #[diesel(sql_type = Text)]
+    pub template: String,
+    #[diesel(sql_type = Varchar)]
+    pub script_type: ScriptType,
+    #[diesel(sql_type = BigInt)]
+    pub occurrence_count: i64,
+    #[diesel(sql_type = Integer)]
+    pub first_seen_block_height: i32,
+    #[diesel(sql_type = Integer)]
+    pub last_seen_block_height: i32,
+    #[diesel(sql_type = Text)]
+    pub example_txids: String, // Comma separated hex txids
+}
+
+/// Loads the `limit` most used templates first seen at or after `since`
+pub fn top_templates(conn: &mut PgConnection, since: u32, limit: u32) -> Result<Vec<TemplateRow>> {
+    // DB QUERY!
+    sql_query(TOP_TEMPLATES_QUERY)
+        .bind::<Integer, _>(since as i32)
+        .bind::<BigInt, _>(limit as i64)
+        .load::<TemplateRow>(conn)
+        .context("Failed to query script templates")
+}
+
+/// Prints the most used non-standard script templates
+pub fn print_templates_report(conn: &mut PgConnection, since: u32, limit: u32) -> Result<()> {
+    let rows = top_templates(conn, since, limit)?;
+
+    println!("Script templates first seen from height {}", since);
+    for row in &rows {
+        println!();
+        println!(
+            "  {} ({}) {} outputs, heights {}-{}",
+            &row.template_hash[..16],
+            row.script_type,
+            row.occurrence_count,
+            row.first_seen_block_height,
+            row.last_seen_block_height
+        );
+        println!("    {}", row.template);
+        println!("    e.g. {}", row.example_txids);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::Integer;
+
+use crate::db;
+use crate::db::models::UtxoAgeSnapshot;
+use crate::utxo_age::{totals_by_age_band, UtxoAgeDay};
+
+use super::format_btc;
+
+/// Unspent outputs at height H by the day they were created, aged at the timestamp of block H.
+/// Rescans the outputs, so it works for any height but is slower than the creation day totals.
+const UTXO_AGE_AT_HEIGHT_QUERY: &str = "
+    WITH at_block AS (
+        SELECT block_timestamp::DATE AS block_date FROM blocks WHERE block_height = $1
+    )
+    SELECT
+        ((SELECT block_date FROM at_block) - b.block_timestamp::DATE)::INTEGER AS age_days,
+        COUNT(*)::BIGINT AS output_count,
+        COALESCE(SUM(o.value_satoshis), 0)::BIGINT AS value_satoshis
+    FROM address_outputs o
+    JOIN blocks b ON b.block_height = o.block_height
+    LEFT JOIN address_inputs i ON i.input_id = o.spending_input_id
+    WHERE o.block_height <= $1
+      AND (i.input_id IS NULL OR i.block_height > $1)
+      AND o.is_unspendable = FALSE
+    GROUP BY b.block_timestamp::DATE";
+
+/// Stored age distribution snapshots over a range of heights
+const SNAPSHOT_SERIES_QUERY: &str = "
+    SELECT block_height, age_band, band_index, output_count, value_satoshis
+    FROM utxo_age_snapshots
+    WHERE block_height BETWEEN $1 AND $2
+    ORDER BY block_height, band_index";
+
+/// Loads the unspent outputs by creation day at `height`, scanning the outputs
+pub fn utxo_age_days_at(conn: &mut PgConnection, height: u32) -> Result<Vec<UtxoAgeDay>> {
+    // DB QUERY!
+    sql_query(UTXO_AGE_AT_HEIGHT_QUERY)
+        .bind::<Integer, _>(height as i32)
+        .load::<UtxoAgeDay>(conn)
+        .context("Failed to query UTXO age")
+}
+
+/// Prints the distribution of unspent value across age bands at a height.
+/// The last processed height is read from the creation day totals kept by the processor,
+/// other heights are computed by scanning the outputs.
+pub fn print_utxo_age_report(conn: &mut PgConnection, height: u32) -> Result<()> {
+    let days = if db::get_last_processed_height(conn)? == Some(height) {
+        db::get_utxo_age_days(conn, height as i32)?
+    } else {
+        utxo_age_days_at(conn, height)?
+    };
+    let bands = totals_by_age_band(&days);
+    let total_value: i64 = bands.iter().map(|b| b.value_satoshis).sum();
+    let total_count: i64 = bands.iter().map(|b| b.output_count).sum();
+
+    println!(
+        "Unspent outputs at height {}: {} outputs, {}",
+        height,
+        total_count,
+        format_btc(total_value)
+    );
+    for band in &bands {
+        let share = if total_value > 0 {
+            band.value_satoshis as f64 * 100.0 / total_value as f64
+        } else {
+            0.0
+        };
+        println!(
+            "  {:<8} {:>12} outputs {:>26} {:>7.2}%",
+            band.age_band,
+            band.output_count,
+            format_btc(band.value_satoshis),
+            share
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the stored age distribution snapshots between `from` and `to` (inclusive), as CSV
+pub fn print_utxo_age_series(conn: &mut PgConnection, from: u32, to: u32) -> Result<()> {
+    // DB QUERY!
+    let rows = sql_query(SNAPSHOT_SERIES_QUERY)
+        .bind::<Integer, _>(from as i32)
+        .bind::<Integer, _>(to as i32)
+        .load::<UtxoAgeSnapshot>(conn)
+        .context("Failed to query UTXO age snapshots")?;
+
+    println!("height,age_band,output_count,value_satoshis");
+    for row in &rows {
+        println!(
+            "{},{},{},{}",
+            row.block_height, row.age_band, row.output_count, row.value_satoshis
+        );
+    }
+
+    Ok(())
+}
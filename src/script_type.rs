@@ -0,0 +1,243 @@
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::{Jsonb, Text};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Script types of output scripts, matching the rows of the `script_types` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum ScriptType {
+    P2pkh,
+    P2sh,
+    P2pk,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    P2ms,
+    NonStandard,
+    Unknown,
+}
+
+impl ScriptType {
+    /// Every script type, in the order they are listed in the `script_types` table
+    pub const ALL: [ScriptType; 9] = [
+        ScriptType::P2pkh,
+        ScriptType::P2sh,
+        ScriptType::P2pk,
+        ScriptType::P2wpkh,
+        ScriptType::P2wsh,
+        ScriptType::P2tr,
+        ScriptType::P2ms,
+        ScriptType::NonStandard,
+        ScriptType::Unknown,
+    ];
+
+    /// The key of this script type in the `script_types` table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptType::P2pkh => "p2pkh",
+            ScriptType::P2sh => "p2sh",
+            ScriptType::P2pk => "p2pk",
+            ScriptType::P2wpkh => "p2wpkh",
+            ScriptType::P2wsh => "p2wsh",
+            ScriptType::P2tr => "p2tr",
+            ScriptType::P2ms => "p2ms",
+            ScriptType::NonStandard => "non-standard",
+            ScriptType::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad() so that width and alignment flags are honoured in reports
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for ScriptType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScriptType::ALL
+            .into_iter()
+            .find(|t| t.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown script type '{}'", s))
+    }
+}
+
+impl ToSql<Text, Pg> for ScriptType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ScriptType {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        Ok(s.parse()?)
+    }
+}
+
+/// How an address's public key came to be exposed, stored in `addresses.public_key_exposure_source`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum ExposureSource {
+    /// The key is part of the output script itself (P2PK, P2MS)
+    OutputScript,
+    /// The key was revealed in the scriptSig of a spend
+    Spend,
+    /// The key was exposed through another address it backs (e.g. the P2PKH of a P2PK key)
+    Sibling,
+}
+
+impl ExposureSource {
+    /// The value stored in the database for this source
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExposureSource::OutputScript => "output_script",
+            ExposureSource::Spend => "spend",
+            ExposureSource::Sibling => "sibling",
+        }
+    }
+}
+
+impl fmt::Display for ExposureSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for ExposureSource {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ExposureSource {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "output_script" => Ok(ExposureSource::OutputScript),
+            "spend" => Ok(ExposureSource::Spend),
+            "sibling" => Ok(ExposureSource::Sibling),
+            other => Err(format!("Unknown public key exposure source '{}'", other).into()),
+        }
+    }
+}
+
+/// Format of a public key, stored in the P2PK extra data and the `*_format` key columns
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum PubkeyFormat {
+    Compressed,   // 33 bytes, 0x02 or 0x03 prefix
+    Uncompressed, // 65 bytes, 0x04 prefix
+    Hybrid,       // 65 bytes, 0x06 or 0x07 prefix
+    Invalid,      // Not a point on the curve
+}
+
+impl PubkeyFormat {
+    /// The value stored in the database for this format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PubkeyFormat::Compressed => "compressed",
+            PubkeyFormat::Uncompressed => "uncompressed",
+            PubkeyFormat::Hybrid => "hybrid",
+            PubkeyFormat::Invalid => "invalid",
+        }
+    }
+
+    /// Whether the key is a valid curve point, and so counts as an exposed public key
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, PubkeyFormat::Invalid)
+    }
+}
+
+impl ToSql<Text, Pg> for PubkeyFormat {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for PubkeyFormat {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "compressed" => Ok(PubkeyFormat::Compressed),
+            "uncompressed" => Ok(PubkeyFormat::Uncompressed),
+            "hybrid" => Ok(PubkeyFormat::Hybrid),
+            "invalid" => Ok(PubkeyFormat::Invalid),
+            other => Err(format!("Unknown public key format '{}'", other).into()),
+        }
+    }
+}
+
+/// Extra data stored with P2PK addresses
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct P2pkData {
+    pub pubkey_format: PubkeyFormat,
+}
+
+/// Extra data stored with bare multisig (P2MS) addresses
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct P2msData {
+    pub m: u8, // Required signatures
+    pub n: u8, // Number of public keys
+}
+
+/// Recognisable patterns of non-standard scripts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "pattern", rename_all = "kebab-case")]
+pub enum NonStandardData {
+    /// A P2PKH script followed by extra operations
+    P2pkhPlus { extra_ops: Vec<String> },
+    /// A script containing a 20-byte push, treated as a public key hash
+    Hash160Found {
+        hash_position: usize,
+        script_ops: Vec<String>,
+    },
+}
+
+/// Extra data stored with scripts that match no known pattern
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnknownScriptData {
+    pub script_pattern: Vec<String>,
+}
+
+/// Typed contents of `addresses.script_extra_data`, one variant per script type that has any.
+/// Serialised untagged so that the stored JSON keeps its existing shape.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Jsonb)]
+#[serde(untagged)]
+pub enum ScriptExtraData {
+    P2pk(P2pkData),
+    P2ms(P2msData),
+    NonStandard(NonStandardData),
+    Unknown(UnknownScriptData),
+}
+
+impl ToSql<Jsonb, Pg> for ScriptExtraData {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        // JSONB binary format: a version byte followed by the JSON text
+        out.write_all(&[1])?;
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for ScriptExtraData {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let bytes = bytes.as_bytes();
+        if bytes.first() != Some(&1) {
+            return Err("Unsupported JSONB encoding version".into());
+        }
+        Ok(serde_json::from_slice(&bytes[1..])?)
+    }
+}
@@ -0,0 +1,350 @@
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::script::Script;
+use bitcoin::Witness;
+use secp256k1::Signature;
+
+use crate::pubkey::classify_public_key;
+
+/// An ECDSA signature found in an input, with the properties recorded for auditing
+pub struct ParsedSignature {
+    pub r_value: [u8; 32],
+    pub s_value: [u8; 32],
+    pub sighash_type: u8,
+    pub is_low_r: bool, // R fits in 32 bytes without a padding byte (r < 2^255)
+    pub is_low_s: bool, // S is at most half the curve order (BIP146)
+    pub is_strict_der: bool, // Encoding follows the strict DER rules of BIP66
+}
+
+/// A signature together with where it was found in the input
+pub struct InputSignature {
+    pub signature: ParsedSignature,
+    pub signature_index: i16, // Position among the signatures of the input
+    pub in_witness: bool,
+    // The key it was paired with, when the input makes that unambiguous
+    pub public_key: Option<Vec<u8>>,
+}
+
+/// Parses a pushed signature (DER followed by the sighash byte), accepting the lax
+/// encodings that were valid before BIP66 so that their R values are still recorded
+pub fn parse_signature(push: &[u8]) -> Option<ParsedSignature> {
+    // 8 bytes of DER framing plus at least one byte each of R and S, plus the sighash byte
+    if push.len() < 9 || push.len() > 73 || push[0] != 0x30 {
+        return None;
+    }
+
+    let (der, sighash_type) = push.split_at(push.len() - 1);
+    let signature = Signature::from_der_lax(der).ok()?;
+
+    let compact = signature.serialize_compact();
+    let mut r_value = [0u8; 32];
+    let mut s_value = [0u8; 32];
+    r_value.copy_from_slice(&compact[..32]);
+    s_value.copy_from_slice(&compact[32..]);
+
+    let mut normalized = signature;
+    normalized.normalize_s();
+
+    Some(ParsedSignature {
+        r_value,
+        s_value,
+        sighash_type: sighash_type[0],
+        is_low_r: r_value[0] < 0x80,
+        is_low_s: normalized == signature,
+        is_strict_der: is_valid_signature_encoding(push),
+    })
+}
+
+/// Finds the ECDSA signatures pushed by an input's scriptSig and witness.
+/// A signature is paired with a public key for the common single key spends:
+/// `<sig> <pubkey>` in the scriptSig (P2PKH) or the witness (P2WPKH).
+pub fn extract_signatures(script_sig: &Script, witness: &Witness) -> Vec<InputSignature> {
+    let pushes: Vec<&[u8]> = script_sig
+        .instructions()
+        .filter_map(Result::ok)
+        .filter_map(|instruction| match instruction {
+            Instruction::PushBytes(bytes) => Some(bytes.as_bytes()),
+            Instruction::Op(_) => None,
+        })
+        .collect();
+    let witness_items: Vec<&[u8]> = witness.iter().collect();
+
+    let mut signatures = Vec::new();
+    for (items, in_witness) in [(&pushes, false), (&witness_items, true)] {
+        let single_key = match items.as_slice() {
+            [_, key] if classify_public_key(key).is_valid() => Some(key.to_vec()),
+            _ => None,
+        };
+
+        for item in items.iter() {
+            if let Some(signature) = parse_signature(item) {
+                signatures.push(InputSignature {
+                    signature,
+                    signature_index: signatures.len() as i16,
+                    in_witness,
+                    public_key: single_key.clone(),
+                });
+            }
+        }
+    }
+
+    signatures
+}
+
+/// Strict DER check of a signature with its sighash byte, as in Bitcoin Core's
+/// IsValidSignatureEncoding (BIP66)
+fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    // Format: 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    // R must be a positive integer without unnecessary leading zero bytes
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+
+    // Same for S
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::secp256k1::{ecdsa, Message, PublicKey, Secp256k1};
+    use bitcoin::sighash::SighashCache;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid};
+
+    /// Satoshi's key, paid by the coinbase of block 9 and spent in block 170
+    const SATOSHI_KEY: &str = "0411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3";
+    /// Hal Finney's key, paid in block 170
+    const HAL_KEY: &str = "04ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84c";
+    /// The signature of the first bitcoin transaction between people, in block 170
+    const BLOCK_170_SIGNATURE: &str = "304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901";
+    const BLOCK_170_TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+    const BLOCK_9_COINBASE_TXID: &str =
+        "0437cd7f8525ceed2324359c2d0ba26006d92d856a9c20fa0241106ee5a597c9";
+
+    fn p2pk_script(key: &str) -> ScriptBuf {
+        let key = PushBytesBuf::try_from(hex::decode(key).unwrap()).unwrap();
+        Builder::new()
+            .push_slice(key)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    fn push_script(pushes: &[&[u8]]) -> ScriptBuf {
+        pushes
+            .iter()
+            .fold(Builder::new(), |builder, push| {
+                builder.push_slice(PushBytesBuf::try_from(push.to_vec()).unwrap())
+            })
+            .into_script()
+    }
+
+    /// The block 170 transaction, rebuilt from its signature and the two keys it involves
+    fn block_170_transaction() -> Transaction {
+        Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(BLOCK_9_COINBASE_TXID.parse().unwrap(), 0),
+                script_sig: push_script(&[&hex::decode(BLOCK_170_SIGNATURE).unwrap()]),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut {
+                    value: Amount::from_btc(10.0).unwrap(),
+                    script_pubkey: p2pk_script(HAL_KEY),
+                },
+                TxOut {
+                    value: Amount::from_btc(40.0).unwrap(),
+                    script_pubkey: p2pk_script(SATOSHI_KEY),
+                },
+            ],
+        }
+    }
+
+    /// The signature of block 170 with S replaced by n - S, which is just as valid
+    fn high_s_signature() -> Vec<u8> {
+        let push = hex::decode(BLOCK_170_SIGNATURE).unwrap();
+        let mut signature = ecdsa::Signature::from_der(&push[..push.len() - 1]).unwrap();
+        let mut compact = signature.serialize_compact();
+        // n - S, computed as the negation of the low S
+        let negated = bitcoin::secp256k1::SecretKey::from_slice(&compact[32..])
+            .unwrap()
+            .negate();
+        compact[32..].copy_from_slice(&negated.secret_bytes());
+        signature = ecdsa::Signature::from_compact(&compact).unwrap();
+
+        let mut high_s = signature.serialize_der().to_vec();
+        high_s.push(0x01);
+        high_s
+    }
+
+    #[test]
+    fn block_170_signature_is_genuine() {
+        let tx = block_170_transaction();
+        assert_eq!(tx.compute_txid(), BLOCK_170_TXID.parse::<Txid>().unwrap());
+
+        let sighash = SighashCache::new(&tx)
+            .legacy_signature_hash(0, &p2pk_script(SATOSHI_KEY), 0x01)
+            .unwrap();
+        let push = hex::decode(BLOCK_170_SIGNATURE).unwrap();
+        let signature = ecdsa::Signature::from_der(&push[..push.len() - 1]).unwrap();
+        let key = PublicKey::from_slice(&hex::decode(SATOSHI_KEY).unwrap()).unwrap();
+        Secp256k1::verification_only()
+            .verify_ecdsa(
+                &Message::from_digest(sighash.to_byte_array()),
+                &signature,
+                &key,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn parses_block_170_signature() {
+        let signature = parse_signature(&hex::decode(BLOCK_170_SIGNATURE).unwrap()).unwrap();
+
+        assert_eq!(
+            hex::encode(signature.r_value),
+            "4e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd41"
+        );
+        assert_eq!(
+            hex::encode(signature.s_value),
+            "181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d09"
+        );
+        assert_eq!(signature.sighash_type, 0x01);
+        assert!(signature.is_low_r);
+        assert!(signature.is_low_s);
+        assert!(signature.is_strict_der);
+    }
+
+    #[test]
+    fn flags_high_s() {
+        let high_s = high_s_signature();
+        let signature = parse_signature(&high_s).unwrap();
+
+        assert!(!signature.is_low_s);
+        // A high S needs a padding byte, but is still strict DER
+        assert_eq!(high_s[37], 0x21);
+        assert!(signature.is_strict_der);
+        assert_eq!(
+            hex::encode(signature.r_value),
+            "4e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd41"
+        );
+    }
+
+    #[test]
+    fn accepts_lax_der_from_before_bip66() {
+        // The block 170 signature with R padded by a needless zero byte, which BIP66 made invalid
+        let mut padded = hex::decode(BLOCK_170_SIGNATURE).unwrap();
+        padded[1] += 1;
+        padded[3] += 1;
+        padded.insert(4, 0x00);
+
+        assert!(!is_valid_signature_encoding(&padded));
+        let signature = parse_signature(&padded).unwrap();
+        assert!(!signature.is_strict_der);
+        assert_eq!(
+            hex::encode(signature.r_value),
+            "4e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd41"
+        );
+    }
+
+    #[test]
+    fn flags_high_r() {
+        // Made up R and S values: a high R is padded to 33 bytes,
+        // 0x30 0x45 0x02 0x21 0x00 <R> 0x02 0x20 <S> <sighash>
+        let r = [0x80; 32];
+        let s = [0x11; 32];
+        let mut push = vec![0x30, 0x45, 0x02, 0x21, 0x00];
+        push.extend_from_slice(&r);
+        push.extend_from_slice(&[0x02, 0x20]);
+        push.extend_from_slice(&s);
+        push.push(0x01);
+
+        let signature = parse_signature(&push).unwrap();
+        assert_eq!(signature.r_value, r);
+        assert!(!signature.is_low_r);
+        assert!(signature.is_strict_der);
+    }
+
+    #[test]
+    fn rejects_pushes_that_are_not_signatures() {
+        assert!(parse_signature(&hex::decode(SATOSHI_KEY).unwrap()).is_none());
+        assert!(parse_signature(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01]).is_none());
+    }
+
+    #[test]
+    fn pairs_signatures_with_keys_for_nonce_reuse() {
+        let signature = hex::decode(BLOCK_170_SIGNATURE).unwrap();
+        let key = hex::decode(SATOSHI_KEY).unwrap();
+
+        // Two P2PKH-style inputs by the same key with the same R are grouped together
+        let first = extract_signatures(&push_script(&[&signature, &key]), &Witness::new());
+        let second =
+            extract_signatures(&push_script(&[&high_s_signature(), &key]), &Witness::new());
+        let grouping_key =
+            |s: &InputSignature| (s.public_key.clone(), s.signature.r_value.to_vec());
+        assert_eq!(first.len(), 1);
+        assert_eq!(grouping_key(&first[0]), grouping_key(&second[0]));
+        assert_eq!(first[0].public_key.as_deref(), Some(key.as_slice()));
+
+        // A P2PK input has no key of its own, it comes from the spent address
+        let p2pk = extract_signatures(
+            &block_170_transaction().input[0].script_sig,
+            &Witness::new(),
+        );
+        assert_eq!(p2pk.len(), 1);
+        assert_eq!(p2pk[0].public_key, None);
+
+        // Nor do the signatures of a multisig input, which are indexed in order
+        let multisig = extract_signatures(
+            &push_script(&[&[], &signature, &high_s_signature()]),
+            &Witness::new(),
+        );
+        assert_eq!(multisig.len(), 2);
+        assert_eq!(multisig[1].signature_index, 1);
+        assert!(multisig
+            .iter()
+            .all(|s| s.public_key.is_none() && !s.in_witness));
+
+        // Witness signatures are paired with the witness key (P2WPKH)
+        let compressed_key = PublicKey::from_slice(&key).unwrap().serialize();
+        let witness = Witness::from_slice(&[signature.clone(), compressed_key.to_vec()]);
+        let p2wpkh = extract_signatures(ScriptBuf::new().as_script(), &witness);
+        assert_eq!(p2wpkh.len(), 1);
+        assert!(p2wpkh[0].in_witness);
+        assert_eq!(
+            p2wpkh[0].public_key.as_deref(),
+            Some(compressed_key.as_slice())
+        );
+    }
+}
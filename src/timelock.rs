@@ -0,0 +1,294 @@
+use bitcoin::absolute::LockTime;
+use bitcoin::transaction::Version;
+use bitcoin::{Sequence, Transaction};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use std::fmt;
+use std::io::Write;
+
+/// Wallets setting the locktime against fee sniping use the current tip height,
+/// or occasionally a height up to this many blocks earlier (Bitcoin Core)
+const ANTI_FEE_SNIPING_WINDOW: u32 = 100;
+
+/// How a transaction's locktime is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum LockTimeKind {
+    /// A locktime of zero, no restriction
+    None,
+    /// Below 500,000,000: the block height the transaction can be mined after
+    Height,
+    /// From 500,000,000: the UNIX time the transaction can be mined after
+    Time,
+}
+
+impl LockTimeKind {
+    /// The value stored in the database for this kind of locktime
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockTimeKind::None => "none",
+            LockTimeKind::Height => "height",
+            LockTimeKind::Time => "time",
+        }
+    }
+}
+
+impl fmt::Display for LockTimeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for LockTimeKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for LockTimeKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "none" => Ok(LockTimeKind::None),
+            "height" => Ok(LockTimeKind::Height),
+            "time" => Ok(LockTimeKind::Time),
+            other => Err(format!("Unknown locktime kind '{}'", other).into()),
+        }
+    }
+}
+
+/// Unit of a BIP68 relative timelock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum RelativeLockKind {
+    /// Blocks since the spent output was confirmed
+    Blocks,
+    /// Intervals of 512 seconds since the spent output was confirmed
+    Time,
+}
+
+impl RelativeLockKind {
+    /// The value stored in the database for this kind of relative timelock
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelativeLockKind::Blocks => "blocks",
+            RelativeLockKind::Time => "time",
+        }
+    }
+}
+
+impl fmt::Display for RelativeLockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl ToSql<Text, Pg> for RelativeLockKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for RelativeLockKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match std::str::from_utf8(bytes.as_bytes())? {
+            "blocks" => Ok(RelativeLockKind::Blocks),
+            "time" => Ok(RelativeLockKind::Time),
+            other => Err(format!("Unknown relative timelock kind '{}'", other).into()),
+        }
+    }
+}
+
+/// Version, locktime and RBF metadata of a transaction
+pub struct TransactionTimelocks {
+    pub version: i32,
+    pub lock_time: u32,
+    pub lock_time_kind: LockTimeKind,
+    // A locktime only applies if some input has a non-final sequence, otherwise it is ignored
+    pub is_lock_time_enforced: bool,
+    pub is_anti_fee_sniping: bool,
+    pub signals_rbf: bool, // BIP125: some input has a sequence below 0xfffffffe
+}
+
+impl TransactionTimelocks {
+    /// Classifies the locktime and sequences of a transaction mined at `height`
+    pub fn new(tx: &Transaction, height: u32) -> Self {
+        let lock_time = tx.lock_time.to_consensus_u32();
+        let lock_time_kind = match tx.lock_time {
+            _ if lock_time == 0 => LockTimeKind::None,
+            LockTime::Blocks(_) => LockTimeKind::Height,
+            LockTime::Seconds(_) => LockTimeKind::Time,
+        };
+        let is_coinbase = tx.is_coinbase();
+        let is_lock_time_enforced =
+            lock_time_kind != LockTimeKind::None && tx.is_lock_time_enabled();
+
+        // A height locktime just below the block it was mined in, set by the wallet to the tip
+        // height so that the transaction cannot be included in a reorganisation of older blocks
+        let is_anti_fee_sniping = !is_coinbase
+            && is_lock_time_enforced
+            && lock_time_kind == LockTimeKind::Height
+            && lock_time < height
+            && height - lock_time <= ANTI_FEE_SNIPING_WINDOW;
+
+        Self {
+            version: tx.version.0,
+            lock_time,
+            lock_time_kind,
+            is_lock_time_enforced,
+            is_anti_fee_sniping,
+            signals_rbf: !is_coinbase && tx.input.iter().any(|i| i.sequence.is_rbf()),
+        }
+    }
+}
+
+/// A BIP68 relative timelock set by an input's sequence
+pub struct RelativeLock {
+    pub kind: RelativeLockKind,
+    pub value: u16, // Blocks, or 512 second intervals
+}
+
+/// The sequence of an input and the relative timelock it sets, if any
+pub struct InputSequence {
+    pub sequence: u32,
+    pub relative_lock: Option<RelativeLock>,
+}
+
+impl InputSequence {
+    /// Decodes the sequence of an input of a transaction with the given version.
+    /// Relative timelocks only apply to version 2+ transactions without the disable flag set;
+    /// the version is compared unsigned, as consensus does, so negative versions count too.
+    pub fn new(tx_version: Version, sequence: Sequence) -> Self {
+        let relative_lock = if tx_version.0 as u32 >= 2 && sequence.is_relative_lock_time() {
+            let kind = if sequence.is_time_locked() {
+                RelativeLockKind::Time
+            } else {
+                RelativeLockKind::Blocks
+            };
+            Some(RelativeLock {
+                kind,
+                value: (sequence.to_consensus_u32() & 0xffff) as u16,
+            })
+        } else {
+            None
+        };
+
+        Self {
+            sequence: sequence.to_consensus_u32(),
+            relative_lock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{OutPoint, ScriptBuf, TxIn, Txid, Witness};
+
+    /// A transaction with one input of each of the given sequences
+    fn transaction(version: i32, lock_time: u32, sequences: &[u32]) -> Transaction {
+        Transaction {
+            version: Version(version),
+            lock_time: LockTime::from_consensus(lock_time),
+            input: sequences
+                .iter()
+                .enumerate()
+                .map(|(i, sequence)| TxIn {
+                    previous_output: OutPoint::new(Txid::from_byte_array([1; 32]), i as u32),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence(*sequence),
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn anti_fee_sniping_window() {
+        let height = 800_000;
+        for (lock_time, expected) in [
+            (799_999, true), // The tip the wallet saw
+            (799_900, true), // The furthest Core randomly sets it back
+            (799_899, false),
+            (800_000, false), // Not below the block it was mined in
+        ] {
+            let timelocks =
+                TransactionTimelocks::new(&transaction(2, lock_time, &[0xffff_fffe]), height);
+            assert_eq!(timelocks.lock_time_kind, LockTimeKind::Height);
+            assert!(timelocks.is_lock_time_enforced);
+            assert_eq!(timelocks.is_anti_fee_sniping, expected, "{}", lock_time);
+        }
+
+        // Only enforced locktimes count: final sequences disable the locktime
+        let timelocks = TransactionTimelocks::new(&transaction(2, 799_999, &[0xffff_ffff]), height);
+        assert!(!timelocks.is_lock_time_enforced);
+        assert!(!timelocks.is_anti_fee_sniping);
+
+        // Time locktimes are never anti fee sniping
+        let timelocks =
+            TransactionTimelocks::new(&transaction(2, 1_700_000_000, &[0xffff_fffe]), height);
+        assert_eq!(timelocks.lock_time_kind, LockTimeKind::Time);
+        assert!(!timelocks.is_anti_fee_sniping);
+    }
+
+    #[test]
+    fn rbf_threshold() {
+        for (sequence, expected) in [
+            (0xffff_ffff, false),
+            (0xffff_fffe, false),
+            (0xffff_fffd, true),
+            (0, true),
+        ] {
+            let timelocks = TransactionTimelocks::new(&transaction(1, 0, &[sequence]), 1);
+            assert_eq!(timelocks.signals_rbf, expected, "{:#x}", sequence);
+        }
+
+        // Any input signalling is enough
+        let timelocks =
+            TransactionTimelocks::new(&transaction(1, 0, &[0xffff_ffff, 0xffff_fffd]), 1);
+        assert!(timelocks.signals_rbf);
+    }
+
+    #[test]
+    fn relative_lock_in_blocks_or_time() {
+        let sequence = InputSequence::new(Version::TWO, Sequence(144));
+        let lock = sequence.relative_lock.unwrap();
+        assert_eq!(lock.kind, RelativeLockKind::Blocks);
+        assert_eq!(lock.value, 144);
+
+        // The type flag (bit 22) makes it 512 second intervals
+        let sequence = InputSequence::new(Version::TWO, Sequence((1 << 22) | 1_000));
+        let lock = sequence.relative_lock.unwrap();
+        assert_eq!(lock.kind, RelativeLockKind::Time);
+        assert_eq!(lock.value, 1_000);
+
+        // Bits outside the flags and the 16 bit value are ignored
+        let sequence = InputSequence::new(Version::TWO, Sequence((1 << 16) | 10));
+        assert_eq!(sequence.relative_lock.unwrap().value, 10);
+    }
+
+    #[test]
+    fn relative_lock_disabled() {
+        // The disable flag (bit 31)
+        let sequence = InputSequence::new(Version::TWO, Sequence((1 << 31) | 144));
+        assert!(sequence.relative_lock.is_none());
+        assert_eq!(sequence.sequence, (1 << 31) | 144);
+
+        // Version 1 transactions have no relative timelocks
+        let sequence = InputSequence::new(Version::ONE, Sequence(144));
+        assert!(sequence.relative_lock.is_none());
+
+        // Negative versions are at least 2 when read unsigned, as consensus does
+        let sequence = InputSequence::new(Version(-1), Sequence(144));
+        assert_eq!(
+            sequence.relative_lock.unwrap().kind,
+            RelativeLockKind::Blocks
+        );
+    }
+}
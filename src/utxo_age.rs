@@ -0,0 +1,71 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer};
+
+/// Blocks between snapshots of the age distribution (about a day)
+pub const SNAPSHOT_INTERVAL: u32 = 144;
+
+/// Age bands of the unspent set with the age (in days) each ends before; the last is open ended
+pub const AGE_BANDS: [(&str, Option<i32>); 12] = [
+    ("<1d", Some(1)),
+    ("1d-1w", Some(7)),
+    ("1w-1m", Some(30)),
+    ("1m-3m", Some(91)),
+    ("3m-6m", Some(182)),
+    ("6m-1y", Some(365)),
+    ("1y-2y", Some(730)),
+    ("2y-3y", Some(1_095)),
+    ("3y-5y", Some(1_826)),
+    ("5y-7y", Some(2_556)),
+    ("7y-10y", Some(3_652)),
+    ("10y+", None),
+];
+
+/// Unspent outputs created on one day, aged in whole days at the height they are looked at
+#[derive(QueryableByName, Debug)]
+pub struct UtxoAgeDay {
+    #[diesel(sql_type = Integer)]
+    pub age_days: i32,
+    #[diesel(sql_type = BigInt)]
+    pub output_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub value_satoshis: i64,
+}
+
+/// Unspent outputs in one age band
+pub struct AgeBandTotals {
+    pub band_index: usize,
+    pub age_band: &'static str,
+    pub output_count: i64,
+    pub value_satoshis: i64,
+}
+
+/// Sums the unspent outputs of each creation day into the age bands, youngest first.
+/// Every band is returned, empty ones included, so that snapshots line up.
+pub fn totals_by_age_band(days: &[UtxoAgeDay]) -> Vec<AgeBandTotals> {
+    let mut bands: Vec<AgeBandTotals> = AGE_BANDS
+        .iter()
+        .enumerate()
+        .map(|(band_index, (age_band, _))| AgeBandTotals {
+            band_index,
+            age_band,
+            output_count: 0,
+            value_satoshis: 0,
+        })
+        .collect();
+
+    for day in days {
+        let band = &mut bands[age_band_index(day.age_days)];
+        band.output_count += day.output_count;
+        band.value_satoshis += day.value_satoshis;
+    }
+
+    bands
+}
+
+/// The band an age in days falls in
+fn age_band_index(age_days: i32) -> usize {
+    AGE_BANDS
+        .iter()
+        .position(|(_, ends_before)| ends_before.is_none_or(|days| age_days < days))
+        .unwrap_or(AGE_BANDS.len() - 1)
+}